@@ -1,5 +1,14 @@
-use rustracer::utils::init_headless_from_path;
-use std::{path::Path, time::Duration};
+use rustracer::{
+    camera::Camera,
+    pattern::Pattern,
+    ray::Ray,
+    shape::object::Object,
+    transformation::{create_scaling, create_translation, view_transform},
+    tuple::Tuple,
+    utils::init_headless_from_path,
+    world::World,
+};
+use std::{hint::black_box, path::Path, time::Duration};
 
 use criterion::{Criterion, criterion_group, criterion_main};
 
@@ -39,6 +48,59 @@ fn criterion_benchmark_11_c(c: &mut Criterion) {
     });
 }
 
+// Hot paths using the inverse of the transformations, on every ray
+fn criterion_benchmark_inverse(c: &mut Criterion) {
+    let world = World::default_world();
+    let ray = Ray::new(
+        Tuple::new_point(0.0, 0.0, -5.0),
+        Tuple::new_vector(0.0, 0.0, 1.0),
+    );
+    c.bench_function("intersect world", |b| {
+        b.iter(|| black_box(&world).intersect_world(black_box(&ray)))
+    });
+    c.bench_function("world hit", |b| {
+        b.iter(|| black_box(&world).hit(black_box(&ray)))
+    });
+    let point = Tuple::new_point(10.0, -10.0, 10.0);
+    c.bench_function("is shadowed", |b| {
+        b.iter(|| {
+            black_box(&world).is_shadowed_for_light(black_box(&point), &world.light_sources[0])
+        })
+    });
+
+    let camera = Camera::new(201, 101, 1.5).with_transformation(view_transform(
+        &Tuple::new_point(0.0, 1.5, -5.0),
+        &Tuple::new_point(0.0, 1.0, 0.0),
+        &Tuple::new_vector(0.0, 1.0, 0.0),
+    ));
+    c.bench_function("ray for pixel", |b| {
+        b.iter(|| black_box(&camera).ray_for_pixel(black_box(100), black_box(50)))
+    });
+
+    let mut object = Object::new_sphere();
+    object.set_transform(&create_scaling(2.0, 2.0, 2.0));
+    let mut pattern = Pattern::new_stripe_pattern(vec![
+        rustracer::color::WHITE,
+        rustracer::color::BLACK,
+    ]);
+    pattern.set_transform(&create_translation(0.5, 0.0, 0.0));
+    let point = Tuple::new_point(1.5, 0.0, 0.0);
+    c.bench_function("pattern color at object", |b| {
+        b.iter(|| black_box(&pattern).color_at_object(black_box(&object), point.clone()))
+    });
+
+    let normal_point = Tuple::new_point(0.0, 2.0, 0.0);
+    c.bench_function("normal at", |b| {
+        b.iter(|| black_box(&object).normal_at(normal_point.clone()))
+    });
+}
+
+criterion_group!(
+    name = hot_paths;
+    config = Criterion::default().measurement_time(Duration::from_secs(5));
+    targets = criterion_benchmark_inverse
+);
+
 criterion_group!(
    name =  benches;
      config = Criterion::default().sample_size(20).measurement_time(Duration::from_secs(100));
@@ -50,4 +112,4 @@ criterion_group!(
     criterion_benchmark_11_b,
     criterion_benchmark_11_c
 );
-criterion_main!(benches);
+criterion_main!(hot_paths, benches);
@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+/// How a value goes from one keyframe to the next
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    /// Constant speed
+    Linear,
+    /// Easing in and out of each keyframe, stopping there
+    Smooth,
+}
+
+impl Interpolation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Interpolation::Linear => "linear",
+            Interpolation::Smooth => "smooth",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Interpolation> {
+        [Interpolation::Linear, Interpolation::Smooth]
+            .into_iter()
+            .find(|interpolation| interpolation.name() == name)
+    }
+
+    /// Weight of the next keyframe when `t` of the way between two of them
+    pub fn factor(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Interpolation::Linear => t,
+            Interpolation::Smooth => t * t * (3.0 - 2.0 * t),
+        }
+    }
+
+    /// Keyframes on both sides of `time` and the weight of the second one, `times` being
+    /// sorted. Before the first keyframe and after the last one the values stay still.
+    pub fn segment(&self, times: &[f64], time: f64) -> (usize, usize, f64) {
+        let next = times.iter().position(|key| *key > time);
+        match next {
+            Some(0) => (0, 0, 0.0),
+            None => (times.len() - 1, times.len() - 1, 0.0),
+            Some(next) => {
+                let previous = next - 1;
+                let t = (time - times[previous]) / (times[next] - times[previous]);
+                (previous, next, self.factor(t))
+            }
+        }
+    }
+}
+
+/// Frames of an animated scene
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animation {
+    pub frames: usize,
+    /// Frames per second, the keyframes being placed in seconds
+    pub fps: f64,
+}
+
+impl Animation {
+    pub fn new_animation(frames: usize, fps: f64) -> Animation {
+        Animation { frames, fps }
+    }
+
+    /// Time of `frame` in seconds, the first frame being at 0
+    pub fn time(&self, frame: usize) -> f64 {
+        frame as f64 / self.fps
+    }
+
+    /// Path of the image of `frame` in the sequence named after `output`, `render.ppm`
+    /// giving `render.0012.ppm`
+    pub fn frame_path(&self, output: &Path, frame: usize) -> PathBuf {
+        let mut name = output.file_stem().unwrap_or_default().to_os_string();
+        name.push(format!(".{:04}", frame));
+        if let Some(extension) = output.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+        output.with_file_name(name)
+    }
+}
+
+#[cfg(test)]
+mod animation_tests {
+    use super::*;
+    use crate::utils::compare_float;
+
+    #[test]
+    // Smooth interpolation eases in and out of the keyframes
+    fn interpolation_factor() {
+        assert_eq!(Interpolation::Linear.factor(0.25), 0.25);
+        assert_eq!(Interpolation::Smooth.factor(0.5), 0.5);
+        assert!(compare_float(Interpolation::Smooth.factor(0.25), 0.15625));
+        assert_eq!(Interpolation::Smooth.factor(1.5), 1.0);
+        assert_eq!(
+            Interpolation::from_name("smooth"),
+            Some(Interpolation::Smooth)
+        );
+    }
+
+    #[test]
+    // The values hold before the first keyframe and after the last one
+    fn interpolation_segment() {
+        let times = [0.0, 1.0, 3.0];
+        let linear = Interpolation::Linear;
+        assert_eq!(linear.segment(&times, -1.0), (0, 0, 0.0));
+        assert_eq!(linear.segment(&times, 0.0), (0, 1, 0.0));
+        assert_eq!(linear.segment(&times, 2.5), (1, 2, 0.75));
+        assert_eq!(linear.segment(&times, 3.0), (2, 2, 0.0));
+        assert_eq!(linear.segment(&times, 4.0), (2, 2, 0.0));
+    }
+
+    #[test]
+    // Frames are numbered in the name of the image
+    fn animation_frames() {
+        let animation = Animation::new_animation(48, 24.0);
+        assert_eq!(animation.time(36), 1.5);
+        assert_eq!(
+            animation.frame_path(Path::new("out/render.ppm"), 12),
+            Path::new("out/render.0012.ppm")
+        );
+    }
+}
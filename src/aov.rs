@@ -0,0 +1,397 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    camera::{Camera, Integrator},
+    canvas::Canvas,
+    color::{self, Color},
+    inspection::Recorder,
+    ray::Ray,
+    reflection::{MAX_RECURTION, PointLight},
+    stereo::StereoLayout,
+    tuple::Tuple,
+    world::{Computation, World},
+};
+
+/// Arbitrary output variable: one part of the color of the hit of each camera ray, written
+/// in its own image for compositing. The diffuse, specular, reflection, refraction and
+/// emission images add up to the Whitted render, without the media and the fog.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aov {
+    /// Direct light scattered by the surface, ambient included
+    Diffuse,
+    /// Highlights of the lights
+    Specular,
+    /// Reflected color, weighted by the Fresnel reflectance on transparent surfaces
+    Reflection,
+    /// Refracted color, weighted like the reflection
+    Refraction,
+    Emission,
+    /// Share of the light blocked, white in full shadow
+    Shadow,
+    /// Distance to the hit in every channel, 0 where the ray misses
+    Depth,
+    /// Shading normal in the red, green and blue channels
+    Normal,
+}
+
+pub const AOVS: [Aov; 8] = [
+    Aov::Diffuse,
+    Aov::Specular,
+    Aov::Reflection,
+    Aov::Refraction,
+    Aov::Emission,
+    Aov::Shadow,
+    Aov::Depth,
+    Aov::Normal,
+];
+
+impl Aov {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Diffuse => "diffuse",
+            Aov::Specular => "specular",
+            Aov::Reflection => "reflection",
+            Aov::Refraction => "refraction",
+            Aov::Emission => "emission",
+            Aov::Shadow => "shadow",
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Aov> {
+        AOVS.into_iter().find(|aov| aov.name() == name)
+    }
+
+    /// Path of the image of the variable next to `beauty`, `render.ppm` giving
+    /// `render.depth.ppm`
+    pub fn path(&self, beauty: &Path) -> PathBuf {
+        let mut name = beauty.file_stem().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(self.name());
+        if let Some(extension) = beauty.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+        beauty.with_file_name(name)
+    }
+}
+
+/// Every variable of the hit of a camera ray, black where it misses
+#[derive(Debug, Clone, PartialEq)]
+pub struct AovSample {
+    pub diffuse: Color,
+    pub specular: Color,
+    pub reflection: Color,
+    pub refraction: Color,
+    pub emission: Color,
+    pub shadow: Color,
+    pub depth: f64,
+    pub normal: Tuple,
+    /// Color of the ray, as given by `World::color_at`
+    pub color: Color,
+}
+
+impl AovSample {
+    fn new_aov_sample() -> AovSample {
+        AovSample {
+            diffuse: color::BLACK,
+            specular: color::BLACK,
+            reflection: color::BLACK,
+            refraction: color::BLACK,
+            emission: color::BLACK,
+            shadow: color::BLACK,
+            depth: 0.0,
+            normal: Tuple::new_vector(0.0, 0.0, 0.0),
+            color: color::BLACK,
+        }
+    }
+
+    /// Adds the variables of `other` weighted by `weight`
+    fn accumulate(&mut self, other: &AovSample, weight: f64) {
+        self.diffuse += other.diffuse * weight;
+        self.specular += other.specular * weight;
+        self.reflection += other.reflection * weight;
+        self.refraction += other.refraction * weight;
+        self.emission += other.emission * weight;
+        self.shadow += other.shadow * weight;
+        self.depth += other.depth * weight;
+        self.normal = self.normal.clone() + other.normal.clone() * weight;
+        self.color += other.color * weight;
+    }
+
+    pub fn value(&self, aov: Aov) -> Color {
+        match aov {
+            Aov::Diffuse => self.diffuse,
+            Aov::Specular => self.specular,
+            Aov::Reflection => self.reflection,
+            Aov::Refraction => self.refraction,
+            Aov::Emission => self.emission,
+            Aov::Shadow => self.shadow,
+            Aov::Depth => Color::new_color(self.depth, self.depth, self.depth),
+            Aov::Normal => Color::new_color(self.normal.x, self.normal.y, self.normal.z),
+        }
+    }
+}
+
+/// Recorder splitting the color of the camera ray, the bounces only counting through the
+/// reflected and refracted colors
+struct AovRecorder {
+    /// Number of rays being traced, 1 while the camera ray is shaded
+    depth: usize,
+    lights: usize,
+    sample: AovSample,
+}
+
+impl Recorder for AovRecorder {
+    fn ray(&mut self, _ray: &Ray) {
+        self.depth += 1;
+    }
+
+    fn hit(&mut self, comps: &Computation) {
+        if self.depth == 1 {
+            self.sample.emission = comps.object.get_material().emission;
+            self.sample.depth = comps.t;
+            self.sample.normal = comps.normalv.clone();
+        }
+    }
+
+    fn light(&mut self, _light: &PointLight, visibility: Color) {
+        if self.depth == 1 {
+            self.lights += 1;
+            self.sample.shadow += color::WHITE - visibility;
+        }
+    }
+
+    fn lit(&mut self, diffuse: Color, specular: Color) {
+        if self.depth == 1 {
+            self.sample.diffuse += diffuse;
+            self.sample.specular += specular;
+        }
+    }
+
+    fn shaded(
+        &mut self,
+        _surface: Color,
+        reflected: Color,
+        refracted: Color,
+        reflectance: Option<f64>,
+    ) {
+        if self.depth == 1 {
+            let (reflected, refracted) = match reflectance {
+                Some(reflectance) => (reflected * reflectance, refracted * (1.0 - reflectance)),
+                None => (reflected, refracted),
+            };
+            self.sample.reflection = reflected;
+            self.sample.refraction = refracted;
+        }
+    }
+
+    fn color(&mut self, color: Color) {
+        if self.depth == 1 {
+            self.sample.color = color;
+        }
+        self.depth -= 1;
+    }
+}
+
+impl World {
+    /// Traces `ray` like `color_at`, keeping the parts of the color of its hit apart
+    pub fn aovs_at(&self, ray: &Ray) -> AovSample {
+        let mut recorder = AovRecorder {
+            depth: 0,
+            lights: 0,
+            sample: AovSample::new_aov_sample(),
+        };
+        self.color_at_with(ray, MAX_RECURTION, &mut recorder);
+
+        let mut sample = recorder.sample;
+        if recorder.lights > 0 {
+            sample.shadow = sample.shadow * (1.0 / recorder.lights as f64);
+        }
+        sample
+    }
+}
+
+impl Camera {
+    /// Color of the pixel with the variables of its hit, averaged over the shutter like the
+    /// color. The color of the Whitted integrator is the one of the rays traced for the
+    /// variables, the other integrators and the anaglyphs tracing it on their own.
+    pub(crate) fn color_and_aovs_at(
+        &self,
+        world: &World,
+        col: usize,
+        row: usize,
+    ) -> (Color, AovSample) {
+        let (lens, x, y) = self.lens_for_pixel(col, row);
+        let mut sum = AovSample::new_aov_sample();
+        let mut count = 0;
+        let color = self.over_shutter(|time| {
+            let sample = world.aovs_at(&lens.ray_for_pixel(x, y).with_time(time));
+            sum.accumulate(&sample, 1.0);
+            count += 1;
+            sample.color
+        });
+
+        let mut sample = AovSample::new_aov_sample();
+        sample.accumulate(&sum, 1.0 / count as f64);
+        let shared = self.integrator == Integrator::Whitted
+            && self
+                .stereo
+                .is_none_or(|stereo| stereo.layout != StereoLayout::Anaglyph);
+        if shared {
+            (color, sample)
+        } else {
+            (self.color_at(world, col, row), sample)
+        }
+    }
+}
+
+/// Image of `aov` that can be viewed, the normals mapped from `[-1, 1]` to `[0, 1]` and the
+/// depth from white at the camera to black at the farthest hit
+pub fn displayable(aov: Aov, image: &Canvas) -> Canvas {
+    let mut image = image.clone();
+    match aov {
+        Aov::Normal => {
+            for pixel in image.pixels().iter_mut() {
+                if *pixel != color::BLACK {
+                    *pixel = (*pixel + color::WHITE) * 0.5;
+                }
+            }
+        }
+        Aov::Depth => {
+            let far = image
+                .pixels
+                .iter()
+                .map(|pixel| pixel.max_component())
+                .fold(0.0, f64::max);
+            for pixel in image.pixels().iter_mut() {
+                if *pixel != color::BLACK {
+                    *pixel = color::WHITE - *pixel * (1.0 / far);
+                }
+            }
+        }
+        _ => {}
+    }
+    image
+}
+
+#[cfg(test)]
+mod aov_tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::{
+        reflection::Material,
+        shape::object::Object,
+        tile::Tile,
+        transformation::{create_translation, view_transform},
+        utils,
+    };
+
+    #[test]
+    // The parts of the color add up to it
+    fn aovs_sum() {
+        let mut w = World::default_world();
+        let mut floor = Object::new_plane();
+        floor.set_transform(&create_translation(0.0, -1.0, 0.0));
+        floor.material = Material::default_material()
+            .with_reflective(0.5)
+            .with_transparency(0.5)
+            .with_refractive_index(1.5)
+            .with_emission(Color::new_color(0.1, 0.0, 0.0));
+        w.add_object(floor);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -3.0),
+            Tuple::new_vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let sample = w.aovs_at(&r);
+        assert_eq!(sample.color, w.color_at(&r, MAX_RECURTION));
+        assert_eq!(
+            sample.diffuse
+                + sample.specular
+                + sample.reflection
+                + sample.refraction
+                + sample.emission,
+            sample.color
+        );
+        assert_eq!(sample.emission, Color::new_color(0.1, 0.0, 0.0));
+        assert_ne!(sample.reflection, color::BLACK);
+        assert!(utils::compare_float(sample.depth, 2.0_f64.sqrt()));
+        assert_eq!(sample.normal, Tuple::new_vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    // The shadow mask is white where the light is blocked, the misses are black
+    fn aovs_shadow() {
+        let w = World::default_world();
+        let lit = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(w.aovs_at(&lit).shadow, color::BLACK);
+
+        // the back of the outer sphere, seen from inside, is hidden from the light
+        let shadowed = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.75),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let sample = w.aovs_at(&shadowed);
+        assert_eq!(sample.shadow, color::WHITE);
+        assert_eq!(sample.specular, color::BLACK);
+
+        let miss = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        assert_eq!(w.aovs_at(&miss), AovSample::new_aov_sample());
+    }
+
+    #[test]
+    // Each requested variable gets its image, written next to the beauty image
+    fn render_aovs() {
+        let w = World::default_world();
+        let from = Tuple::new_point(0.0, 0.0, -5.0);
+        let to = Tuple::new_point(0.0, 0.0, 0.0);
+        let up = Tuple::new_vector(0.0, 1.0, 0.0);
+        let c = Camera::new(11, 11, PI / 2.0)
+            .with_transformation(view_transform(&from, &to, &up))
+            .with_tile_size(4);
+        let reference = c.render(&w);
+
+        // the beauty image comes from the rays traced for the variables
+        let c = c.with_aovs(vec![Aov::Depth, Aov::Normal]);
+        let (image, images) = c.render_resuming(&w, &[], |p| {
+            assert_eq!(p.pixels.len(), 3 * p.tile.pixel_count());
+        });
+        assert_eq!(image.pixels, reference.pixels);
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].pixel_at(5, 5), Color::new_color(4.0, 4.0, 4.0));
+        assert_eq!(images[1].pixel_at(5, 5), Color::new_color(0.0, 0.0, -1.0));
+        assert_eq!(
+            displayable(Aov::Normal, &images[1]).pixel_at(5, 5),
+            Color::new_color(0.5, 0.5, 0.0)
+        );
+        assert_eq!(
+            displayable(Aov::Depth, &images[0]).pixel_at(0, 0),
+            color::BLACK
+        );
+
+        // the variables of the tiles done are taken from their layers
+        let done = vec![(Tile::new_tile(4, 4, 4, 4), vec![color::WHITE; 3 * 16])];
+        let (image, images) = c.render_resuming(&w, &done, |_| {});
+        assert_eq!(image.pixel_at(5, 5), color::WHITE);
+        assert_eq!(images[0].pixel_at(5, 5), color::WHITE);
+        assert_eq!(images[1].pixel_at(5, 5), color::WHITE);
+        assert_eq!(images[0].pixel_at(0, 0), color::BLACK);
+
+        assert_eq!(
+            Aov::Depth.path(Path::new("out/render.ppm")),
+            Path::new("out/render.depth.ppm")
+        );
+        for aov in AOVS {
+            assert_eq!(Aov::from_name(aov.name()), Some(aov));
+        }
+    }
+}
@@ -1,33 +1,183 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    f64::consts::PI,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
-use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use rayon::prelude::*;
-use serde_yaml::with;
 
-use crate::matrix::memoized_inverse;
-use crate::transformation;
 use crate::{
+    aov::Aov,
     canvas::Canvas,
     color::{self, Color},
-    matrix::Matrix,
+    debug::DebugMode,
+    matrix::Matrix4,
+    motion::Shutter,
+    occlusion::AmbientOcclusion,
     ray::Ray,
     reflection,
+    stereo::{Stereo, StereoLayout, anaglyph},
+    tile::{self, Tile, TileOrder},
+    tonemap::ToneMapping,
+    transformation::view_transform,
     tuple::Tuple,
-    utils,
     world::World,
 };
 
+/// Algorithm computing the color of the pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Integrator {
+    /// Recursive ray tracing of the reflections and refractions
+    Whitted,
+    /// Average of `samples` jittered Monte Carlo paths per pixel
+    PathTracing { samples: usize },
+    /// Grayscale image of the ambient occlusion
+    AmbientOcclusion(AmbientOcclusion),
+    /// False color image of a property of the hits
+    Debug(DebugMode),
+}
+
+/// How the pixels are turned into the rays leaving the camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Rays from the eye through an image plane spanning `field_of_view`
+    Perspective,
+    /// Parallel rays leaving an image plane `view_width` wide
+    Orthographic { view_width: f64 },
+    /// Longitude across the width and latitude across the height, covering every direction
+    /// on a 2:1 image. The pixels stay square on wider images, which only cover the
+    /// latitudes around the horizon, and taller images still stop at the poles.
+    Equirectangular,
+    /// Full turn across the width, the height of the cylinder keeping the pixels square
+    Cylindrical,
+    /// Angle from the view direction growing linearly with the distance to the center, up
+    /// to half of `field_of_view` at the left and right edges. The whole image is covered,
+    /// the angle stopping at a half turn.
+    Fisheye,
+}
+
+impl Projection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Projection::Perspective => "perspective",
+            Projection::Orthographic { .. } => "orthographic",
+            Projection::Equirectangular => "equirectangular",
+            Projection::Cylindrical => "cylindrical",
+            Projection::Fisheye => "fisheye",
+        }
+    }
+}
+
+/// Tile just rendered, with the progress of the whole image
+#[derive(Debug, Clone, Copy)]
+pub struct TileProgress<'a> {
+    pub tile: &'a Tile,
+    /// Colors of the pixels of the tile row by row, followed by the values of each AOV of
+    /// the camera laid out the same way
+    pub pixels: &'a [Color],
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Position and aim of a camera, turned into its transformation by `view_transform`
+#[derive(Debug, Clone, PartialEq)]
+pub struct View {
+    pub from: Tuple,
+    pub to: Tuple,
+    pub up: Tuple,
+}
+
+impl View {
+    pub fn new_view(from: Tuple, to: Tuple, up: Tuple) -> View {
+        View { from, to, up }
+    }
+
+    /// View of a camera whose transformation has `inverse` for inverse, aiming at the point
+    /// one unit ahead
+    fn from_inverse(inverse: &Matrix4) -> View {
+        View {
+            from: inverse * Tuple::new_point(0.0, 0.0, 0.0),
+            to: inverse * Tuple::new_point(0.0, 0.0, -1.0),
+            up: inverse * Tuple::new_vector(0.0, 1.0, 0.0),
+        }
+    }
+
+    pub fn transformation(&self) -> Matrix4 {
+        view_transform(&self.from, &self.to, &self.up)
+    }
+
+    pub fn forward(&self) -> Tuple {
+        (self.to.clone() - self.from.clone()).normalize()
+    }
+
+    pub fn right(&self) -> Tuple {
+        Tuple::cross_product(&self.up, &self.forward()).normalize()
+    }
+
+    /// Moves both ends of the view by `forward`, `right` and `up` units along its axes
+    pub fn translate(&mut self, forward: f64, right: f64, up: f64) {
+        let offset = self.forward() * forward + self.right() * right + self.up.normalize() * up;
+        self.from = self.from.clone() + offset.clone();
+        self.to = self.to.clone() + offset;
+    }
+
+    /// Turns `from` around `to`, by `yaw` radians around the up vector and by `pitch` radians
+    /// toward it, stopping short of the poles
+    pub fn orbit(&mut self, yaw: f64, pitch: f64) {
+        let up = self.up.normalize();
+        let offset = rotate_around(&(self.from.clone() - self.to.clone()), &up, yaw);
+
+        let elevation = Tuple::dot_product(&offset.normalize(), &up).acos();
+        let pitch = pitch.clamp(elevation - PI + 0.01, elevation - 0.01);
+        let right = Tuple::cross_product(&offset, &up).normalize();
+        let offset = rotate_around(&offset, &right, pitch);
+
+        self.from = self.to.clone() + offset;
+    }
+}
+
+/// Rotation of `vector` by `angle` radians around the unit `axis` (Rodrigues' formula)
+fn rotate_around(vector: &Tuple, axis: &Tuple, angle: f64) -> Tuple {
+    vector.clone() * angle.cos()
+        + Tuple::cross_product(axis, vector) * angle.sin()
+        + axis.clone() * (Tuple::dot_product(axis, vector) * (1.0 - angle.cos()))
+}
+
 ///virtual camera
 #[derive(Debug, Clone)]
 pub struct Camera {
     pub hsize: usize,
     pub vsize: usize,
     pub field_of_view: f64,
-    pub transformation: Matrix,
+    pub projection: Projection,
+    pub transformation: Matrix4,
     pub half_width: f64,
     pub half_height: f64,
     pub pixel_size: f64,
+    pub integrator: Integrator,
+    /// Side of the square tiles rendered in parallel, in pixels
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    /// Position and aim the transformation is built from, kept up to date by `set_transform`
+    pub view: View,
+    /// Variables written next to the image when rendering to a file
+    pub aovs: Vec<Aov>,
+    /// How the linear colors are shown and written to 8 bit images
+    pub tone_mapping: ToneMapping,
+    /// Interval the rays are traced in, moving objects being blurred across it
+    pub shutter: Shutter,
+    /// Rectangle of pixels rendered by the tiled renders, the whole image by default
+    pub region: Option<Tile>,
+    /// Whether the image is cut to `region`, the pixels outside of it being black otherwise
+    pub crop: bool,
+    /// Eyes rendered instead of the camera itself
+    pub stereo: Option<Stereo>,
+    /// Cameras of the left and right eyes, kept up to date with `stereo`
+    pub(crate) eyes: Vec<Camera>,
+    /// Inverse of `transformation`, kept up to date by `set_transform`
+    inverse: Matrix4,
 }
 
 impl Camera {
@@ -37,7 +187,10 @@ impl Camera {
         let half_width;
         let half_height;
 
-        if aspect >= 1.0 {
+        if let Projection::Orthographic { view_width } = self.projection {
+            half_width = view_width / 2.0;
+            half_height = half_width / aspect;
+        } else if aspect >= 1.0 {
             half_width = half_view;
             half_height = half_view / aspect;
         } else {
@@ -49,6 +202,7 @@ impl Camera {
         self.pixel_size = pixel_size;
         self.half_height = half_height;
         self.half_width = half_width;
+        self.update_eyes();
         self
     }
 
@@ -57,10 +211,23 @@ impl Camera {
             hsize: 200,
             vsize: 100,
             field_of_view: 1.5,
-            transformation: Matrix::new_identity_matrix(4),
+            projection: Projection::Perspective,
+            transformation: Matrix4::new_identity_matrix(),
+            inverse: Matrix4::new_identity_matrix(),
             half_width: 0.0,
             half_height: 0.0,
             pixel_size: 0.0,
+            integrator: Integrator::Whitted,
+            tile_size: 16,
+            tile_order: TileOrder::Spiral,
+            view: View::from_inverse(&Matrix4::new_identity_matrix()),
+            aovs: vec![],
+            tone_mapping: ToneMapping::new_linear(),
+            shutter: Shutter::new_instant(0.0),
+            region: None,
+            crop: false,
+            stereo: None,
+            eyes: vec![],
         }
         .calculate_ratios()
     }
@@ -83,121 +250,328 @@ impl Camera {
         self.calculate_ratios()
     }
 
-    pub fn with_transformation(mut self, transformation: Matrix) -> Self {
-        self.transformation = transformation;
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self.calculate_ratios()
+    }
+
+    pub fn with_transformation(mut self, transformation: Matrix4) -> Self {
+        self.set_transform(&transformation);
         self
     }
 
-    pub fn set_transform(&mut self, new_transformation: &Matrix) {
-        self.transformation = new_transformation.clone();
+    pub fn with_view(mut self, view: View) -> Self {
+        self.set_view(view);
+        self
     }
 
-    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
-        Ray::new(
-            Tuple::new_point(0.0, 0.0, 0.0),
-            Tuple::new_vector(0.0, 0.0, 0.0),
-        );
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
 
-        let xoffset = (px as f64 + 0.5) * self.pixel_size;
-        let yoffset = (py as f64 + 0.5) * self.pixel_size;
+    pub fn with_tile_size(mut self, tile_size: usize) -> Self {
+        self.tile_size = tile_size;
+        self
+    }
 
-        let world_x = self.half_width - xoffset;
-        let world_y = self.half_height - yoffset;
+    pub fn with_tile_order(mut self, tile_order: TileOrder) -> Self {
+        self.tile_order = tile_order;
+        self
+    }
 
-        let pixel = memoized_inverse(self.clone().transformation).unwrap()
-            * Tuple::new_point(world_x, world_y, -1.0);
-        let origin = memoized_inverse(self.clone().transformation).unwrap()
-            * Tuple::new_point(0.0, 0.0, 0.0);
-        let direction = (pixel - origin.clone()).normalize();
+    pub fn with_aovs(mut self, aovs: Vec<Aov>) -> Self {
+        self.aovs = aovs;
+        self
+    }
 
-        Ray::new(origin, direction)
+    pub fn with_tone_mapping(mut self, tone_mapping: ToneMapping) -> Self {
+        self.tone_mapping = tone_mapping;
+        self
     }
 
-    fn color_at(&self, world: &World, col: usize, row: usize) -> Color {
-        let ray = self.ray_for_pixel(col, row);
-        world.color_at(&ray, 5)
+    pub fn with_region(mut self, region: Option<Tile>) -> Self {
+        self.region = region;
+        self
     }
 
-    pub fn render(&self, world: World) -> Canvas {
-        let mut image = Canvas::new_canvas(self.hsize, self.vsize);
-        println!("Starting render");
-        for y in 0..self.vsize {
-            for x in 0..self.hsize {
-                image.set_pixel_color(x, y, self.color_at(&world, x, y));
+    pub fn with_crop(mut self, crop: bool) -> Self {
+        self.crop = crop;
+        self
+    }
+
+    /// Pixels rendered, the region being clipped to the image
+    pub fn render_region(&self) -> Tile {
+        match self.region {
+            Some(region) => {
+                let x = region.x.min(self.hsize);
+                let y = region.y.min(self.vsize);
+                Tile::new_tile(
+                    x,
+                    y,
+                    region.width.min(self.hsize - x),
+                    region.height.min(self.vsize - y),
+                )
             }
+            None => Tile::new_tile(0, 0, self.hsize, self.vsize),
         }
-        image
     }
 
-    // factoriser les fonctions
+    /// Blank image the render is written to, with the position in the camera image of its
+    /// top left pixel
+    pub fn output_canvas(&self) -> (Canvas, usize, usize) {
+        if self.crop {
+            let region = self.render_region();
+            let canvas = Canvas::new_canvas(region.width, region.height);
+            (canvas, region.x, region.y)
+        } else {
+            (Canvas::new_canvas(self.hsize, self.vsize), 0, 0)
+        }
+    }
 
-    pub fn render_with_update_bar(&self, world: World) -> Canvas {
-        let mut image = Canvas::new_canvas(self.hsize, self.vsize);
-        println!("Starting render");
-        let bar = ProgressBar::new((self.hsize * self.vsize) as u64);
-        bar.set_style(
-            ProgressStyle::with_template("{bar:120} [{percent_precise}%] [T : {elapsed:}]")
-                .unwrap(),
-        );
+    pub fn set_transform(&mut self, new_transformation: &Matrix4) {
+        self.transformation = *new_transformation;
+        self.inverse = self.transformation.inverse().unwrap();
+        self.view = View::from_inverse(&self.inverse);
+        self.update_eyes();
+    }
+
+    pub fn set_view(&mut self, view: View) {
+        self.set_transform(&view.transformation());
+        self.view = view;
+    }
+
+    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
+        self.ray_for_pixel_offset(px, py, 0.5, 0.5)
+    }
+
+    /// Ray through the point at `(dx, dy)` in `[0, 1)²` inside the pixel
+    pub fn ray_for_pixel_offset(&self, px: usize, py: usize, dx: f64, dy: f64) -> Ray {
+        let xoffset = (px as f64 + dx) * self.pixel_size;
+        let yoffset = (py as f64 + dy) * self.pixel_size;
+
+        let world_x = self.half_width - xoffset;
+        let world_y = self.half_height - yoffset;
+
+        match self.projection {
+            Projection::Perspective => {
+                let pixel = self.inverse * Tuple::new_point(world_x, world_y, -1.0);
+                let origin = self.inverse * Tuple::new_point(0.0, 0.0, 0.0);
+                let direction = (pixel - origin.clone()).normalize();
 
-        for y in 0..self.vsize {
-            for x in 0..self.hsize {
-                image.set_pixel_color(x, y, self.color_at(&world, x, y));
+                Ray::new(origin, direction)
+            }
+            Projection::Orthographic { .. } => {
+                let origin = self.inverse * Tuple::new_point(world_x, world_y, 0.0);
+                let direction = (self.inverse * Tuple::new_vector(0.0, 0.0, -1.0)).normalize();
+
+                Ray::new(origin, direction)
+            }
+            _ => {
+                let u = (px as f64 + dx) / self.hsize as f64;
+                let v = (py as f64 + dy) / self.vsize as f64;
+                let origin = self.inverse * Tuple::new_point(0.0, 0.0, 0.0);
+                let direction = (self.inverse * self.panoramic_direction(u, v)).normalize();
+
+                Ray::new(origin, direction)
             }
-            bar.inc(self.hsize as u64);
         }
+    }
 
-        println!("Done rendering");
-        image
+    /// Direction in camera space, looking toward -z with +y up, of the point at `(u, v)`
+    /// in `[0, 1]²` on a panoramic or fisheye image
+    fn panoramic_direction(&self, u: f64, v: f64) -> Tuple {
+        // the camera looks toward -z, its right being -x
+        let toward = |longitude: f64, latitude: f64| {
+            Tuple::new_vector(
+                -longitude.sin() * latitude.cos(),
+                latitude.sin(),
+                -longitude.cos() * latitude.cos(),
+            )
+        };
+
+        match self.projection {
+            Projection::Equirectangular => {
+                let latitudes = (2.0 * self.vsize as f64 / self.hsize as f64).min(1.0) * PI;
+                toward((u - 0.5) * 2.0 * PI, (0.5 - v) * latitudes)
+            }
+            Projection::Cylindrical => {
+                let longitude = (u - 0.5) * 2.0 * PI;
+                let height = (0.5 - v) * 2.0 * PI * self.vsize as f64 / self.hsize as f64;
+                Tuple::new_vector(-longitude.sin(), height, -longitude.cos())
+            }
+            Projection::Fisheye => {
+                // pixels away from the center, then radians per pixel
+                let x = (u - 0.5) * self.hsize as f64;
+                let y = (0.5 - v) * self.vsize as f64;
+                let scale = self.field_of_view / self.hsize as f64;
+                let angle = ((x * x + y * y).sqrt() * scale).min(PI);
+                let around = y.atan2(x);
+                Tuple::new_vector(
+                    -angle.sin() * around.cos(),
+                    angle.sin() * around.sin(),
+                    -angle.cos(),
+                )
+            }
+            _ => unreachable!("the planar projections have no panoramic direction"),
+        }
     }
 
-    pub fn render_par_with_update_bar(&self, world: World) -> Canvas {
-        const BAND_SIZE: usize = 10;
-        let mut image2 = Canvas::new_canvas(self.hsize, self.vsize);
+    pub(crate) fn color_at(&self, world: &World, col: usize, row: usize) -> Color {
+        self.color_at_offset(world, col, row, 0.5, 0.5)
+    }
 
-        println!("Starting render");
+    /// Color of the pixel seen through the point at `(dx, dy)` inside it, path tracing
+    /// jittering its own samples
+    pub(crate) fn color_at_offset(
+        &self,
+        world: &World,
+        col: usize,
+        row: usize,
+        dx: f64,
+        dy: f64,
+    ) -> Color {
+        if let Some(stereo) = self.stereo
+            && stereo.layout == StereoLayout::Anaglyph
+        {
+            let left = self.trace(world, &self.eyes[0], col, row, dx, dy);
+            let right = self.trace(world, &self.eyes[1], col, row, dx, dy);
+            return anaglyph(left, right);
+        }
 
-        let bar_style =
-            ProgressStyle::with_template("{bar:120} [{percent_precise}%] [T : {elapsed:}]")
-                .unwrap();
+        let (lens, col, row) = self.lens_for_pixel(col, row);
+        self.trace(world, lens, col, row, dx, dy)
+    }
 
-        image2
-            .pixels()
-            .par_chunks_mut(self.hsize * BAND_SIZE)
-            .enumerate()
-            .progress_with_style(bar_style)
-            .for_each(|(i, band)| {
-                for row in 0..BAND_SIZE {
-                    for col in 0..self.hsize {
-                        band[row * self.hsize + col] =
-                            self.color_at(&world, col, row + i * BAND_SIZE);
-                    }
+    /// Color given by the integrator of the camera to the pixel of `lens`
+    fn trace(
+        &self,
+        world: &World,
+        lens: &Camera,
+        col: usize,
+        row: usize,
+        dx: f64,
+        dy: f64,
+    ) -> Color {
+        match self.integrator {
+            Integrator::Whitted => self.over_shutter(|time| {
+                let ray = lens.ray_for_pixel_offset(col, row, dx, dy).with_time(time);
+                world.color_at(&ray, reflection::MAX_RECURTION)
+            }),
+            Integrator::PathTracing { samples } => {
+                let mut color = color::BLACK;
+                for _ in 0..samples {
+                    let ray = lens
+                        .ray_for_pixel_offset(col, row, rand::random(), rand::random())
+                        .with_time(self.sample_time());
+                    color += world.path_trace(&ray);
                 }
-            });
+                color * (1.0 / samples as f64)
+            }
+            Integrator::AmbientOcclusion(settings) => self.over_shutter(|time| {
+                let ray = lens.ray_for_pixel_offset(col, row, dx, dy).with_time(time);
+                world.occlusion_at(&ray, &settings)
+            }),
+            Integrator::Debug(mode) => {
+                let far = 2.0 * (self.view.to.clone() - self.view.from.clone()).magnitude();
+                self.over_shutter(|time| {
+                    let ray = lens.ray_for_pixel_offset(col, row, dx, dy).with_time(time);
+                    world.debug_color_at(&ray, mode, far)
+                })
+            }
+        }
+    }
 
-        println!("Done rendering");
-        image2
+    /// Colors of the pixels of `tile` row by row, followed by the values of each AOV of the
+    /// camera laid out the same way
+    fn render_tile(&self, world: &World, tile: &Tile) -> Vec<Color> {
+        let mut pixels = Vec::with_capacity(tile.pixel_count() * (1 + self.aovs.len()));
+        let mut layers = vec![Vec::with_capacity(tile.pixel_count()); self.aovs.len()];
+        for row in tile.y..tile.y + tile.height {
+            for col in tile.x..tile.x + tile.width {
+                if self.aovs.is_empty() {
+                    pixels.push(self.color_at(world, col, row));
+                    continue;
+                }
+                let (color, sample) = self.color_and_aovs_at(world, col, row);
+                pixels.push(color);
+                for (layer, aov) in layers.iter_mut().zip(&self.aovs) {
+                    layer.push(sample.value(*aov));
+                }
+            }
+        }
+        pixels.extend(layers.into_iter().flatten());
+        pixels
     }
 
+    pub fn render(&self, world: &World) -> Canvas {
+        self.render_with_progress(world, |_| {})
+    }
 
-    pub fn render_par_headless(&self, world: World) -> Canvas {
-        const BAND_SIZE: usize = 10;
-        let mut image2 = Canvas::new_canvas(self.hsize, self.vsize);
+    /// Renders the tiles in parallel, handing them out in `tile_order` to the threads,
+    /// and calls `progress` each time one is done
+    pub fn render_with_progress(
+        &self,
+        world: &World,
+        progress: impl Fn(TileProgress) + Sync,
+    ) -> Canvas {
+        self.render_resuming(world, &[], progress).0
+    }
 
-        image2
-            .pixels()
-            .par_chunks_mut(self.hsize * BAND_SIZE)
-            .enumerate()
-            .for_each(|(i, band)| {
-                for row in 0..BAND_SIZE {
-                    for col in 0..self.hsize {
-                        band[row * self.hsize + col] =
-                            self.color_at(&world, col, row + i * BAND_SIZE);
-                    }
+    /// Renders like `render_with_progress`, the tiles of `done` being taken with their
+    /// pixels instead of rendered again, along with one image per AOV of the camera
+    pub fn render_resuming(
+        &self,
+        world: &World,
+        done_tiles: &[(Tile, Vec<Color>)],
+        progress: impl Fn(TileProgress) + Sync,
+    ) -> (Canvas, Vec<Canvas>) {
+        let all_tiles = tile::tiles_in(&self.render_region(), self.tile_size, self.tile_order);
+        let tiles: Vec<&Tile> = all_tiles
+            .iter()
+            .filter(|tile| !done_tiles.iter().any(|(done, _)| done == *tile))
+            .collect();
+
+        let (canvas, left, top) = self.output_canvas();
+        let images = Mutex::new(vec![canvas; 1 + self.aovs.len()]);
+        let place = |images: &mut Vec<Canvas>, tile: &Tile, pixels: &[Color]| {
+            let layers = pixels.chunks(tile.pixel_count().max(1));
+            for (image, layer) in images.iter_mut().zip(layers) {
+                for (i, color) in layer.iter().enumerate() {
+                    image.set_pixel_color(
+                        tile.x + i % tile.width - left,
+                        tile.y + i / tile.width - top,
+                        *color,
+                    );
+                }
+            }
+        };
+        for (tile, pixels) in done_tiles {
+            if all_tiles.contains(tile) {
+                place(&mut images.lock().unwrap(), tile, pixels);
+            }
+        }
+
+        let next = AtomicUsize::new(0);
+        let done = AtomicUsize::new(all_tiles.len() - tiles.len());
+        (0..rayon::current_num_threads())
+            .into_par_iter()
+            .for_each(|_| {
+                while let Some(tile) = tiles.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let pixels = self.render_tile(world, tile);
+                    place(&mut images.lock().unwrap(), tile, &pixels);
+                    progress(TileProgress {
+                        tile,
+                        pixels: &pixels,
+                        done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                        total: all_tiles.len(),
+                    });
                 }
             });
 
-        image2
+        let mut images = images.into_inner().unwrap();
+        let beauty = images.remove(0);
+        (beauty, images)
     }
 }
 
@@ -260,6 +634,22 @@ mod camera_tests {
         );
     }
 
+    #[test]
+    ///Constructing a ray through the corner of a pixel
+    fn construc_ray_pixel_offset() {
+        let camera = Camera::new(201, 101, PI / 2.0);
+        let r = camera.ray_for_pixel_offset(0, 0, 0.0, 0.0);
+        assert_eq!(r.origin, Tuple::new_point(0.0, 0.0, 0.0));
+        assert_eq!(
+            r.direction,
+            Tuple::new_vector(0.6662975290166695, 0.3348062210481772, -0.6662975290166696)
+        );
+        assert_eq!(
+            camera.ray_for_pixel_offset(100, 50, 0.5, 0.5),
+            camera.ray_for_pixel(100, 50)
+        );
+    }
+
     #[test]
     ///Constructing a ray when the camera is transformed
     fn construc_ray_tranform() {
@@ -273,6 +663,78 @@ mod camera_tests {
         );
     }
 
+    #[test]
+    // Orthographic rays are parallel, leaving the image plane view_width wide
+    fn construc_ray_orthographic() {
+        let camera = Camera::new(200, 100, PI / 2.0)
+            .with_projection(Projection::Orthographic { view_width: 4.0 });
+        assert!(utils::compare_float(camera.pixel_size, 0.02));
+
+        let r = camera.ray_for_pixel(0, 0);
+        assert_eq!(r.origin, Tuple::new_point(1.99, 0.99, 0.0));
+        assert_eq!(r.direction, Tuple::new_vector(0.0, 0.0, -1.0));
+
+        let mut camera = camera;
+        camera.set_transform(&create_translation(0.0, -2.0, 5.0).rotation_y(PI / 4.0));
+        let center = camera.ray_for_pixel_offset(100, 50, 0.0, 0.0);
+        let corner = camera.ray_for_pixel(0, 99);
+        assert_eq!(center.origin, Tuple::new_point(0.0, 2.0, -5.0));
+        assert_eq!(center.direction, corner.direction);
+        assert_eq!(
+            center.direction,
+            Tuple::new_vector(2.0_f64.sqrt() / 2.0, 0.0, -2.0_f64.sqrt() / 2.0)
+        );
+    }
+
+    #[test]
+    // Panoramas cover the directions around the camera, their pixels keeping their shape
+    fn construc_ray_panoramic() {
+        let forward = Tuple::new_vector(0.0, 0.0, -1.0);
+        let right = Tuple::new_vector(-1.0, 0.0, 0.0);
+        let up = Tuple::new_vector(0.0, 1.0, 0.0);
+
+        let camera = Camera::new(40, 20, PI / 2.0).with_projection(Projection::Equirectangular);
+        let direction = |u: f64, v: f64| camera.panoramic_direction(u, v);
+        assert_eq!(direction(0.5, 0.5), forward);
+        assert_eq!(direction(0.75, 0.5), right);
+        assert_eq!(direction(0.0, 0.5), forward.clone() * -1.0);
+        assert_eq!(direction(0.3, 0.0), up);
+        assert_eq!(
+            camera.ray_for_pixel_offset(20, 10, 0.0, 0.0).direction,
+            forward
+        );
+        // a wider image covers the latitudes around the horizon with square pixels
+        let camera = Camera::new(40, 10, PI / 2.0).with_projection(Projection::Equirectangular);
+        assert_eq!(
+            camera.panoramic_direction(0.5, 0.0),
+            Tuple::new_vector(0.0, 1.0, -1.0).normalize()
+        );
+        assert_eq!(camera.panoramic_direction(0.75, 0.5), right);
+        let camera = Camera::new(40, 40, PI / 2.0).with_projection(Projection::Equirectangular);
+        assert_eq!(camera.panoramic_direction(0.3, 0.0), up);
+
+        let camera = Camera::new(40, 30, PI / 2.0).with_projection(Projection::Cylindrical);
+        let top = camera.ray_for_pixel_offset(20, 0, 0.0, 0.0).direction;
+        assert_eq!(top, Tuple::new_vector(0.0, 0.75 * PI, -1.0).normalize());
+        assert_eq!(camera.panoramic_direction(0.75, 0.5), right);
+
+        let mut camera = Camera::new(40, 20, PI).with_projection(Projection::Fisheye);
+        camera.set_transform(&create_translation(0.0, -2.0, 5.0).rotation_y(PI / 4.0));
+        assert_eq!(camera.panoramic_direction(0.5, 0.5), forward);
+        assert_eq!(camera.panoramic_direction(1.0, 0.5), right);
+        // the angle per pixel is the same on both axes
+        assert_eq!(
+            camera.panoramic_direction(0.5, 0.0),
+            Tuple::new_vector(0.0, 1.0, -1.0).normalize()
+        );
+        let r = camera.ray_for_pixel_offset(20, 10, 0.0, 0.0);
+        assert_eq!(r.origin, Tuple::new_point(0.0, 2.0, -5.0));
+        assert_eq!(
+            r.direction,
+            Tuple::new_vector(2.0_f64.sqrt() / 2.0, 0.0, -2.0_f64.sqrt() / 2.0)
+        );
+    }
+
     #[test]
     ///Rendering a world with a camera
     fn render_world() {
@@ -283,12 +745,127 @@ mod camera_tests {
         let to = Tuple::new_point(0.0, 0.0, 0.0);
         let up = Tuple::new_vector(0.0, 1.0, 0.0);
 
-        c.transformation = view_transform(&from, &to, &up);
-        let image = c.render(w);
+        c.set_transform(&view_transform(&from, &to, &up));
+        let image = c.render(&w);
 
         assert_eq!(
             image.pixel_at(5, 5),
             Color::new_color(0.3806611930807966, 0.47582649135099575, 0.28549589481059745)
         );
     }
+
+    #[test]
+    ///Rendering a world whose size is not a multiple of the tile size, in every order
+    fn render_world_tiles() {
+        let w = World::default_world();
+        let from = Tuple::new_point(0.0, 0.0, -5.0);
+        let to = Tuple::new_point(0.0, 0.0, 0.0);
+        let up = Tuple::new_vector(0.0, 1.0, 0.0);
+        let c = Camera::new(13, 7, PI / 2.0)
+            .with_transformation(view_transform(&from, &to, &up))
+            .with_tile_size(4);
+
+        let reference = c.clone().with_tile_size(100).render(&w);
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let done = AtomicUsize::new(0);
+            let image = c
+                .clone()
+                .with_tile_order(order)
+                .render_with_progress(&w, |p| {
+                    assert_eq!(p.pixels.len(), p.tile.pixel_count());
+                    assert_eq!(p.total, 8);
+                    done.fetch_add(1, Ordering::Relaxed);
+                });
+            assert_eq!(done.into_inner(), 8);
+            for y in 0..7 {
+                for x in 0..13 {
+                    assert_eq!(image.pixel_at(x, y), reference.pixel_at(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    ///Rendering a region of the image, cropped or not, and resuming a render
+    fn render_world_region() {
+        let w = World::default_world();
+        let from = Tuple::new_point(0.0, 0.0, -5.0);
+        let to = Tuple::new_point(0.0, 0.0, 0.0);
+        let up = Tuple::new_vector(0.0, 1.0, 0.0);
+        let c = Camera::new(13, 7, PI / 2.0)
+            .with_transformation(view_transform(&from, &to, &up))
+            .with_tile_size(4);
+        let reference = c.render(&w);
+
+        let region = Tile::new_tile(3, 2, 8, 20);
+        let c = c.with_region(Some(region));
+        assert_eq!(c.render_region(), Tile::new_tile(3, 2, 8, 5));
+        let image = c.render(&w);
+        assert_eq!((image.width, image.height), (13, 7));
+        assert_eq!(image.pixel_at(6, 3), reference.pixel_at(6, 3));
+        assert_eq!(image.pixel_at(2, 3), color::BLACK);
+
+        let c = c.with_crop(true);
+        let image = c.render(&w);
+        assert_eq!((image.width, image.height), (8, 5));
+        assert_eq!(image.pixel_at(3, 1), reference.pixel_at(6, 3));
+
+        // the tiles already done are not rendered again
+        let done = vec![(Tile::new_tile(3, 2, 4, 4), vec![color::WHITE; 16])];
+        let rendered = AtomicUsize::new(0);
+        let (image, _) = c.render_resuming(&w, &done, |p| {
+            assert!(p.done > 1 && p.total == 4);
+            rendered.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(rendered.into_inner(), 3);
+        assert_eq!(image.pixel_at(0, 0), color::WHITE);
+        assert_eq!(image.pixel_at(5, 1), reference.pixel_at(8, 3));
+    }
+
+    #[test]
+    ///The view of a camera follows its transformation
+    fn camera_view() {
+        let from = Tuple::new_point(1.0, 3.0, 2.0);
+        let to = Tuple::new_point(4.0, -2.0, 8.0);
+        let up = Tuple::new_vector(1.0, 1.0, 0.0);
+        let view = View::new_view(from.clone(), to, up);
+
+        let mut c = Camera::new(11, 11, PI / 2.0).with_view(view.clone());
+        assert_eq!(c.view, view);
+        assert_eq!(
+            c.transformation,
+            view_transform(&view.from, &view.to, &view.up)
+        );
+
+        c.set_transform(&create_translation(0.0, 0.0, -5.0));
+        assert_eq!(c.view.from, Tuple::new_point(0.0, 0.0, 5.0));
+        assert_eq!(c.view.to, Tuple::new_point(0.0, 0.0, 4.0));
+        assert_eq!(c.view.up, Tuple::new_vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    ///Moving a view along its axes and orbiting around its target
+    fn view_navigation() {
+        let mut view = View::new_view(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        view.translate(1.0, 2.0, 3.0);
+        assert_eq!(view.from, Tuple::new_point(2.0, 3.0, -4.0));
+        assert_eq!(view.to, Tuple::new_point(2.0, 3.0, 1.0));
+
+        view.translate(-1.0, -2.0, -3.0);
+        view.orbit(PI / 2.0, 0.0);
+        assert_eq!(view.from, Tuple::new_point(-5.0, 0.0, 0.0));
+
+        view.orbit(0.0, PI / 4.0);
+        let half = 5.0 / 2.0_f64.sqrt();
+        assert_eq!(view.from, Tuple::new_point(-half, half, 0.0));
+
+        // stops short of passing over the top
+        view.orbit(0.0, PI);
+        assert!(view.from.y < 5.0 && view.from.y > 4.99);
+        assert_eq!(view.to, Tuple::new_point(0.0, 0.0, 0.0));
+    }
 }
@@ -0,0 +1,209 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{color::Color, tile::Tile};
+
+/// Time between two saves of the checkpoint of a render
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+const MAGIC: &[u8] = b"RUSTRACER-CHECKPOINT\n";
+
+/// Tiles of a render done so far, saved to disk so an interrupted render can be resumed.
+/// Resuming is tile granular: a tile is saved once all its samples are done, the tiles
+/// being rendered when the render stops starting over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// Fingerprint of the scene and frame being rendered
+    pub hash: u64,
+    pub width: usize,
+    pub height: usize,
+    /// Images rendered for each tile, the beauty image then the AOVs of the camera
+    pub layers: usize,
+    /// Tiles already rendered, with the linear colors of their pixels row by row, one layer
+    /// after the other
+    pub tiles: Vec<(Tile, Vec<Color>)>,
+}
+
+impl Checkpoint {
+    pub fn new_checkpoint(hash: u64, width: usize, height: usize, layers: usize) -> Checkpoint {
+        Checkpoint {
+            hash,
+            width,
+            height,
+            layers,
+            tiles: vec![],
+        }
+    }
+
+    /// Path of the checkpoint of the render written to `output`, `render.ppm` giving
+    /// `render.ppm.checkpoint`
+    pub fn path(output: &Path) -> PathBuf {
+        let mut name = output.file_name().unwrap_or_default().to_os_string();
+        name.push(".checkpoint");
+        output.with_file_name(name)
+    }
+
+    pub fn add(&mut self, tile: Tile, pixels: Vec<Color>) {
+        self.tiles.push((tile, pixels));
+    }
+
+    /// Header, then for each tile its position and size followed by its layers, every
+    /// number being little endian and the channels 64 bit floats
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(self.hash.to_le_bytes());
+        bytes.extend((self.width as u32).to_le_bytes());
+        bytes.extend((self.height as u32).to_le_bytes());
+        bytes.extend((self.layers as u32).to_le_bytes());
+        for (tile, pixels) in &self.tiles {
+            for value in [tile.x, tile.y, tile.width, tile.height] {
+                bytes.extend((value as u32).to_le_bytes());
+            }
+            for color in pixels {
+                let (red, green, blue) = color.channels();
+                for channel in [red, green, blue] {
+                    bytes.extend(channel.to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Checkpoint read from `bytes`, a tile cut short at the end being left out
+    pub fn from_bytes(bytes: &[u8]) -> Option<Checkpoint> {
+        let mut reader = Reader {
+            bytes: bytes.strip_prefix(MAGIC)?,
+        };
+        let hash = u64::from_le_bytes(reader.take()?);
+        let width = u32::from_le_bytes(reader.take()?) as usize;
+        let height = u32::from_le_bytes(reader.take()?) as usize;
+        let layers = u32::from_le_bytes(reader.take()?) as usize;
+        let mut checkpoint = Checkpoint::new_checkpoint(hash, width, height, layers);
+
+        while let Some(tile) = reader.tile() {
+            let pixels = (0..tile.pixel_count() * layers)
+                .map(|_| {
+                    let mut channel = || reader.take().map(f64::from_le_bytes);
+                    Some(Color::new_color(channel()?, channel()?, channel()?))
+                })
+                .collect::<Option<Vec<Color>>>();
+            match pixels {
+                Some(pixels) => checkpoint.add(tile, pixels),
+                None => break,
+            }
+        }
+        Some(checkpoint)
+    }
+
+    /// Checkpoint saved at `path` for the render of a `width` x `height` image of the scene
+    /// of fingerprint `hash` with `layers` images per tile, if there is one
+    pub fn load(
+        path: &Path,
+        hash: u64,
+        width: usize,
+        height: usize,
+        layers: usize,
+    ) -> Option<Checkpoint> {
+        let checkpoint = Checkpoint::from_bytes(&fs::read(path).ok()?)?;
+        (checkpoint.hash == hash
+            && (checkpoint.width, checkpoint.height, checkpoint.layers) == (width, height, layers))
+            .then_some(checkpoint)
+    }
+
+    /// Writes the checkpoint to `path` through a temporary file, so that a crash while
+    /// saving leaves the previous checkpoint whole
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut temporary = path.as_os_str().to_os_string();
+        temporary.push(".tmp");
+        fs::write(&temporary, self.to_bytes())?;
+        fs::rename(&temporary, path)
+    }
+}
+
+/// Bytes of a checkpoint not read yet
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (value, rest) = self.bytes.split_first_chunk()?;
+        self.bytes = rest;
+        Some(*value)
+    }
+
+    fn tile(&mut self) -> Option<Tile> {
+        let mut value = || self.take().map(|bytes| u32::from_le_bytes(bytes) as usize);
+        Some(Tile::new_tile(value()?, value()?, value()?, value()?))
+    }
+}
+
+/// Fingerprint (FNV-1a) of the scene file `scene` rendered at `time`, a checkpoint only
+/// being resumed by the same frame of an unchanged scene
+pub fn scene_hash(scene: &[u8], time: f64) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in scene.iter().chain(&time.to_le_bytes()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod checkpoint_tests {
+    use super::*;
+
+    fn checkpoint() -> Checkpoint {
+        let mut checkpoint = Checkpoint::new_checkpoint(42, 4, 2, 2);
+        checkpoint.add(
+            Tile::new_tile(0, 0, 2, 1),
+            vec![
+                Color::new_color(1.5, 0.25, -1.0),
+                Color::new_color(0.0, 0.5, 1.0),
+                Color::new_color(2.0, 2.0, 2.0),
+                Color::new_color(3.0, 3.0, 3.0),
+            ],
+        );
+        checkpoint.add(
+            Tile::new_tile(2, 0, 1, 1),
+            vec![
+                Color::new_color(0.1, 0.2, 0.3),
+                Color::new_color(4.0, 4.0, 4.0),
+            ],
+        );
+        checkpoint
+    }
+
+    #[test]
+    // The tiles are read back with the pixels of their layers, a tile cut short being dropped
+    fn checkpoint_bytes() {
+        let c = checkpoint();
+        let bytes = c.to_bytes();
+        assert_eq!(Checkpoint::from_bytes(&bytes), Some(c.clone()));
+
+        let cut = Checkpoint::from_bytes(&bytes[..bytes.len() - 5]).unwrap();
+        assert_eq!(cut.tiles, c.tiles[..1]);
+        assert_eq!(Checkpoint::from_bytes(b"P6\n"), None);
+    }
+
+    #[test]
+    // Only the checkpoint of the same scene, image size and layers is resumed
+    fn checkpoint_load() {
+        let path = Checkpoint::path(&std::env::temp_dir().join("rustracer_checkpoint.ppm"));
+        assert!(path.ends_with("rustracer_checkpoint.ppm.checkpoint"));
+
+        let c = checkpoint();
+        c.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path, 42, 4, 2, 2), Some(c));
+        assert_eq!(Checkpoint::load(&path, 43, 4, 2, 2), None);
+        assert_eq!(Checkpoint::load(&path, 42, 4, 3, 2), None);
+        assert_eq!(Checkpoint::load(&path, 42, 4, 2, 1), None);
+
+        assert_eq!(scene_hash(b"scene", 0.0), scene_hash(b"scene", 0.0));
+        assert_ne!(scene_hash(b"scene", 0.0), scene_hash(b"scene", 1.0));
+        assert_ne!(scene_hash(b"scene", 0.0), scene_hash(b"scenf", 0.0));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::utils::*;
+use std::fmt;
 use std::ops::Add;
 use std::ops::AddAssign;
 use std::ops::Mul;
@@ -16,12 +17,50 @@ impl Color {
         Color { red, green, blue }
     }
 
+    /// Channels clamped to `[0, 1]` and rounded to bytes, `NaN` giving 0
     pub fn normalise(&self) -> (u8, u8, u8) {
-        (
-            (self.red * 255.0) as u8,
-            (self.green * 255.0) as u8,
-            (self.blue * 255.0) as u8,
-        )
+        let byte = |channel: f64| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+        (byte(self.red), byte(self.green), byte(self.blue))
+    }
+
+    pub fn channels(&self) -> (f64, f64, f64) {
+        (self.red, self.green, self.blue)
+    }
+
+    /// Applies `f` to each channel
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Color {
+        Color {
+            red: f(self.red),
+            green: f(self.green),
+            blue: f(self.blue),
+        }
+    }
+
+    /// Applies `e^x` to each channel
+    pub fn exp(&self) -> Color {
+        Color {
+            red: self.red.exp(),
+            green: self.green.exp(),
+            blue: self.blue.exp(),
+        }
+    }
+
+    /// Largest of the three channels
+    pub fn max_component(&self) -> f64 {
+        self.red.max(self.green).max(self.blue)
+    }
+}
+
+/// `(red, green, blue)`, the precision applying to each channel
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        fmt::Display::fmt(&self.red, f)?;
+        write!(f, ", ")?;
+        fmt::Display::fmt(&self.green, f)?;
+        write!(f, ", ")?;
+        fmt::Display::fmt(&self.blue, f)?;
+        write!(f, ")")
     }
 }
 
@@ -151,6 +190,30 @@ mod color_tests {
         assert_eq!(color * 2.0, Color::new_color(0.4, 0.6, 0.8));
     }
 
+    #[test]
+    fn color_exponential() {
+        let color = Color::new_color(0.0, 1.0, -1.0);
+        assert_eq!(
+            color.exp(),
+            Color::new_color(1.0, std::f64::consts::E, 1.0 / std::f64::consts::E)
+        );
+    }
+
+    #[test]
+    // Out of range channels saturate instead of wrapping
+    fn color_normalise() {
+        let color = Color::new_color(1.5, 0.5, -0.5);
+        assert_eq!(color.normalise(), (255, 128, 0));
+        let nan = Color::new_color(f64::NAN, 1.0, 0.0);
+        assert_eq!(nan.normalise(), (0, 255, 0));
+    }
+
+    #[test]
+    fn color_max_component() {
+        let color = Color::new_color(0.2, 0.7, 0.4);
+        assert!(compare_float(color.max_component(), 0.7));
+    }
+
     #[test]
     fn color_multiplication() {
         let color = Color::new_color(1.0, 0.2, 0.4);
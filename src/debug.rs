@@ -0,0 +1,251 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::{
+    color::{self, Color},
+    inspection::Recorder,
+    ray::Ray,
+    reflection::{MAX_RECURTION, PointLight},
+    tuple::Tuple,
+    world::World,
+};
+
+/// Number of rays shown in red by the cost heatmap
+pub const COST_SCALE: f64 = 32.0;
+
+/// False color images of the first hit of each camera ray, black where they miss
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugMode {
+    /// Shading normal, each coordinate mapped from `[-1, 1]` to a channel
+    Normals,
+    /// Distance to the hit, from white at the camera to black twice as far as the point
+    /// the camera looks at
+    Depth,
+    /// Logarithm of the distance, reaching black a hundred times farther than `Depth`
+    LogDepth,
+    /// Surface coordinates in the red and green channels
+    Uv,
+    /// Color picked from the index of the object in the scene
+    ObjectId,
+    /// Color picked from the values of the material
+    MaterialId,
+    /// Cosine of the angle between the normal and the direction to the eye
+    FacingRatio,
+    /// Heatmap of the camera, reflection, refraction and shadow rays cast by the shaded
+    /// render, from blue for one ray to red for `COST_SCALE` rays
+    Cost,
+}
+
+pub const DEBUG_MODES: [DebugMode; 8] = [
+    DebugMode::Normals,
+    DebugMode::Depth,
+    DebugMode::LogDepth,
+    DebugMode::Uv,
+    DebugMode::ObjectId,
+    DebugMode::MaterialId,
+    DebugMode::FacingRatio,
+    DebugMode::Cost,
+];
+
+impl DebugMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugMode::Normals => "normals",
+            DebugMode::Depth => "depth",
+            DebugMode::LogDepth => "log-depth",
+            DebugMode::Uv => "uv",
+            DebugMode::ObjectId => "object-id",
+            DebugMode::MaterialId => "material-id",
+            DebugMode::FacingRatio => "facing-ratio",
+            DebugMode::Cost => "cost",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DebugMode> {
+        DEBUG_MODES.into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+/// Recorder counting the rays cast, one shadow ray being cast toward each light
+struct RayCounter {
+    rays: usize,
+}
+
+impl Recorder for RayCounter {
+    fn ray(&mut self, _ray: &Ray) {
+        self.rays += 1;
+    }
+
+    fn light(&mut self, _light: &PointLight, _visibility: Color) {
+        self.rays += 1;
+    }
+}
+
+impl World {
+    /// False color of `ray` in `mode`, `far` being the distance the depth modes are
+    /// scaled by
+    pub fn debug_color_at(&self, ray: &Ray, mode: DebugMode, far: f64) -> Color {
+        if mode == DebugMode::Cost {
+            let mut counter = RayCounter { rays: 0 };
+            self.color_at_with(ray, MAX_RECURTION, &mut counter);
+            return heatmap((counter.rays - 1) as f64 / (COST_SCALE - 1.0));
+        }
+
+        let hit = match self.hit(ray) {
+            Some(hit) => hit,
+            None => return color::BLACK,
+        };
+        let comps = self.computations_at(&hit, ray);
+
+        match mode {
+            DebugMode::Normals => {
+                let n = &comps.normalv;
+                Color::new_color((n.x + 1.0) / 2.0, (n.y + 1.0) / 2.0, (n.z + 1.0) / 2.0)
+            }
+            DebugMode::Depth => gray(1.0 - hit.t / far),
+            DebugMode::LogDepth => gray(1.0 - hit.t.ln_1p() / (100.0 * far).ln_1p()),
+            DebugMode::Uv => {
+                let local_point = comps.placement.inverse * comps.point.clone();
+                let (u, v) = comps.object.shape.local_uv(&local_point);
+                Color::new_color(u, v, 0.0)
+            }
+            DebugMode::ObjectId => {
+                // the index of the object keeps its color from one parse of the scene to the next
+                let index = self
+                    .objects
+                    .iter()
+                    .position(|object| object.id == comps.object.id);
+                hashed_color(index)
+            }
+            DebugMode::MaterialId => hashed_color(format!("{:?}", comps.object.material)),
+            DebugMode::FacingRatio => gray(Tuple::dot_product(&comps.normalv, &comps.eyev)),
+            DebugMode::Cost => unreachable!("the cost doesn't depend on the hit"),
+        }
+    }
+}
+
+fn gray(value: f64) -> Color {
+    let value = value.clamp(0.0, 1.0);
+    Color::new_color(value, value, value)
+}
+
+/// Bright color picked from the hash of `value`
+fn hashed_color(value: impl Hash) -> Color {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    let channel = |shift: u32| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f64 / 255.0;
+    Color::new_color(channel(0), channel(8), channel(16))
+}
+
+/// Blue, cyan, green, yellow then red as `value` goes from 0 to 1
+fn heatmap(value: f64) -> Color {
+    const STOPS: [(f64, f64, f64); 5] = [
+        (0.0, 0.0, 1.0),
+        (0.0, 1.0, 1.0),
+        (0.0, 1.0, 0.0),
+        (1.0, 1.0, 0.0),
+        (1.0, 0.0, 0.0),
+    ];
+    let x = value.clamp(0.0, 1.0) * 4.0;
+    let i = (x as usize).min(3);
+    let f = x - i as f64;
+    let (r1, g1, b1) = STOPS[i];
+    let (r2, g2, b2) = STOPS[i + 1];
+    Color::new_color(r1 + (r2 - r1) * f, g1 + (g2 - g1) * f, b1 + (b2 - b1) * f)
+}
+
+#[cfg(test)]
+mod debug_tests {
+    use super::*;
+
+    fn front_ray() -> Ray {
+        Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        )
+    }
+
+    #[test]
+    // Each mode shows one property of the hit, misses are black
+    fn debug_modes() {
+        let w = World::default_world();
+        let r = front_ray();
+
+        assert_eq!(
+            w.debug_color_at(&r, DebugMode::Normals, 10.0),
+            Color::new_color(0.5, 0.5, 0.0)
+        );
+        assert_eq!(
+            w.debug_color_at(&r, DebugMode::Depth, 10.0),
+            Color::new_color(0.6, 0.6, 0.6)
+        );
+        let log_depth = 1.0 - 4.0_f64.ln_1p() / 1000.0_f64.ln_1p();
+        assert_eq!(
+            w.debug_color_at(&r, DebugMode::LogDepth, 10.0),
+            Color::new_color(log_depth, log_depth, log_depth)
+        );
+        assert_eq!(
+            w.debug_color_at(&r, DebugMode::Uv, 10.0),
+            Color::new_color(0.0, 0.5, 0.0)
+        );
+        assert_eq!(
+            w.debug_color_at(&r, DebugMode::FacingRatio, 10.0),
+            color::WHITE
+        );
+
+        let miss = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        for mode in DEBUG_MODES
+            .into_iter()
+            .filter(|mode| *mode != DebugMode::Cost)
+        {
+            assert_eq!(w.debug_color_at(&miss, mode, 10.0), color::BLACK);
+        }
+        assert_eq!(w.debug_color_at(&miss, DebugMode::Cost, 10.0), heatmap(0.0));
+    }
+
+    #[test]
+    // Objects and materials get their own colors
+    fn debug_ids() {
+        let w = World::default_world();
+        let r = front_ray();
+
+        let object = w.debug_color_at(&r, DebugMode::ObjectId, 10.0);
+        assert_eq!(object, hashed_color(Some(0_usize)));
+        assert_ne!(object, hashed_color(Some(1_usize)));
+        // the same scene built again keeps its colors
+        let again = World::default_world();
+        assert_ne!(again.objects[0].id, w.objects[0].id);
+        assert_eq!(again.debug_color_at(&r, DebugMode::ObjectId, 10.0), object);
+
+        let material = w.debug_color_at(&r, DebugMode::MaterialId, 10.0);
+        let mut m = w.objects[0].material.clone();
+        assert_eq!(material, hashed_color(format!("{:?}", m)));
+        m.diffuse = 0.5;
+        assert_ne!(material, hashed_color(format!("{:?}", m)));
+    }
+
+    #[test]
+    // The cost counts the camera ray and the shadow rays
+    fn debug_cost() {
+        let w = World::default_world();
+        assert_eq!(
+            w.debug_color_at(&front_ray(), DebugMode::Cost, 10.0),
+            heatmap(1.0 / (COST_SCALE - 1.0))
+        );
+        assert_eq!(heatmap(0.5), Color::new_color(0.0, 1.0, 0.0));
+        assert_eq!(heatmap(2.0), Color::new_color(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    // Modes are found back from their names
+    fn debug_mode_names() {
+        for mode in DEBUG_MODES {
+            assert_eq!(DebugMode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(DebugMode::from_name("shaded"), None);
+    }
+}
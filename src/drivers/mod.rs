@@ -1 +1,2 @@
-pub mod minifb_driver;
\ No newline at end of file
+pub mod minifb_driver;
+pub mod viewer;
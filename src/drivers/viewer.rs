@@ -0,0 +1,407 @@
+use std::{
+    f64::consts::PI,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
+
+use crate::{
+    camera::{Camera, Integrator, Projection},
+    canvas::Canvas,
+    debug::{DEBUG_MODES, DebugMode},
+    drivers::minifb_driver,
+    io::yaml::{camera_to_yaml, scene_files, try_parse},
+    progressive::{Progressive, ProgressiveRenderer},
+    world::World,
+};
+
+/// Passes of the render once the camera stands still
+const PREVIEW: Progressive = Progressive {
+    block_size: 16,
+    samples: 8,
+};
+/// Single pass at a fraction of the resolution while the camera moves
+const MOVING: Progressive = Progressive {
+    block_size: 1,
+    samples: 1,
+};
+/// Divider of the image size while the camera moves
+const MOVING_SCALE: usize = 4;
+/// Time without input before the full resolution render starts
+const IDLE_DELAY: Duration = Duration::from_millis(200);
+/// Radians turned per pixel the mouse is dragged
+const ORBIT_SPEED: f64 = 0.01;
+/// Relative change of the field of view per scroll unit
+const ZOOM_SPEED: f64 = 0.05;
+/// Time between two checks of the scene files
+const WATCH_DELAY: Duration = Duration::from_millis(500);
+
+/// Files of a scene and their modification time when last checked
+pub struct SceneWatcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl SceneWatcher {
+    /// Watches the scene file and the files it includes
+    pub fn new_scene_watcher(path: &Path) -> SceneWatcher {
+        SceneWatcher {
+            files: watched(path),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.files[0].0
+    }
+
+    /// Whether a file changed since the last call, the includes being looked up again then
+    pub fn changed(&mut self) -> bool {
+        let changed = self
+            .files
+            .iter()
+            .any(|(path, time)| modified(path) != *time);
+        if changed {
+            self.files = watched(&self.files[0].0);
+        }
+        changed
+    }
+}
+
+fn watched(path: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut files = scene_files(path);
+    if files.is_empty() {
+        files.push(path.to_path_buf());
+    }
+    files
+        .into_iter()
+        .map(|file| {
+            let time = modified(&file);
+            (file, time)
+        })
+        .collect()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|data| data.modified()).ok()
+}
+
+/// Window showing a progressive render of the world. W/A/S/D move the camera, Q/E lower and
+/// raise it, dragging the mouse orbits around the point it looks at, the wheel changes the
+/// field of view, or the width of an orthographic view, and P prints the camera block of the
+/// current view. Right clicking a pixel prints how its color is computed and M goes through
+/// the debug modes. The scene is rendered again when its file, or a file it includes, is saved.
+pub struct Viewer {
+    camera: Camera,
+    world: Arc<World>,
+    window: Window,
+    renderer: ProgressiveRenderer,
+    buffer: Vec<u32>,
+    buffer_size: (usize, usize),
+    last_mouse: Option<(f32, f32)>,
+    picking: bool,
+    /// False colors shown instead of the integrator of the camera
+    debug_mode: Option<DebugMode>,
+    /// Time of the last move while the image is rendered at a reduced resolution
+    moved_at: Option<Instant>,
+    scene: Option<SceneWatcher>,
+    /// Camera of the scene file, the view moved around is kept while it is not edited
+    scene_camera: Camera,
+    watched_at: Instant,
+}
+
+impl Viewer {
+    pub fn new_viewer(camera: Camera, world: World) -> Viewer {
+        let window = minifb_driver::new_window(&Canvas::new_canvas(camera.hsize, camera.vsize));
+        let world = Arc::new(world);
+        let renderer = ProgressiveRenderer::start(camera.clone(), Arc::clone(&world), PREVIEW);
+
+        Viewer {
+            buffer: vec![0; camera.hsize * camera.vsize],
+            buffer_size: (camera.hsize, camera.vsize),
+            scene_camera: camera.clone(),
+            camera,
+            world,
+            window,
+            renderer,
+            last_mouse: None,
+            picking: false,
+            debug_mode: None,
+            moved_at: None,
+            scene: None,
+            watched_at: Instant::now(),
+        }
+    }
+
+    /// Reloads the world and the camera from `path` each time it is modified
+    pub fn with_scene_file(mut self, path: &Path) -> Self {
+        self.scene = Some(SceneWatcher::new_scene_watcher(path));
+        self.update_title(None);
+        self
+    }
+
+    pub fn with_debug_mode(mut self, debug_mode: Option<DebugMode>) -> Self {
+        self.debug_mode = debug_mode;
+        self.update_title(None);
+        self.restart(self.camera.clone(), PREVIEW);
+        self
+    }
+
+    /// Shows the render and handles the input until the window is closed or Escape pressed
+    pub fn run(mut self) {
+        self.window.set_target_fps(60);
+        let mut last_frame = Instant::now();
+
+        while self.window.is_open() && !self.window.is_key_down(Key::Escape) {
+            let elapsed = last_frame.elapsed().as_secs_f64();
+            last_frame = Instant::now();
+
+            if self.navigate(elapsed) {
+                self.moved_at = Some(Instant::now());
+                let camera = self.camera.clone().with_size(
+                    (self.camera.hsize / MOVING_SCALE).max(1),
+                    (self.camera.vsize / MOVING_SCALE).max(1),
+                );
+                self.restart(camera, MOVING);
+            } else if self.moved_at.is_some_and(|at| at.elapsed() > IDLE_DELAY) {
+                self.moved_at = None;
+                self.restart(self.camera.clone(), PREVIEW);
+            }
+
+            let picking = self.window.get_mouse_down(MouseButton::Right);
+            if picking && !self.picking {
+                self.pick();
+            }
+            self.picking = picking;
+
+            if self.watched_at.elapsed() > WATCH_DELAY {
+                self.watched_at = Instant::now();
+                self.reload();
+            }
+
+            if self.window.is_key_pressed(Key::M, KeyRepeat::No) {
+                // shaded after the last mode
+                let next = match self.debug_mode {
+                    None => Some(0),
+                    Some(mode) => DEBUG_MODES.iter().position(|m| *m == mode).map(|i| i + 1),
+                };
+                self.debug_mode = next.and_then(|i| DEBUG_MODES.get(i).copied());
+                self.update_title(None);
+                self.restart(self.camera.clone(), PREVIEW);
+            }
+
+            if self.window.is_key_pressed(Key::P, KeyRepeat::No) {
+                println!("{}", camera_to_yaml(&self.camera));
+            }
+
+            if let Some((canvas, _)) = self.renderer.latest_pass() {
+                // the false colors are shown as they are
+                let canvas = match self.debug_mode {
+                    Some(_) => canvas,
+                    None => self.camera.tone_mapping.apply_canvas(&canvas),
+                };
+                self.buffer = minifb_driver::buffer_from_canvas(&canvas);
+                self.buffer_size = (canvas.width, canvas.height);
+            }
+
+            // We unwrap here as we want this code to exit if it fails. Real applications may want to handle this in a different way
+            self.window
+                .update_with_buffer(&self.buffer, self.buffer_size.0, self.buffer_size.1)
+                .unwrap();
+        }
+    }
+
+    /// Prints the record of the ray through the pixel under the mouse
+    fn pick(&self) {
+        let (x, y) = match self.window.get_mouse_pos(MouseMode::Discard) {
+            Some(position) => position,
+            None => return,
+        };
+        let (width, height) = self.window.get_size();
+        let px = (x as usize * self.camera.hsize / width).min(self.camera.hsize - 1);
+        let py = (y as usize * self.camera.vsize / height).min(self.camera.vsize - 1);
+
+        println!("pixel ({px}, {py})");
+        print!("{}", self.camera.inspect_pixel(&self.world, px, py));
+    }
+
+    /// Parses the scene again if it was modified, the errors being shown in the title
+    fn reload(&mut self) {
+        let path = match self.scene.as_mut() {
+            Some(scene) => match scene.changed() {
+                true => scene.path().to_path_buf(),
+                false => return,
+            },
+            None => return,
+        };
+
+        match try_parse(&path) {
+            Ok((world, camera)) => {
+                let edited = camera.view != self.scene_camera.view
+                    || camera.field_of_view != self.scene_camera.field_of_view
+                    || camera.projection != self.scene_camera.projection
+                    || (camera.hsize, camera.vsize)
+                        != (self.scene_camera.hsize, self.scene_camera.vsize);
+                self.scene_camera = camera.clone();
+                self.camera = if edited {
+                    camera
+                } else {
+                    camera
+                        .with_view(self.camera.view.clone())
+                        .with_fov(self.camera.field_of_view)
+                        .with_projection(self.camera.projection)
+                };
+                self.world = Arc::new(world);
+                self.update_title(None);
+                self.restart(self.camera.clone(), PREVIEW);
+            }
+            Err(message) => self.update_title(Some(&message)),
+        }
+    }
+
+    /// Shows the scene and the mode in the title, or the error of the scene
+    fn update_title(&mut self, error: Option<&str>) {
+        let scene = match &self.scene {
+            Some(scene) => scene.path().display().to_string(),
+            None => "Test".to_string(),
+        };
+        let status = match (error, self.debug_mode) {
+            (Some(error), _) => error.to_string(),
+            (None, Some(mode)) => format!("{} - ESC to exit", mode.name()),
+            (None, None) => "ESC to exit".to_string(),
+        };
+        self.window.set_title(&format!("{scene} - {status}"));
+    }
+
+    fn restart(&mut self, camera: Camera, settings: Progressive) {
+        let camera = match self.debug_mode {
+            Some(mode) => camera.with_integrator(Integrator::Debug(mode)),
+            None => camera,
+        };
+        self.renderer.stop();
+        self.renderer = ProgressiveRenderer::start(camera, Arc::clone(&self.world), settings);
+    }
+
+    /// 1.0 when only `positive` is held, -1.0 when only `negative` is
+    fn key_axis(&self, positive: Key, negative: Key) -> f64 {
+        let mut axis = 0.0;
+        if self.window.is_key_down(positive) {
+            axis += 1.0;
+        }
+        if self.window.is_key_down(negative) {
+            axis -= 1.0;
+        }
+        axis
+    }
+
+    /// Applies the input of the last `elapsed` seconds to the camera, returns whether it moved
+    fn navigate(&mut self, elapsed: f64) -> bool {
+        let mut view = self.camera.view.clone();
+        let mut field_of_view = self.camera.field_of_view;
+        let mut projection = self.camera.projection;
+        let mut moved = false;
+
+        // the distance to the target is covered in a second
+        let step = (view.to.clone() - view.from.clone()).magnitude() * elapsed;
+        let forward = self.key_axis(Key::W, Key::S) * step;
+        let right = self.key_axis(Key::D, Key::A) * step;
+        let up = self.key_axis(Key::E, Key::Q) * step;
+        if forward != 0.0 || right != 0.0 || up != 0.0 {
+            view.translate(forward, right, up);
+            moved = true;
+        }
+
+        let mouse = self.window.get_mouse_pos(MouseMode::Pass);
+        if let (Some((last_x, last_y)), Some((x, y))) = (self.last_mouse, mouse)
+            && self.window.get_mouse_down(MouseButton::Left)
+            && (x, y) != (last_x, last_y)
+        {
+            view.orbit(
+                -(x - last_x) as f64 * ORBIT_SPEED,
+                (y - last_y) as f64 * ORBIT_SPEED,
+            );
+            moved = true;
+        }
+        self.last_mouse = mouse;
+
+        if let Some((_, scroll)) = self.window.get_scroll_wheel()
+            && scroll != 0.0
+        {
+            let zoom = (-scroll as f64 * ZOOM_SPEED).exp();
+            match &mut projection {
+                Projection::Perspective => {
+                    field_of_view = (field_of_view * zoom).clamp(0.1, 3.0);
+                }
+                Projection::Fisheye => {
+                    field_of_view = (field_of_view * zoom).clamp(0.1, 2.0 * PI);
+                }
+                Projection::Orthographic { view_width } => *view_width *= zoom,
+                // the panoramas always cover every direction around
+                Projection::Equirectangular | Projection::Cylindrical => {}
+            }
+            moved = true;
+        }
+
+        if moved {
+            self.camera.set_view(view);
+            self.camera = self
+                .camera
+                .clone()
+                .with_fov(field_of_view)
+                .with_projection(projection);
+        }
+        moved
+    }
+}
+
+#[cfg(test)]
+mod viewer_tests {
+    use super::*;
+
+    #[test]
+    // Saving a scene file is noticed once
+    fn scene_watcher() {
+        let path = std::env::temp_dir().join("rustracer_scene_watcher.yml");
+        fs::write(&path, "[]").unwrap();
+
+        let mut watcher = SceneWatcher::new_scene_watcher(&path);
+        assert!(!watcher.changed());
+
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+    }
+
+    #[test]
+    // Saving a file included by the scene is noticed, even one included since the last reload
+    fn scene_watcher_includes() {
+        let directory = std::env::temp_dir();
+        let path = directory.join("rustracer_scene_watcher_includes.yml");
+        let first = directory.join("rustracer_scene_watcher_first.yml");
+        let second = directory.join("rustracer_scene_watcher_second.yml");
+        fs::write(&first, "[]").unwrap();
+        fs::write(&second, "[]").unwrap();
+        fs::write(&path, "- include: rustracer_scene_watcher_first.yml").unwrap();
+
+        let touch = |path: &Path, seconds: u64| {
+            let file = fs::File::options().write(true).open(path).unwrap();
+            file.set_modified(SystemTime::now() + Duration::from_secs(seconds))
+                .unwrap();
+        };
+
+        let mut watcher = SceneWatcher::new_scene_watcher(&path);
+        touch(&first, 10);
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        fs::write(&first, "- include: rustracer_scene_watcher_second.yml").unwrap();
+        touch(&first, 20);
+        assert!(watcher.changed());
+        touch(&second, 10);
+        assert!(watcher.changed());
+        assert_eq!(watcher.path(), path);
+    }
+}
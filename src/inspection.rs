@@ -0,0 +1,377 @@
+use std::fmt;
+
+use uuid::Uuid;
+
+use crate::{
+    camera::Camera,
+    color::{self, Color},
+    ray::Ray,
+    reflection::{MAX_RECURTION, Material, PointLight},
+    shape::shape::Shape,
+    tuple::Tuple,
+    world::{Computation, World},
+};
+
+/// Where a traced ray comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RayKind {
+    Camera,
+    Reflection,
+    Refraction,
+}
+
+/// Hook following `World::color_at_with` through the bounces of a ray. The calls come in
+/// the order the rays are traced, a bounce being traced entirely before its parent is shaded.
+pub trait Recorder {
+    /// A ray is traced, from the camera unless announced by `bounce`
+    fn ray(&mut self, _ray: &Ray) {}
+    /// The current ray hits a surface
+    fn hit(&mut self, _comps: &Computation) {}
+    /// Part of `light` reaching the current hit
+    fn light(&mut self, _light: &PointLight, _visibility: Color) {}
+    /// Diffuse, ambient included, and specular light the last announced light adds to
+    /// the current hit, once shadowed
+    fn lit(&mut self, _diffuse: Color, _specular: Color) {}
+    /// The next ray is traced from the current hit
+    fn bounce(&mut self, _kind: RayKind) {}
+    /// Parts of the color of the current hit, `reflectance` being the Fresnel weight of
+    /// the reflection against the refraction
+    fn shaded(
+        &mut self,
+        _surface: Color,
+        _reflected: Color,
+        _refracted: Color,
+        _reflectance: Option<f64>,
+    ) {
+    }
+    /// Color of the current ray, once through the media and the fog
+    fn color(&mut self, _color: Color) {}
+}
+
+/// Records nothing
+impl Recorder for () {}
+
+/// A light seen from a hit
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightRecord {
+    pub position: Tuple,
+    /// White when the light is not shadowed, black when it is completely
+    pub visibility: Color,
+}
+
+/// Everything about the hit of a ray taken into account by its color
+#[derive(Debug, Clone)]
+pub struct HitRecord {
+    /// Index of the object in `World::objects`
+    pub index: usize,
+    pub id: Uuid,
+    pub shape: Shape,
+    pub t: f64,
+    pub point: Tuple,
+    pub normal: Tuple,
+    pub inside: bool,
+    pub n1: f64,
+    pub n2: f64,
+    pub material: Material,
+    pub lights: Vec<LightRecord>,
+    /// Emission and lighting of the surface
+    pub surface: Color,
+    pub reflected: Color,
+    pub refracted: Color,
+    pub reflectance: Option<f64>,
+}
+
+/// A traced ray, what it hit and the rays traced from there
+#[derive(Debug, Clone)]
+pub struct RayRecord {
+    pub kind: RayKind,
+    pub ray: Ray,
+    pub hit: Option<HitRecord>,
+    pub bounces: Vec<RayRecord>,
+    pub color: Color,
+}
+
+/// Recorder building the tree of the rays traced from a camera ray
+pub struct Inspector<'a> {
+    world: &'a World,
+    /// Rays being traced, the camera ray first
+    stack: Vec<RayRecord>,
+    next_kind: RayKind,
+    root: Option<RayRecord>,
+}
+
+impl Inspector<'_> {
+    pub fn new_inspector(world: &World) -> Inspector<'_> {
+        Inspector {
+            world,
+            stack: vec![],
+            next_kind: RayKind::Camera,
+            root: None,
+        }
+    }
+
+    /// Record of the camera ray, once its color is known
+    pub fn record(self) -> Option<RayRecord> {
+        self.root
+    }
+
+    fn current_hit(&mut self) -> Option<&mut HitRecord> {
+        self.stack.last_mut().and_then(|record| record.hit.as_mut())
+    }
+}
+
+impl Recorder for Inspector<'_> {
+    fn ray(&mut self, ray: &Ray) {
+        self.stack.push(RayRecord {
+            kind: self.next_kind,
+            ray: ray.clone(),
+            hit: None,
+            bounces: vec![],
+            color: color::BLACK,
+        });
+        self.next_kind = RayKind::Camera;
+    }
+
+    fn hit(&mut self, comps: &Computation) {
+        let index = self
+            .world
+            .objects
+            .iter()
+            .position(|object| object.id == comps.object.id)
+            .unwrap();
+        if let Some(record) = self.stack.last_mut() {
+            record.hit = Some(HitRecord {
+                index,
+                id: comps.object.id,
+                shape: comps.object.shape.clone(),
+                t: comps.t,
+                point: comps.point.clone(),
+                normal: comps.normalv.clone(),
+                inside: comps.inside,
+                n1: comps.n1,
+                n2: comps.n2,
+                material: comps.object.get_material(),
+                lights: vec![],
+                surface: color::BLACK,
+                reflected: color::BLACK,
+                refracted: color::BLACK,
+                reflectance: None,
+            });
+        }
+    }
+
+    fn light(&mut self, light: &PointLight, visibility: Color) {
+        if let Some(hit) = self.current_hit() {
+            hit.lights.push(LightRecord {
+                position: light.position.clone(),
+                visibility,
+            });
+        }
+    }
+
+    fn bounce(&mut self, kind: RayKind) {
+        self.next_kind = kind;
+    }
+
+    fn shaded(
+        &mut self,
+        surface: Color,
+        reflected: Color,
+        refracted: Color,
+        reflectance: Option<f64>,
+    ) {
+        if let Some(hit) = self.current_hit() {
+            hit.surface = surface;
+            hit.reflected = reflected;
+            hit.refracted = refracted;
+            hit.reflectance = reflectance;
+        }
+    }
+
+    fn color(&mut self, color: Color) {
+        let mut record = match self.stack.pop() {
+            Some(record) => record,
+            None => return,
+        };
+        record.color = color;
+        match self.stack.last_mut() {
+            Some(parent) => parent.bounces.push(record),
+            None => self.root = Some(record),
+        }
+    }
+}
+
+impl World {
+    /// Traces `ray` like `color_at`, recording every step
+    pub fn inspect(&self, ray: &Ray) -> RayRecord {
+        let mut inspector = Inspector::new_inspector(self);
+        self.color_at_with(ray, MAX_RECURTION, &mut inspector);
+        inspector.record().unwrap()
+    }
+}
+
+impl Camera {
+    /// Record of the ray through the center of a pixel, traced by the Whitted integrator
+    /// when the shutter opens
+    pub fn inspect_pixel(&self, world: &World, px: usize, py: usize) -> RayRecord {
+        let (lens, px, py) = self.lens_for_pixel(px, py);
+        world.inspect(&lens.ray_for_pixel(px, py).with_time(self.shutter.open))
+    }
+}
+
+impl RayRecord {
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "    ".repeat(depth);
+        let kind = match self.kind {
+            RayKind::Camera => "camera",
+            RayKind::Reflection => "reflection",
+            RayKind::Refraction => "refraction",
+        };
+        writeln!(
+            f,
+            "{indent}{kind} ray from {:.3} toward {:.3}",
+            self.ray.origin, self.ray.direction
+        )?;
+
+        match &self.hit {
+            None => writeln!(f, "{indent}  no hit")?,
+            Some(hit) => {
+                // the variant name, without the geometry
+                let shape = format!("{:?}", hit.shape);
+                let shape = shape.split(['(', ' ', '{']).next().unwrap_or_default();
+                writeln!(
+                    f,
+                    "{indent}  hit object #{} ({shape} {}) at t = {:.4}",
+                    hit.index, hit.id, hit.t
+                )?;
+                writeln!(
+                    f,
+                    "{indent}  point {:.4}, normal {:.4}, inside: {}, n1 = {:.3}, n2 = {:.3}",
+                    hit.point, hit.normal, hit.inside, hit.n1, hit.n2
+                )?;
+
+                let m = &hit.material;
+                writeln!(
+                    f,
+                    "{indent}  material: color {:.3}{}, ambient {}, diffuse {}, specular {}, shininess {}",
+                    m.color,
+                    if m.pattern.is_some() {
+                        " (patterned)"
+                    } else {
+                        ""
+                    },
+                    m.ambient,
+                    m.diffuse,
+                    m.specular,
+                    m.shininess
+                )?;
+                writeln!(
+                    f,
+                    "{indent}            reflective {}, transparency {}, refractive index {}, emission {:.3}",
+                    m.reflective, m.transparency, m.refractive_index, m.emission
+                )?;
+
+                for light in &hit.lights {
+                    let status = if light.visibility == color::WHITE {
+                        "lit".to_string()
+                    } else if light.visibility == color::BLACK {
+                        "shadowed".to_string()
+                    } else {
+                        format!("partly shadowed {:.3}", light.visibility)
+                    };
+                    writeln!(f, "{indent}  light at {:.3}: {status}", light.position)?;
+                }
+
+                write!(
+                    f,
+                    "{indent}  surface {:.4} + reflected {:.4} + refracted {:.4}",
+                    hit.surface, hit.reflected, hit.refracted
+                )?;
+                match hit.reflectance {
+                    Some(reflectance) => writeln!(f, ", reflectance {reflectance:.4}")?,
+                    None => writeln!(f)?,
+                }
+            }
+        }
+        writeln!(f, "{indent}  color {:.4}", self.color)?;
+
+        for bounce in &self.bounces {
+            bounce.write(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RayRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod inspection_tests {
+    use super::*;
+    use crate::{reflection::Material, shape::object::Object, transformation::create_translation};
+
+    #[test]
+    // The record of a ray holds what its color is made of
+    fn inspect_hit() {
+        let w = World::default_world();
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        let record = w.inspect(&r);
+        assert_eq!(record.kind, RayKind::Camera);
+        assert_eq!(record.color, w.color_at(&r, MAX_RECURTION));
+        assert!(record.bounces.is_empty());
+
+        let hit = record.hit.unwrap();
+        assert_eq!(hit.index, 0);
+        assert_eq!(hit.t, 4.0);
+        assert_eq!(hit.normal, Tuple::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(hit.lights.len(), 1);
+        assert_eq!(hit.lights[0].visibility, color::WHITE);
+        assert_eq!(hit.surface, record.color);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        assert!(w.inspect(&r).hit.is_none());
+    }
+
+    #[test]
+    // Reflections and refractions are recorded as bounces of the hit they come from
+    fn inspect_bounces() {
+        let mut w = World::default_world();
+        let mut floor = Object::new_plane();
+        floor.set_transform(&create_translation(0.0, -1.0, 0.0));
+        floor.material = Material::default_material()
+            .with_reflective(0.5)
+            .with_transparency(0.5)
+            .with_refractive_index(1.5);
+        w.add_object(floor);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -3.0),
+            Tuple::new_vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let record = w.inspect(&r);
+        assert_eq!(record.color, w.color_at(&r, MAX_RECURTION));
+
+        let hit = record.hit.as_ref().unwrap();
+        assert_eq!(hit.index, 2);
+        assert_eq!((hit.n1, hit.n2), (1.0, 1.5));
+        assert!(hit.reflectance.is_some());
+        assert_eq!(record.bounces.len(), 2);
+        assert_eq!(record.bounces[0].kind, RayKind::Reflection);
+        assert_eq!(record.bounces[1].kind, RayKind::Refraction);
+        assert_eq!(record.bounces[0].color * 0.5, hit.reflected);
+
+        let text = record.to_string();
+        assert!(text.contains("hit object #2 (Plane"));
+        assert!(text.contains("\n    reflection ray"));
+    }
+}
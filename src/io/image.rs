@@ -0,0 +1,153 @@
+use std::{fs, io, path::Path};
+
+use crate::{canvas::Canvas, color::Color};
+
+/// Binary PPM image of `canvas`, the channels clamped to `[0, 1]`
+pub fn canvas_to_ppm(canvas: &Canvas) -> Vec<u8> {
+    let mut ppm = format!("P6\n{} {}\n255\n", canvas.width, canvas.height).into_bytes();
+    for color in &canvas.pixels {
+        let (red, green, blue) = color.normalise();
+        ppm.extend([red, green, blue]);
+    }
+    ppm
+}
+
+/// Portable float map of `canvas`: little endian 32 bit floats, bottom row first
+pub fn canvas_to_pfm(canvas: &Canvas) -> Vec<u8> {
+    let mut pfm = format!("PF\n{} {}\n-1.0\n", canvas.width, canvas.height).into_bytes();
+    for row in canvas.pixels.chunks(canvas.width).rev() {
+        for color in row {
+            let (red, green, blue) = color.channels();
+            for channel in [red, green, blue] {
+                pfm.extend((channel as f32).to_le_bytes());
+            }
+        }
+    }
+    pfm
+}
+
+/// Radiance RGBE image of `canvas`, negative channels written as 0. The rows are run length
+/// encoded when their width allows it, without looking for runs.
+pub fn canvas_to_hdr(canvas: &Canvas) -> Vec<u8> {
+    let mut hdr = format!(
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        canvas.height, canvas.width
+    )
+    .into_bytes();
+
+    let encoded = (8..0x8000).contains(&canvas.width);
+    for row in canvas.pixels.chunks(canvas.width) {
+        let rgbe: Vec<[u8; 4]> = row.iter().map(to_rgbe).collect();
+        if !encoded {
+            hdr.extend(rgbe.iter().flatten());
+            continue;
+        }
+
+        hdr.extend([2, 2, (canvas.width >> 8) as u8, (canvas.width & 0xff) as u8]);
+        for channel in 0..4 {
+            for chunk in rgbe.chunks(128) {
+                hdr.push(chunk.len() as u8);
+                hdr.extend(chunk.iter().map(|pixel| pixel[channel]));
+            }
+        }
+    }
+    hdr
+}
+
+/// Shared exponent encoding of `color`
+fn to_rgbe(color: &Color) -> [u8; 4] {
+    let (red, green, blue) = color.channels();
+    let (red, green, blue) = (red.max(0.0), green.max(0.0), blue.max(0.0));
+    let max = red.max(green).max(blue);
+    if max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // max = mantissa * 2^exponent with the mantissa in [0.5, 1)
+    let exponent = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2.0_f64.powi(exponent);
+    [
+        (red * scale) as u8,
+        (green * scale) as u8,
+        (blue * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+/// Whether the format of `path` keeps the values outside `[0, 1]`, its extension being
+/// `pfm` or `hdr`
+pub fn is_float_image(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("pfm" | "hdr")
+    )
+}
+
+/// Writes `canvas` in the format given by the extension of `path`, PPM by default
+pub fn write_image(canvas: &Canvas, path: &Path) -> io::Result<()> {
+    let data = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("pfm") => canvas_to_pfm(canvas),
+        Some("hdr") => canvas_to_hdr(canvas),
+        _ => canvas_to_ppm(canvas),
+    };
+    fs::write(path, data)
+}
+
+#[cfg(test)]
+mod image_tests {
+    use super::*;
+
+    #[test]
+    // The header gives the size, then each pixel takes three bytes
+    fn ppm_pixels() {
+        let mut c = Canvas::new_canvas(2, 1);
+        c.set_pixel_color(0, 0, Color::new_color(1.5, 0.0, -0.5));
+        c.set_pixel_color(1, 0, Color::new_color(0.0, 0.5, 1.0));
+
+        let ppm = canvas_to_ppm(&c);
+        assert!(ppm.starts_with(b"P6\n2 1\n255\n"));
+        assert_eq!(&ppm[11..], &[255, 0, 0, 0, 128, 255]);
+    }
+
+    #[test]
+    // Float maps keep the values out of range, bottom row first
+    fn pfm_pixels() {
+        let mut c = Canvas::new_canvas(1, 2);
+        c.set_pixel_color(0, 0, Color::new_color(4.5, 0.0, -0.5));
+
+        let pfm = canvas_to_pfm(&c);
+        let header = b"PF\n1 2\n-1.0\n";
+        assert!(pfm.starts_with(header));
+        assert_eq!(pfm.len(), header.len() + 2 * 3 * 4);
+
+        let float = |i: usize| {
+            let start = header.len() + 4 * i;
+            f32::from_le_bytes(pfm[start..start + 4].try_into().unwrap())
+        };
+        assert_eq!([float(0), float(1), float(2)], [0.0, 0.0, 0.0]);
+        assert_eq!([float(3), float(4), float(5)], [4.5, 0.0, -0.5]);
+    }
+
+    #[test]
+    // Radiance pixels share an exponent, long rows are run length encoded
+    fn hdr_pixels() {
+        assert_eq!(to_rgbe(&Color::new_color(1.0, 0.5, 0.0)), [128, 64, 0, 129]);
+        assert_eq!(to_rgbe(&Color::new_color(6.0, -1.0, 0.0)), [192, 0, 0, 131]);
+        assert_eq!(to_rgbe(&Color::new_color(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n";
+        let c = Canvas::new_canvas_with_color(2, 1, Color::new_color(1.0, 0.5, 0.0));
+        let hdr = canvas_to_hdr(&c);
+        assert_eq!(&hdr[header.len()..], &[128, 64, 0, 129, 128, 64, 0, 129]);
+
+        let c = Canvas::new_canvas_with_color(200, 1, Color::new_color(1.0, 0.5, 0.0));
+        let hdr = canvas_to_hdr(&c);
+        let row = &hdr[hdr.len() - (4 + 4 * (200 + 2))..];
+        assert_eq!(&row[..4], &[2, 2, 0, 200]);
+        assert_eq!(&row[4..6], &[128, 128]);
+        assert_eq!(row[4 + 129], 72);
+
+        assert!(is_float_image(Path::new("render.hdr")));
+        assert!(!is_float_image(Path::new("render.ppm")));
+    }
+}
@@ -1,17 +1,31 @@
 /* ---------------------------------------------------------------------------------------------- */
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 use yaml_rust::{Yaml, YamlLoader, yaml};
 
 use crate::{
-    camera::Camera,
-    color::Color,
-    matrix::Matrix,
+    animation::{Animation, Interpolation},
+    aov::Aov,
+    camera::{Camera, Integrator, Projection, View},
+    color::{self, Color},
+    debug::DebugMode,
+    matrix::Matrix4,
+    media::{Fog, Medium},
+    motion::{Motion, Shutter},
+    occlusion::AmbientOcclusion,
     pattern::Pattern,
-    reflection::{Material, PointLight},
+    reflection::{Attenuation, Material, PointLight},
     shape::object::Object,
+    stereo::{Stereo, StereoLayout},
+    tile::{Tile, TileOrder},
+    tonemap::{ToneMap, ToneMapping},
     transformation::{self, *},
     tuple::Tuple,
+    world::World,
 };
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -20,26 +34,33 @@ type Definitions<'a> = HashMap<&'a Yaml, Yaml>;
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn get_definitions(yaml: &Yaml) -> Definitions {
+fn get_definitions(yaml: &Yaml) -> Result<Definitions<'_>, String> {
     let mut definitions = HashMap::new();
 
-    for elem in yaml.as_vec().unwrap().iter() {
-        let hash = elem.as_hash().unwrap();
+    for elem in mk_array(yaml)?.iter() {
+        let hash = mk_hash(elem)?;
 
         if let Some(definition_key) = hash.get(&Yaml::from_str("define")) {
-            let definition_value = hash.get(&Yaml::from_str("value")).unwrap();
+            let context = |error| format!("Definition {:?}: {}", definition_key, error);
+            let definition_value = hash
+                .get(&Yaml::from_str("value"))
+                .ok_or_else(|| context("Missing key: value".to_string()))?;
 
             // Does not handle recursive "extend"
             let definition_value = match hash.get(&Yaml::from_str("extend")) {
                 Some(parent) => {
                     if let Some(definition_value_hash) = definition_value.as_hash() {
-                        let mut parent_hash = get_hash(&definitions, parent).clone();
+                        let mut parent_hash =
+                            get_hash(&definitions, parent).map_err(context)?.clone();
                         parent_hash.extend(definition_value_hash.clone().into_iter());
 
                         Yaml::Hash(parent_hash)
                     } else {
                         // To implement if encountered in the wild (like array extension)
-                        panic!("Extension unsupported for {:?}", definition_value);
+                        return Err(context(format!(
+                            "Extension unsupported for {:?}",
+                            definition_value
+                        )));
                     }
                 }
                 None => definition_value.clone(),
@@ -49,320 +70,589 @@ fn get_definitions(yaml: &Yaml) -> Definitions {
         }
     }
 
-    definitions
+    Ok(definitions)
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn get_hash<'a>(definitions: &'a Definitions, yaml: &'a Yaml) -> &'a yaml::Hash {
+fn get_hash<'a>(definitions: &'a Definitions, yaml: &'a Yaml) -> Result<&'a yaml::Hash, String> {
     match yaml.as_hash() {
-        Some(hash) => hash,
-        None => definitions
-            .get(yaml)
-            .unwrap_or_else(|| panic!("Definition {:?} not found", yaml))
-            .as_hash()
-            .unwrap(),
+        Some(hash) => Ok(hash),
+        None => mk_hash(
+            definitions
+                .get(yaml)
+                .ok_or_else(|| format!("Definition {:?} not found", yaml))?,
+        ),
     }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn get_array<'a>(definitions: &'a Definitions, yaml: &'a Yaml) -> &'a yaml::Array {
+fn get_array<'a>(definitions: &'a Definitions, yaml: &'a Yaml) -> Result<&'a yaml::Array, String> {
     match yaml.as_vec() {
-        Some(hash) => hash,
-        None => definitions
-            .get(yaml)
-            .unwrap_or_else(|| panic!("Definition {:?} not found", yaml))
-            .as_vec()
-            .unwrap(),
+        Some(array) => Ok(array),
+        None => mk_array(
+            definitions
+                .get(yaml)
+                .ok_or_else(|| format!("Definition {:?} not found", yaml))?,
+        ),
     }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_bool(yaml: &Yaml) -> bool {
+/// Value of `key` in `hash` read by `mk`, the errors naming the key
+fn mk_from_key<'a, T>(
+    hash: &'a yaml::Hash,
+    key: &str,
+    mk: impl FnOnce(&'a Yaml) -> Result<T, String>,
+) -> Result<Option<T>, String> {
+    hash.get(&Yaml::from_str(key))
+        .map(|yaml| mk(yaml).map_err(|error| format!("{}: {}", key, error)))
+        .transpose()
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+/// Same as `mk_from_key`, the key being mandatory
+fn mk_required<'a, T>(
+    hash: &'a yaml::Hash,
+    key: &str,
+    mk: impl FnOnce(&'a Yaml) -> Result<T, String>,
+) -> Result<T, String> {
+    mk_from_key(hash, key, mk)?.ok_or_else(|| format!("Missing key: {}", key))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_str(yaml: &Yaml) -> Result<&str, String> {
+    yaml.as_str()
+        .ok_or_else(|| format!("Expected string, got: {:?}", yaml))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_array(yaml: &Yaml) -> Result<&yaml::Array, String> {
+    yaml.as_vec()
+        .ok_or_else(|| format!("Expected list, got: {:?}", yaml))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_hash(yaml: &Yaml) -> Result<&yaml::Hash, String> {
+    yaml.as_hash()
+        .ok_or_else(|| format!("Expected mapping, got: {:?}", yaml))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_bool(yaml: &Yaml) -> Result<bool, String> {
     match yaml.as_bool() {
-        None => panic!("Expected boolean, got: {:?}", yaml),
-        Some(value) => value,
+        None => Err(format!("Expected boolean, got: {:?}", yaml)),
+        Some(value) => Ok(value),
     }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_bool_from_key(hash: &yaml::Hash, key: &str) -> Option<bool> {
-    hash.get(&Yaml::from_str(key)).map(mk_bool)
+fn mk_bool_from_key(hash: &yaml::Hash, key: &str) -> Result<Option<bool>, String> {
+    mk_from_key(hash, key, mk_bool)
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_usize(yaml: &Yaml) -> usize {
+fn mk_usize(yaml: &Yaml) -> Result<usize, String> {
     match yaml.as_i64() {
-        None => panic!("Expected integer, got: {:?}", yaml),
-        Some(value) => value as usize,
+        Some(value) if value >= 0 => Ok(value as usize),
+        _ => Err(format!("Expected positive integer, got: {:?}", yaml)),
     }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_usize_from_key(hash: &yaml::Hash, key: &str) -> Option<usize> {
-    hash.get(&Yaml::from_str(key)).map(mk_usize)
+fn mk_usize_from_key(hash: &yaml::Hash, key: &str) -> Result<Option<usize>, String> {
+    mk_from_key(hash, key, mk_usize)
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_f64(yaml: &Yaml) -> f64 {
+fn mk_f64(yaml: &Yaml) -> Result<f64, String> {
     match yaml.as_f64() {
         None => match yaml.as_i64() {
-            None => panic!("Expected scalar, got: {:?}", yaml),
-            Some(value) => value as f64,
+            None => Err(format!("Expected scalar, got: {:?}", yaml)),
+            Some(value) => Ok(value as f64),
         },
-        Some(value) => value,
+        Some(value) => Ok(value),
     }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_f64_from_key(hash: &yaml::Hash, key: &str) -> Option<f64> {
-    hash.get(&Yaml::from_str(key)).map(mk_f64)
+fn mk_f64_from_key(hash: &yaml::Hash, key: &str) -> Result<Option<f64>, String> {
+    mk_from_key(hash, key, mk_f64)
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_color(yaml: &Yaml) -> Color {
-    let rgb = yaml.as_vec().unwrap();
-    assert_eq!(rgb.len(), 3);
-
-    Color::new_color(mk_f64(&rgb[0]), mk_f64(&rgb[1]), mk_f64(&rgb[2]))
-}
-
-/* ---------------------------------------------------------------------------------------------- */
-
-fn mk_color_from_key(hash: &yaml::Hash, key: &str) -> Option<Color> {
-    hash.get(&Yaml::from_str(key)).map(mk_color)
+/// Three numbers given as `[a, b, c]`
+fn mk_triple(yaml: &Yaml) -> Result<(f64, f64, f64), String> {
+    match mk_array(yaml)?.as_slice() {
+        [a, b, c] => Ok((mk_f64(a)?, mk_f64(b)?, mk_f64(c)?)),
+        _ => Err(format!("Expected three numbers, got: {:?}", yaml)),
+    }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_point(yaml: &Yaml) -> Tuple {
-    let xyz = yaml.as_vec().unwrap();
-    assert_eq!(xyz.len(), 3);
+fn mk_color(yaml: &Yaml) -> Result<Color, String> {
+    let (red, green, blue) = mk_triple(yaml)?;
 
-    Tuple::new_point(mk_f64(&xyz[0]), mk_f64(&xyz[1]), mk_f64(&xyz[2]))
+    Ok(Color::new_color(red, green, blue))
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_point_from_key(hash: &yaml::Hash, key: &str) -> Option<Tuple> {
-    hash.get(&Yaml::from_str(key)).map(mk_point)
+fn mk_color_from_key(hash: &yaml::Hash, key: &str) -> Result<Option<Color>, String> {
+    mk_from_key(hash, key, mk_color)
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_vector(yaml: &Yaml) -> Tuple {
-    let xyz = yaml.as_vec().unwrap();
-    assert_eq!(xyz.len(), 3);
+fn mk_point(yaml: &Yaml) -> Result<Tuple, String> {
+    let (x, y, z) = mk_triple(yaml)?;
 
-    Tuple::new_vector(mk_f64(&xyz[0]), mk_f64(&xyz[1]), mk_f64(&xyz[2]))
+    Ok(Tuple::new_point(x, y, z))
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_vector_from_key(hash: &yaml::Hash, key: &str) -> Option<Tuple> {
-    hash.get(&Yaml::from_str(key)).map(mk_vector)
+fn mk_vector(yaml: &Yaml) -> Result<Tuple, String> {
+    let (x, y, z) = mk_triple(yaml)?;
+
+    Ok(Tuple::new_vector(x, y, z))
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_pattern(defs: &Definitions, hash: &yaml::Hash) -> Option<Pattern> {
-    if let Some(color) = hash.get(&Yaml::from_str("color")) {
-        Some(Pattern::new_plain_pattern(mk_color(color)))
-    } else if let Some(pattern) = hash.get(&Yaml::from_str("pattern")) {
-        let pattern_hash = pattern.as_hash().unwrap();
-        let ty = pattern_hash
-            .get(&Yaml::from_str("type"))
-            .unwrap()
-            .as_str()
-            .unwrap();
+fn mk_pattern(defs: &Definitions, hash: &yaml::Hash) -> Result<Option<Pattern>, String> {
+    if let Some(color) = mk_color_from_key(hash, "color")? {
+        return Ok(Some(Pattern::new_plain_pattern(color)));
+    }
+
+    mk_from_key(hash, "pattern", |pattern| {
+        let pattern_hash = mk_hash(pattern)?;
+        let ty = mk_required(pattern_hash, "type", mk_str)?;
+        let colors = || {
+            mk_required(pattern_hash, "colors", |colors| {
+                mk_array(colors)?
+                    .iter()
+                    .map(mk_color)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+        };
+        let two_colors = || match colors()?.as_slice() {
+            [first, second] => Ok((*first, *second)),
+            _ => Err(format!("Expected two colors for a {} pattern", ty)),
+        };
 
         let mut pattern = match ty {
             "checkers" => {
-                let colors = pattern_hash
-                    .get(&Yaml::from_str("colors"))
-                    .unwrap()
-                    .as_vec()
-                    .unwrap();
-
-                Pattern::new_checker_pattern(mk_color(&colors[0]), mk_color(&colors[1]))
+                let (first, second) = two_colors()?;
+                Pattern::new_checker_pattern(first, second)
             }
 
             "gradient" => {
-                let colors = pattern_hash
-                    .get(&Yaml::from_str("colors"))
-                    .unwrap()
-                    .as_vec()
-                    .unwrap();
-
-                Pattern::new_gradiant_pattern(mk_color(&colors[0]), mk_color(&colors[1]))
+                let (first, second) = two_colors()?;
+                Pattern::new_gradiant_pattern(first, second)
             }
 
-            "ring" => {
-                let colors = pattern_hash
-                    .get(&Yaml::from_str("colors"))
-                    .unwrap()
-                    .as_vec()
-                    .unwrap();
+            "ring" => Pattern::new_ring_pattern(colors()?),
 
-                let v: Vec<_> = colors.iter().map(mk_color).collect();
+            "stripes" => Pattern::new_stripe_pattern(colors()?),
+            _ => return Err(format!("Unknown pattern: {:?}", ty)),
+        };
 
-                Pattern::new_ring_pattern(v)
-            }
+        let transformation = mk_transform(defs, pattern_hash)?;
+        pattern.set_transform(&transformation);
 
-            "stripes" => {
-                let colors = pattern_hash
-                    .get(&Yaml::from_str("colors"))
-                    .unwrap()
-                    .as_vec()
-                    .unwrap();
+        Ok(pattern)
+    })
+}
 
-                let v: Vec<_> = colors.iter().map(mk_color).collect();
+/* ---------------------------------------------------------------------------------------------- */
 
-                Pattern::new_stripe_pattern(v)
-            }
-            _ => panic!("Unknown pattern: {:?}", pattern),
-        };
+fn mk_material(defs: &Definitions, hash: &yaml::Hash) -> Result<Material, String> {
+    let default = Material::default_material();
 
-        let transformation = mk_transform(defs, pattern_hash);
-        pattern.set_transform(&transformation);
+    let material = mk_from_key(hash, "material", |material_yaml| {
+        let material_hash = get_hash(defs, material_yaml)?;
+        let f64_or = |key, default| {
+            mk_f64_from_key(material_hash, key).map(|value| value.unwrap_or(default))
+        };
+        let color_or = |key, default| {
+            mk_color_from_key(material_hash, key).map(|value| value.unwrap_or(default))
+        };
 
-        Some(pattern)
-    } else {
-        None
-    }
+        Ok(Material::default_material()
+            .with_ambient(f64_or("ambient", default.ambient)?)
+            .with_diffuse(f64_or("diffuse", default.diffuse)?)
+            .with_reflective(f64_or("reflective", default.reflective)?)
+            .with_refractive_index(f64_or("refractive-index", default.refractive_index)?)
+            .with_shininess(f64_or("shininess", default.shininess)?)
+            .with_specular(f64_or("specular", default.specular)?)
+            .with_transparency(f64_or("transparency", default.transparency)?)
+            .with_absorption(color_or("absorption", default.absorption)?)
+            .with_density(f64_or("density", default.density)?)
+            .with_emission(color_or("emission", default.emission)?)
+            .with_pattern(mk_pattern(defs, material_hash)?)
+            .with_medium(mk_medium(defs, material_hash)?))
+    })?;
+
+    Ok(material.unwrap_or(default))
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_material(defs: &Definitions, hash: &yaml::Hash) -> Material {
-    let default = Material::default_material();
+fn mk_medium(defs: &Definitions, hash: &yaml::Hash) -> Result<Option<Medium>, String> {
+    mk_from_key(hash, "medium", |medium_yaml| {
+        let medium_hash = get_hash(defs, medium_yaml)?;
+        Ok(Medium::new_medium(
+            mk_required(medium_hash, "density", mk_f64)?,
+            mk_color_from_key(medium_hash, "color")?.unwrap_or(color::WHITE),
+            mk_usize_from_key(medium_hash, "samples")?.unwrap_or(8),
+        ))
+    })
+}
 
-    match hash.get(&Yaml::from_str("material")) {
-        Some(material_yaml) => {
-            let material_hash = get_hash(defs, material_yaml);
-
-            Material::default_material()
-                .with_ambient(mk_f64_from_key(material_hash, "ambient").unwrap_or(default.ambient))
-                .with_diffuse(mk_f64_from_key(material_hash, "diffuse").unwrap_or(default.diffuse))
-                .with_reflective(
-                    mk_f64_from_key(material_hash, "reflective").unwrap_or(default.reflective),
-                )
-                .with_refractive_index(
-                    mk_f64_from_key(material_hash, "refractive-index")
-                        .unwrap_or(default.refractive_index),
-                )
-                .with_shininess(
-                    mk_f64_from_key(material_hash, "shininess").unwrap_or(default.shininess),
-                )
-                .with_specular(
-                    mk_f64_from_key(material_hash, "specular").unwrap_or(default.specular),
-                )
-                .with_transparency(
-                    mk_f64_from_key(material_hash, "transparency").unwrap_or(default.transparency),
-                )
-                .with_pattern(mk_pattern(defs, material_hash))
-        }
-        None => default,
-    }
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_fog(hash: &yaml::Hash) -> Result<Fog, String> {
+    Ok(Fog::new_fog(
+        mk_required(hash, "density", mk_f64)?,
+        mk_required(hash, "color", mk_color)?,
+    ))
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn get_transformations(defs: &Definitions, array: &[Yaml], transformations: &mut Vec<Yaml>) {
+fn get_transformations(
+    defs: &Definitions,
+    array: &[Yaml],
+    transformations: &mut Vec<Yaml>,
+) -> Result<(), String> {
     for transform in array {
         match transform[0].as_str() {
             Some(_) => transformations.push(transform.clone()),
             None => {
-                let embedded_transformations = get_array(defs, transform);
-                get_transformations(defs, embedded_transformations, transformations);
+                let embedded_transformations = get_array(defs, transform)?;
+                get_transformations(defs, embedded_transformations, transformations)?;
             }
         }
     }
+    Ok(())
 }
 
-fn mk_transform(defs: &Definitions, hash: &yaml::Hash) -> Matrix {
-    let mut mat = Matrix::new_identity_matrix(4);
-
-    if let Some(transform_array) = hash.get(&Yaml::from_str("transform")) {
-        let transform_array = transform_array.as_vec().unwrap();
-
-        let mut transformations_yaml = vec![];
-        get_transformations(defs, transform_array, &mut transformations_yaml);
-
-        for transform in transformations_yaml {
-            let transform = get_array(defs, &transform);
-            let operation = transform[0].as_str().unwrap();
-
-            let transformation = match operation {
-                "rotate-x" => create_rotation_x(mk_f64(&transform[1])),
-                "rotate-y" => create_rotation_y(mk_f64(&transform[1])),
-                "rotate-z" => create_rotation_z(mk_f64(&transform[1])),
-                "scale" => create_scaling(
-                    mk_f64(&transform[1]),
-                    mk_f64(&transform[2]),
-                    mk_f64(&transform[3]),
-                ),
-                "shear" => create_shearing(
-                    mk_f64(&transform[1]),
-                    mk_f64(&transform[2]),
-                    mk_f64(&transform[3]),
-                    mk_f64(&transform[4]),
-                    mk_f64(&transform[5]),
-                    mk_f64(&transform[6]),
-                ),
-                "translate" => create_translation(
-                    mk_f64(&transform[1]),
-                    mk_f64(&transform[2]),
-                    mk_f64(&transform[3]),
-                ),
-                other => panic!("Unexpected transformation {:?}", other),
+fn mk_transform(defs: &Definitions, hash: &yaml::Hash) -> Result<Matrix4, String> {
+    let transform = mk_from_key(hash, "transform", |transform_array| {
+        mk_transform_list(defs, mk_array(transform_array)?)
+    })?;
+    Ok(transform.unwrap_or(Matrix4::new_identity_matrix()))
+}
+
+fn mk_transform_list(defs: &Definitions, transform_array: &[Yaml]) -> Result<Matrix4, String> {
+    Ok(create_transform(&mk_transformations(
+        defs,
+        transform_array,
+    )?))
+}
+
+fn mk_transformations(
+    defs: &Definitions,
+    transform_array: &[Yaml],
+) -> Result<Vec<Transformation>, String> {
+    let mut transformations_yaml = vec![];
+    get_transformations(defs, transform_array, &mut transformations_yaml)?;
+
+    transformations_yaml
+        .iter()
+        .map(|transform| {
+            let transform = get_array(defs, transform)?;
+            let operation = mk_str(&transform[0])?;
+            let argument = |i: usize| match transform.get(i) {
+                Some(yaml) => mk_f64(yaml).map_err(|error| format!("{}: {}", operation, error)),
+                None => Err(format!("Missing argument {} of {}", i, operation)),
             };
 
-            mat = transformation * mat;
-        }
-    }
+            Ok(match operation {
+                "rotate-x" => Transformation::RotationX(argument(1)?),
+                "rotate-y" => Transformation::RotationY(argument(1)?),
+                "rotate-z" => Transformation::RotationZ(argument(1)?),
+                "scale" => Transformation::Scaling(argument(1)?, argument(2)?, argument(3)?),
+                "shear" => Transformation::Shearing([
+                    argument(1)?,
+                    argument(2)?,
+                    argument(3)?,
+                    argument(4)?,
+                    argument(5)?,
+                    argument(6)?,
+                ]),
+                "translate" => {
+                    Transformation::Translation(argument(1)?, argument(2)?, argument(3)?)
+                }
+                other => return Err(format!("Unexpected transformation {:?}", other)),
+            })
+        })
+        .collect()
+}
+
+/* ---------------------------------------------------------------------------------------------- */
 
-    mat
+/// Keyframed transform of a moving object, like
+/// `motion: [[0, [[translate, 0, 1, 0]]], [0.5, [[translate, 2, 1, 0]]]]` where each
+/// keyframe gives its time in seconds and its transform, two keyframes listing the same
+/// kinds of transformations being blended parameter by parameter
+fn mk_motion(defs: &Definitions, hash: &yaml::Hash) -> Result<Option<Motion>, String> {
+    mk_from_key(hash, "motion", |keyframes| {
+        let keyframes = mk_array(keyframes)?
+            .iter()
+            .map(|keyframe| match mk_array(keyframe)?.as_slice() {
+                [time, transform] => Ok((
+                    mk_f64(time)?,
+                    mk_transformations(defs, mk_array(transform)?)?,
+                )),
+                _ => Err(format!(
+                    "Expected a keyframe as [time, transform], got: {:?}",
+                    keyframe
+                )),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if keyframes.is_empty() {
+            return Err("A motion needs at least one keyframe".to_string());
+        }
+        Ok(Motion::new_motion(keyframes))
+    })
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_object(defs: &Definitions, hash: &yaml::Hash, ty: &str) -> Object {
+fn mk_object(defs: &Definitions, hash: &yaml::Hash, ty: &str) -> Result<Object, String> {
     let object = match ty {
         // "cube" => Object::new_cube(),
         "plane" => Object::new_plane(),
+        "quad" => Object::new_quad(),
         "sphere" => Object::new_sphere(),
-        _ => panic!("Unexpected object type: {:?}", ty),
+        "triangle" => Object::new_triangle(
+            mk_required(hash, "p1", mk_point)?,
+            mk_required(hash, "p2", mk_point)?,
+            mk_required(hash, "p3", mk_point)?,
+        ),
+        _ => return Err(format!("Unexpected object type: {:?}", ty)),
     }
-    .with_material(mk_material(defs, hash))
-    .with_shadow(mk_bool_from_key(hash, "shadow").unwrap_or(true))
-    .with_transformation(mk_transform(defs, hash));
+    .with_material(mk_material(defs, hash)?)
+    .with_shadow(mk_bool_from_key(hash, "shadow")?.unwrap_or(true))
+    .with_transformation(mk_transform(defs, hash)?)
+    .with_motion(mk_motion(defs, hash)?);
+
+    Ok(object)
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_ambient_occlusion(hash: &yaml::Hash) -> Result<AmbientOcclusion, String> {
+    Ok(AmbientOcclusion::new_ambient_occlusion(
+        mk_usize_from_key(hash, "samples")?.unwrap_or(16),
+        mk_f64_from_key(hash, "distance")?.unwrap_or(1.0),
+    ))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_debug_mode(hash: &yaml::Hash) -> Result<DebugMode, String> {
+    mk_required(hash, "debug-mode", |yaml| {
+        let name = mk_str(yaml)?;
+        DebugMode::from_name(name).ok_or_else(|| format!("Unknown debug mode: {}", name))
+    })
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_integrator(hash: &yaml::Hash) -> Result<Integrator, String> {
+    let name = match mk_from_key(hash, "integrator", mk_str)? {
+        None => return Ok(Integrator::Whitted),
+        Some(name) => name,
+    };
+
+    Ok(match name {
+        "whitted" => Integrator::Whitted,
+        "path-tracing" => Integrator::PathTracing {
+            samples: mk_usize_from_key(hash, "samples")?.unwrap_or(16),
+        },
+        "ambient-occlusion" => Integrator::AmbientOcclusion(mk_ambient_occlusion(hash)?),
+        "debug" => Integrator::Debug(mk_debug_mode(hash)?),
+        _ => return Err(format!("Unknown integrator: {}", name)),
+    })
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_tile_order(hash: &yaml::Hash) -> Result<TileOrder, String> {
+    let order = mk_from_key(hash, "tile-order", |yaml| match mk_str(yaml)? {
+        "spiral" => Ok(TileOrder::Spiral),
+        "scanline" => Ok(TileOrder::Scanline),
+        "hilbert" => Ok(TileOrder::Hilbert),
+        name => Err(format!("Unknown tile order: {}", name)),
+    })?;
+    Ok(order.unwrap_or(TileOrder::Spiral))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_aovs(hash: &yaml::Hash) -> Result<Vec<Aov>, String> {
+    let aovs = mk_from_key(hash, "aovs", |names| {
+        mk_array(names)?
+            .iter()
+            .map(|name| {
+                let name = mk_str(name)?;
+                Aov::from_name(name).ok_or_else(|| format!("Unknown AOV: {}", name))
+            })
+            .collect()
+    })?;
+    Ok(aovs.unwrap_or_default())
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_tone_mapping(hash: &yaml::Hash) -> Result<ToneMapping, String> {
+    let operator = mk_from_key(hash, "tone-mapping", |yaml| {
+        let name = mk_str(yaml)?;
+        ToneMap::from_name(name).ok_or_else(|| format!("Unknown tone mapping: {}", name))
+    })?;
+
+    Ok(ToneMapping::new_tone_mapping(
+        operator.unwrap_or(ToneMap::Clamp),
+        mk_f64_from_key(hash, "exposure")?.unwrap_or(0.0),
+        mk_bool_from_key(hash, "srgb")?.unwrap_or(false),
+    ))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_projection(hash: &yaml::Hash) -> Result<Projection, String> {
+    let name = match mk_from_key(hash, "projection", mk_str)? {
+        None => return Ok(Projection::Perspective),
+        Some(name) => name,
+    };
+
+    Ok(match name {
+        "perspective" => Projection::Perspective,
+        "orthographic" => Projection::Orthographic {
+            view_width: mk_required(hash, "view-width", mk_f64)?,
+        },
+        "equirectangular" => Projection::Equirectangular,
+        "cylindrical" => Projection::Cylindrical,
+        "fisheye" => Projection::Fisheye,
+        _ => return Err(format!("Unknown projection: {}", name)),
+    })
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_stereo(hash: &yaml::Hash, view: &View) -> Result<Option<Stereo>, String> {
+    let layout = mk_from_key(hash, "stereo", |yaml| {
+        let name = mk_str(yaml)?;
+        StereoLayout::from_name(name).ok_or_else(|| format!("Unknown stereo layout: {}", name))
+    })?;
+    let layout = match layout {
+        None => return Ok(None),
+        Some(layout) => layout,
+    };
+
+    // the eyes converge on the point the camera looks at by default
+    let distance = (view.to.clone() - view.from.clone()).magnitude();
+    Ok(Some(Stereo::new_stereo(
+        layout,
+        mk_required(hash, "interocular", mk_f64)?,
+        mk_f64_from_key(hash, "convergence")?.unwrap_or(distance),
+    )))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+/// Shutter open over `shutter: [open, close]`, in seconds, taking the picture at 0 otherwise
+fn mk_shutter(hash: &yaml::Hash) -> Result<Shutter, String> {
+    let interval = mk_from_key(hash, "shutter", |interval| {
+        match mk_array(interval)?.as_slice() {
+            [open, close] => Ok((mk_f64(open)?, mk_f64(close)?)),
+            _ => Err(format!("Expected [open, close], got: {:?}", interval)),
+        }
+    })?;
+
+    Ok(match interval {
+        Some((open, close)) => Shutter::new_shutter(
+            open,
+            close,
+            mk_usize_from_key(hash, "shutter-samples")?.unwrap_or(8),
+        ),
+        None => Shutter::new_instant(0.0),
+    })
+}
 
-    object
+/* ---------------------------------------------------------------------------------------------- */
+
+/// Rectangle of pixels given as `region: [x, y, width, height]`
+fn mk_region(hash: &yaml::Hash) -> Result<Option<Tile>, String> {
+    mk_from_key(hash, "region", |region| {
+        match mk_array(region)?.as_slice() {
+            [x, y, width, height] => Ok(Tile::new_tile(
+                mk_usize(x)?,
+                mk_usize(y)?,
+                mk_usize(width)?,
+                mk_usize(height)?,
+            )),
+            _ => Err(format!(
+                "Expected a region as [x, y, width, height], got: {:?}",
+                region
+            )),
+        }
+    })
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_camera(hash: &yaml::Hash) -> Camera {
-    Camera::default()
+fn mk_camera(hash: &yaml::Hash) -> Result<Camera, String> {
+    let projection = mk_projection(hash)?;
+    // only the perspective and fisheye projections need a field of view
+    let field_of_view = match projection {
+        Projection::Perspective | Projection::Fisheye => {
+            mk_required(hash, "field-of-view", mk_f64)?
+        }
+        _ => mk_f64_from_key(hash, "field-of-view")?.unwrap_or(Camera::default().field_of_view),
+    };
+
+    let view = View::new_view(
+        mk_required(hash, "from", mk_point)?,
+        mk_required(hash, "to", mk_point)?,
+        mk_required(hash, "up", mk_vector)?,
+    );
+    let stereo = mk_stereo(hash, &view)?;
+
+    Ok(Camera::default()
         .with_size(
-            mk_usize_from_key(hash, "width").unwrap(),
-            mk_usize_from_key(hash, "height").unwrap(),
+            mk_required(hash, "width", mk_usize)?,
+            mk_required(hash, "height", mk_usize)?,
         )
-        .with_fov(mk_f64_from_key(hash, "field-of-view").unwrap())
-        .with_transformation(view_transform(
-            &mk_point_from_key(hash, "from").unwrap(),
-            &mk_point_from_key(hash, "to").unwrap(),
-            &mk_vector_from_key(hash, "up").unwrap(),
-        ))
+        .with_fov(field_of_view)
+        .with_projection(projection)
+        .with_view(view)
+        .with_integrator(mk_integrator(hash)?)
+        .with_tile_size(mk_usize_from_key(hash, "tile-size")?.unwrap_or(16))
+        .with_tile_order(mk_tile_order(hash)?)
+        .with_aovs(mk_aovs(hash)?)
+        .with_tone_mapping(mk_tone_mapping(hash)?)
+        .with_stereo(stereo)
+        .with_shutter(mk_shutter(hash)?)
+        .with_region(mk_region(hash)?)
+        .with_crop(mk_bool_from_key(hash, "crop")?.unwrap_or(false)))
 }
 
 /* ---------------------------------------------------------------------------------------------- */
@@ -380,63 +670,602 @@ fn mk_camera(hash: &yaml::Hash) -> Camera {
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_point_light(hash: &yaml::Hash) -> PointLight {
-    PointLight::new_point_light(
-        mk_color_from_key(hash, "intensity").unwrap(),
-        mk_point_from_key(hash, "at").unwrap(),
+fn mk_attenuation(hash: &yaml::Hash) -> Result<Attenuation, String> {
+    let attenuation = mk_from_key(hash, "attenuation", |yaml| match yaml {
+        Yaml::String(name) => match name.as_str() {
+            "none" => Ok(Attenuation::None),
+            "inverse-square" => Ok(Attenuation::InverseSquare),
+            _ => Err(format!("Unknown attenuation: {}", name)),
+        },
+        Yaml::Array(coefficients) if coefficients.len() == 3 => Ok(Attenuation::Polynomial {
+            constant: mk_f64(&coefficients[0])?,
+            linear: mk_f64(&coefficients[1])?,
+            quadratic: mk_f64(&coefficients[2])?,
+        }),
+        _ => Err(format!(
+            "Expected an attenuation name or [constant, linear, quadratic], got: {:?}",
+            yaml
+        )),
+    })?;
+    Ok(attenuation.unwrap_or(Attenuation::None))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_point_light(hash: &yaml::Hash) -> Result<PointLight, String> {
+    Ok(PointLight::new_point_light(
+        mk_required(hash, "intensity", mk_color)?,
+        mk_required(hash, "at", mk_point)?,
     )
+    .with_attenuation(mk_attenuation(hash)?))
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-fn mk_light(hash: &yaml::Hash) -> PointLight {
+fn mk_light(hash: &yaml::Hash) -> Result<PointLight, String> {
     if hash.get(&Yaml::from_str("corner")).is_some() {
         // mk_area_light(hash)
-        unimplemented!()
+        Err("Area lights are not supported, use an emissive quad".to_string())
     } else if hash.get(&Yaml::from_str("at")).is_some() {
         mk_point_light(hash)
     } else {
-        panic!("Unexpected light type, got: {:?}", hash);
+        Err(format!("Unexpected light type, got: {:?}", hash))
     }
 }
 
 /* ---------------------------------------------------------------------------------------------- */
 
-// TODO: don't unwrap() everywhere...
-pub fn parse(path: &std::path::Path) -> (Vec<Object>, Vec<PointLight>, Camera) {
-    let yaml = std::fs::read_to_string(path).unwrap();
-    let docs = YamlLoader::load_from_str(&yaml).unwrap();
-    let doc = &docs[0];
+/// Emissive objects sharing a `light-group` are sampled as a single light,
+/// the others make a light of their own
+fn add_emissive_light(
+    world: &mut World,
+    groups: &mut HashMap<String, usize>,
+    hash: &yaml::Hash,
+    object: Object,
+    samples: usize,
+) -> Result<(), String> {
+    if !object.shape.can_be_sampled() {
+        return Err(format!(
+            "Only spheres, quads and triangles can have light-samples, got: {:?}",
+            object.shape
+        ));
+    }
+    match mk_from_key(hash, "light-group", mk_str)? {
+        Some(group) => match groups.get(group) {
+            Some(light_index) => {
+                let light_index = *light_index;
+                world.add_to_emissive_light(light_index, object);
+                let light = &mut world.emissive_lights[light_index];
+                light.samples = light.samples.max(samples);
+            }
+            None => {
+                groups.insert(group.to_string(), world.emissive_lights.len());
+                world.add_emissive_light(vec![object], samples);
+            }
+        },
+        None => world.add_emissive_light(vec![object], samples),
+    }
+    Ok(())
+}
 
-    let mut objects = vec![];
-    let mut lights = vec![];
-    let mut camera = None;
+/* ---------------------------------------------------------------------------------------------- */
 
-    // First, look for all definitions
-    let definitions = get_definitions(doc);
+/// Camera block of a scene file placing a camera like `camera`
+pub fn camera_to_yaml(camera: &Camera) -> String {
+    let xyz = |tuple: &Tuple| format!("[{:?}, {:?}, {:?}]", tuple.x, tuple.y, tuple.z);
+    let mut lines = vec![
+        "- add: camera".to_string(),
+        format!("  width: {}", camera.hsize),
+        format!("  height: {}", camera.vsize),
+    ];
+    if camera.projection != Projection::Perspective {
+        lines.push(format!("  projection: {}", camera.projection.name()));
+    }
+    match camera.projection {
+        Projection::Perspective | Projection::Fisheye => {
+            lines.push(format!("  field-of-view: {:?}", camera.field_of_view));
+        }
+        Projection::Orthographic { view_width } => {
+            lines.push(format!("  view-width: {:?}", view_width));
+        }
+        Projection::Equirectangular | Projection::Cylindrical => {}
+    }
+    lines.extend([
+        format!("  from: {}", xyz(&camera.view.from)),
+        format!("  to: {}", xyz(&camera.view.to)),
+        format!("  up: {}", xyz(&camera.view.up)),
+    ]);
+    if let Some(stereo) = camera.stereo {
+        lines.push(format!("  stereo: {}", stereo.layout.name()));
+        lines.push(format!("  interocular: {:?}", stereo.interocular));
+        lines.push(format!("  convergence: {:?}", stereo.convergence));
+    }
+    if !camera.shutter.is_instant() {
+        let shutter = camera.shutter;
+        lines.push(format!(
+            "  shutter: [{:?}, {:?}]",
+            shutter.open, shutter.close
+        ));
+        lines.push(format!("  shutter-samples: {}", shutter.samples));
+    }
+    if let Some(region) = camera.region {
+        lines.push(format!(
+            "  region: [{}, {}, {}, {}]",
+            region.x, region.y, region.width, region.height
+        ));
+        lines.push(format!("  crop: {}", camera.crop));
+    }
+    lines.join("\n")
+}
 
-    for elem in doc.as_vec().unwrap().iter() {
-        let hash = elem.as_hash().unwrap();
+/* ---------------------------------------------------------------------------------------------- */
 
-        if let Some(x) = hash.get(&Yaml::from_str("add")) {
-            let ty = x.as_str().unwrap();
+fn as_number(yaml: &Yaml) -> Option<f64> {
+    yaml.as_f64().or(yaml.as_i64().map(|value| value as f64))
+}
 
-            match ty {
-                "camera" => {
-                    camera = Some(mk_camera(hash));
-                }
-                "light" => {
-                    lights.push(mk_light(hash));
-                }
-                "cube" | "plane" | "sphere" => {
-                    objects.push(mk_object(&definitions, hash, ty));
-                }
-                _ => unimplemented!(),
+/// Value of two keyframes blended by `factor`, numbers and lists of numbers being
+/// interpolated while the other values switch at the second keyframe. Integer values
+/// (sample counts, sizes...) stay integers, rounded to the nearest one.
+fn blend(a: &Yaml, b: &Yaml, factor: f64) -> Yaml {
+    match (a, b) {
+        (Yaml::Integer(a), Yaml::Integer(b)) => {
+            Yaml::Integer((*a as f64 + (b - a) as f64 * factor).round() as i64)
+        }
+        _ => interpolate(a, b, factor),
+    }
+}
+
+/// Same as `blend`, the numbers of lists (points, colors...) being real
+fn interpolate(a: &Yaml, b: &Yaml, factor: f64) -> Yaml {
+    match (a, b) {
+        _ if a == b || factor == 0.0 => a.clone(),
+        _ if factor == 1.0 => b.clone(),
+        (Yaml::Array(a), Yaml::Array(b)) if a.len() == b.len() => Yaml::Array(
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| interpolate(a, b, factor))
+                .collect(),
+        ),
+        _ => match (as_number(a), as_number(b)) {
+            (Some(a), Some(b)) => Yaml::Real((a + (b - a) * factor).to_string()),
+            _ => a.clone(),
+        },
+    }
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+/// Value at `time` of a keyframed value, like
+/// `{ keyframes: [[0, [0, 1, -5]], [2, [5, 1, 0]]], interpolation: smooth }` where each
+/// keyframe gives its time in seconds and its value
+fn mk_keyframes(hash: &yaml::Hash, time: f64) -> Result<Yaml, String> {
+    let interpolation = mk_from_key(hash, "interpolation", |yaml| {
+        let name = mk_str(yaml)?;
+        Interpolation::from_name(name).ok_or_else(|| format!("Unknown interpolation: {}", name))
+    })?
+    .unwrap_or(Interpolation::Linear);
+
+    let keys = mk_required(hash, "keyframes", |keyframes| {
+        mk_array(keyframes)?
+            .iter()
+            .map(|keyframe| match mk_array(keyframe)?.as_slice() {
+                [key_time, value] => Ok((mk_f64(key_time)?, mk_frame(value, time)?)),
+                _ => Err(format!(
+                    "Expected a keyframe as [time, value], got: {:?}",
+                    keyframe
+                )),
+            })
+            .collect::<Result<Vec<(f64, Yaml)>, String>>()
+    })?;
+    if keys.is_empty() {
+        return Err("keyframes: At least one keyframe is needed".to_string());
+    }
+    let times: Vec<f64> = keys.iter().map(|(time, _)| *time).collect();
+
+    let (previous, next, factor) = interpolation.segment(&times, time);
+    Ok(blend(&keys[previous].1, &keys[next].1, factor))
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+/// Copy of `yaml` with the keyframed values replaced by their value at `time`
+fn mk_frame(yaml: &Yaml, time: f64) -> Result<Yaml, String> {
+    Ok(match yaml {
+        Yaml::Hash(hash) if hash.contains_key(&Yaml::from_str("keyframes")) => {
+            mk_keyframes(hash, time)?
+        }
+        Yaml::Hash(hash) => Yaml::Hash(
+            hash.iter()
+                .map(|(key, value)| {
+                    let value = mk_frame(value, time)
+                        .map_err(|error| format!("{}: {}", key.as_str().unwrap_or("?"), error))?;
+                    Ok((key.clone(), value))
+                })
+                .collect::<Result<_, String>>()?,
+        ),
+        Yaml::Array(array) => Yaml::Array(
+            array
+                .iter()
+                .map(|value| mk_frame(value, time))
+                .collect::<Result<_, _>>()?,
+        ),
+        _ => yaml.clone(),
+    })
+}
+
+/* ---------------------------------------------------------------------------------------------- */
+
+fn mk_animation(hash: &yaml::Hash) -> Result<Animation, String> {
+    Ok(Animation::new_animation(
+        mk_required(hash, "frames", mk_usize)?,
+        mk_f64_from_key(hash, "fps")?.unwrap_or(24.0),
+    ))
+}
+
+/// Elements of the scene file at `path`, the `- include: file.yml` elements being replaced by
+/// the elements of that file, its path being relative to the including file. The files read
+/// are added to `files`, `chain` holding the files being included to catch loops.
+fn load_elements(
+    path: &Path,
+    chain: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<yaml::Array, String> {
+    let context = |error: &dyn std::fmt::Display| format!("{}: {}", path.display(), error);
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if chain.contains(&canonical) {
+        return Err(context(&"Included by itself"));
+    }
+    files.push(path.to_path_buf());
+
+    let yaml = fs::read_to_string(path).map_err(|error| context(&error))?;
+    let docs = YamlLoader::load_from_str(&yaml).map_err(|error| context(&error))?;
+    let doc = docs.first().ok_or_else(|| context(&"Empty scene"))?;
+
+    chain.push(canonical);
+    let mut elements = vec![];
+    for (index, elem) in mk_array(doc)
+        .map_err(|error| context(&error))?
+        .iter()
+        .enumerate()
+    {
+        let include = match elem.as_hash() {
+            Some(hash) => mk_from_key(hash, "include", mk_str)
+                .map_err(|error| context(&format!("Element {}: {}", index, error)))?,
+            None => None,
+        };
+        match include {
+            Some(file) => {
+                let file = path.parent().unwrap_or(Path::new("")).join(file);
+                elements.extend(load_elements(&file, chain, files)?);
             }
+            None => elements.push(elem.clone()),
         }
     }
+    chain.pop();
+
+    Ok(elements)
+}
+
+/// Scene file at `path` with its includes spliced in
+fn load(path: &Path) -> Result<Yaml, String> {
+    load_elements(path, &mut vec![], &mut vec![]).map(Yaml::Array)
+}
 
-    (objects, lights, camera.unwrap())
+/// Files read to parse the scene at `path`, the scene file first then the files it
+/// includes, as far as they could be read
+pub fn scene_files(path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let _ = load_elements(path, &mut vec![], &mut files);
+    files
+}
+
+/// Frames of the scene, if it has an animation section
+pub fn parse_animation(path: &Path) -> Result<Option<Animation>, String> {
+    let doc = load(path)?;
+
+    for (index, elem) in mk_array(&doc)?.iter().enumerate() {
+        let context = |error| format!("Element {}: {}", index, error);
+        let hash = mk_hash(elem).map_err(context)?;
+        if hash.get(&Yaml::from_str("add")) == Some(&Yaml::from_str("animation")) {
+            return mk_animation(hash)
+                .map(Some)
+                .map_err(|error| context(format!("animation: {}", error)));
+        }
+    }
+    Ok(None)
 }
 
 /* ---------------------------------------------------------------------------------------------- */
+
+pub fn parse(path: &Path) -> (World, Camera) {
+    parse_at(path, 0.0)
+}
+
+/// Same as `parse`, the keyframed values being taken at `time` seconds
+pub fn parse_at(path: &Path, time: f64) -> (World, Camera) {
+    try_parse_at(path, time).unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Same as `parse`, the problems of the scene being returned instead of panicking
+pub fn try_parse(path: &Path) -> Result<(World, Camera), String> {
+    try_parse_at(path, 0.0)
+}
+
+/// Same as `parse_at`, the problems of the scene being returned instead of panicking
+pub fn try_parse_at(path: &Path, time: f64) -> Result<(World, Camera), String> {
+    let doc = &mk_frame(&load(path)?, time)?;
+
+    let mut world = World::new_world();
+    let mut light_groups = HashMap::new();
+    let mut camera = None;
+
+    // First, look for all definitions
+    let definitions = get_definitions(doc)?;
+
+    for (index, elem) in mk_array(doc)?.iter().enumerate() {
+        let hash = mk_hash(elem).map_err(|error| format!("Element {}: {}", index, error))?;
+        let ty = match mk_from_key(hash, "add", mk_str) {
+            Ok(Some(ty)) => ty,
+            Ok(None) => continue,
+            Err(error) => return Err(format!("Element {}: {}", index, error)),
+        };
+        let context = |error| format!("Element {}: {}: {}", index, ty, error);
+
+        match ty {
+            "camera" => {
+                camera = Some(mk_camera(hash).map_err(context)?);
+            }
+            "light" => {
+                world.light_sources.push(mk_light(hash).map_err(context)?);
+            }
+            "ambient-occlusion" => {
+                world.ambient_occlusion = Some(mk_ambient_occlusion(hash).map_err(context)?);
+            }
+            "fog" => {
+                world.fog = Some(mk_fog(hash).map_err(context)?);
+            }
+            // read by parse_animation
+            "animation" => {}
+            "cube" | "plane" | "quad" | "sphere" | "triangle" => {
+                let object = mk_object(&definitions, hash, ty).map_err(context)?;
+                match mk_usize_from_key(hash, "light-samples").map_err(context)? {
+                    Some(samples) => {
+                        add_emissive_light(&mut world, &mut light_groups, hash, object, samples)
+                            .map_err(context)?
+                    }
+                    None => world.add_object(object),
+                }
+            }
+            _ => return Err(format!("Element {}: Unexpected element: {}", index, ty)),
+        }
+    }
+
+    // the shutter opens at the time of the frame
+    let mut camera =
+        camera.ok_or_else(|| format!("{}: The scene has no camera", path.display()))?;
+    camera.shutter = camera.shutter.shifted(time);
+    Ok((world, camera))
+}
+
+#[cfg(test)]
+mod yaml_tests {
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    // A broken scene is reported as an error
+    fn try_parse_error() {
+        let path = std::env::temp_dir().join("rustracer_try_parse_error.yml");
+        std::fs::write(&path, "- add: camera\n  width: 10\n- add: teapot\n").unwrap();
+        assert!(try_parse(&path).is_err());
+
+        assert!(try_parse(Path::new("scenes/missing.yml")).is_err());
+        assert!(try_parse(Path::new("scenes/ch11_refraction.yml")).is_ok());
+
+        // a plane has no area to sample
+        let scene = "\
+- add: camera
+  width: 10
+  height: 10
+  field-of-view: 1.0
+  from: [0, 1, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: plane
+  material:
+    emission: [1, 1, 1]
+  light-samples: 4
+";
+        std::fs::write(&path, scene).unwrap();
+        assert!(try_parse(&path).unwrap_err().contains("light-samples"));
+
+        // the error names the element and the key of the wrong value
+        let scene = scene.replace("emission: [1, 1, 1]", "ambient: bright");
+        std::fs::write(&path, scene).unwrap();
+        let error = try_parse(&path).unwrap_err();
+        assert!(
+            error.contains("Element 1: plane: material: ambient"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    // Included files are read in place, relative to the including file, loops being errors
+    fn parse_include() {
+        let directory = std::env::temp_dir().join("rustracer_parse_include");
+        std::fs::create_dir_all(directory.join("parts")).unwrap();
+        let path = directory.join("scene.yml");
+        let camera = camera_to_yaml(&Camera::new(10, 10, 1.0));
+        std::fs::write(&path, format!("{}\n- include: parts/objects.yml\n", camera)).unwrap();
+        std::fs::write(
+            directory.join("parts/objects.yml"),
+            "\
+- define: red
+  value:
+    color: [1, 0, 0]
+- include: sphere.yml
+- add: plane
+  material: red
+",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("parts/sphere.yml"),
+            "- add: sphere\n  material: red\n",
+        )
+        .unwrap();
+
+        let (world, _) = try_parse(&path).unwrap();
+        assert_eq!(world.objects.len(), 2);
+        assert_eq!(world.objects[0].material, world.objects[1].material);
+        assert_eq!(
+            scene_files(&path),
+            vec![
+                path.clone(),
+                directory.join("parts/objects.yml"),
+                directory.join("parts/sphere.yml")
+            ]
+        );
+
+        std::fs::write(
+            directory.join("parts/sphere.yml"),
+            "- include: objects.yml\n",
+        )
+        .unwrap();
+        assert!(try_parse(&path).unwrap_err().contains("Included by itself"));
+    }
+
+    #[test]
+    // The camera block written for a camera gives it back
+    fn camera_to_yaml_projection() {
+        let path = std::env::temp_dir().join("rustracer_camera_to_yaml.yml");
+        for projection in [
+            Projection::Perspective,
+            Projection::Orthographic { view_width: 6.5 },
+            Projection::Equirectangular,
+            Projection::Cylindrical,
+            Projection::Fisheye,
+        ] {
+            let camera = Camera::new(30, 20, 0.8).with_projection(projection);
+            std::fs::write(&path, camera_to_yaml(&camera)).unwrap();
+
+            let (_, parsed) = try_parse(&path).unwrap();
+            assert_eq!(parsed.projection, projection);
+            assert_eq!(parsed.ray_for_pixel(3, 4), camera.ray_for_pixel(3, 4));
+        }
+
+        let stereo = Stereo::new_stereo(StereoLayout::TopBottom, 0.2, 3.5);
+        let camera = Camera::new(30, 20, 0.8).with_stereo(Some(stereo));
+        std::fs::write(&path, camera_to_yaml(&camera)).unwrap();
+        assert_eq!(try_parse(&path).unwrap().1.stereo, Some(stereo));
+
+        let shutter = Shutter::new_shutter(0.0, 0.5, 4);
+        let camera = Camera::new(30, 20, 0.8).with_shutter(shutter);
+        std::fs::write(&path, camera_to_yaml(&camera)).unwrap();
+        assert_eq!(try_parse(&path).unwrap().1.shutter, shutter);
+
+        let region = Tile::new_tile(4, 2, 10, 8);
+        let camera = Camera::new(30, 20, 0.8)
+            .with_region(Some(region))
+            .with_crop(true);
+        std::fs::write(&path, camera_to_yaml(&camera)).unwrap();
+        let (_, parsed) = try_parse(&path).unwrap();
+        assert_eq!((parsed.region, parsed.crop), (Some(region), true));
+    }
+
+    #[test]
+    // Keyframed values are interpolated at the time of the frame
+    fn parse_keyframes() {
+        let path = std::env::temp_dir().join("rustracer_parse_keyframes.yml");
+        let scene = "\
+- add: animation
+  frames: 48
+  fps: 24
+- add: camera
+  width: 10
+  height: 10
+  field-of-view: 1.0
+  from: { keyframes: [[0, [0, 1, -5]], [2, [4, 1, -5]]] }
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: light
+  intensity: [1, 1, 1]
+  at: { keyframes: [[0, [0, 0, 0]], [1, [0, 8, 0]]], interpolation: smooth }
+- add: sphere
+  material:
+    ambient: { keyframes: [[0, 0.1], [2, 0.5]] }
+- add: ambient-occlusion
+  samples: { keyframes: [[0, 4], [2, 12]] }
+";
+        std::fs::write(&path, scene).unwrap();
+        assert_eq!(
+            parse_animation(&path).unwrap(),
+            Some(Animation::new_animation(48, 24.0))
+        );
+        assert!(
+            parse_animation(Path::new("scenes/ch11_refraction.yml"))
+                .unwrap()
+                .is_none()
+        );
+
+        let (world, camera) = parse_at(&path, 0.5);
+        assert_eq!(camera.view.from, Tuple::new_point(1.0, 1.0, -5.0));
+        assert_eq!(
+            world.light_sources[0].position,
+            Tuple::new_point(0.0, 4.0, 0.0)
+        );
+        assert!(utils::compare_float(world.objects[0].material.ambient, 0.2));
+        // integer values stay integers
+        assert_eq!(world.ambient_occlusion.unwrap().samples, 6);
+        let (world, _) = parse_at(&path, 0.3);
+        assert_eq!(world.ambient_occlusion.unwrap().samples, 5);
+
+        let (world, camera) = parse_at(&path, 3.0);
+        assert_eq!(camera.view.from, Tuple::new_point(4.0, 1.0, -5.0));
+        assert_eq!(
+            world.light_sources[0].position,
+            Tuple::new_point(0.0, 8.0, 0.0)
+        );
+
+        // smooth keyframes start slowly
+        let (world, _) = parse_at(&path, 0.25);
+        assert_eq!(
+            world.light_sources[0].position,
+            Tuple::new_point(0.0, 1.25, 0.0)
+        );
+    }
+
+    #[test]
+    // Moving objects get their transform at each keyframe, the shutter follows the frames
+    fn parse_motion() {
+        let path = std::env::temp_dir().join("rustracer_parse_motion.yml");
+        let scene = "\
+- add: camera
+  width: 10
+  height: 10
+  field-of-view: 1.0
+  from: [0, 1, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+  shutter: [0, 0.5]
+- add: sphere
+  transform:
+    - [translate, 0, 1, 0]
+  motion:
+    - [0, [[translate, 0, 1, 0]]]
+    - [0.5, [[scale, 2, 2, 2], [translate, 4, 1, 0]]]
+";
+        std::fs::write(&path, scene).unwrap();
+
+        let (world, camera) = parse_at(&path, 2.0);
+        assert_eq!(camera.shutter, Shutter::new_shutter(2.0, 2.5, 8));
+        let motion = world.objects[0].motion.as_ref().unwrap();
+        assert_eq!(motion.transform_at(0.0), create_translation(0.0, 1.0, 0.0));
+        assert_eq!(
+            motion.transform_at(0.5),
+            create_translation(4.0, 1.0, 0.0) * create_scaling(2.0, 2.0, 2.0)
+        );
+    }
+}
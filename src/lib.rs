@@ -1,16 +1,30 @@
+pub mod animation;
+pub mod aov;
 pub mod camera;
 pub mod canvas;
+pub mod checkpoint;
 pub mod color;
+pub mod debug;
 pub mod drivers;
 pub mod error;
+pub mod inspection;
 pub mod io;
 pub mod matrix;
+pub mod media;
+pub mod motion;
+pub mod occlusion;
+pub mod path_tracing;
 pub mod pattern;
 pub mod ppm;
+pub mod progressive;
 pub mod ray;
 pub mod reflection;
 pub mod refraction;
+pub mod sampling;
 pub mod shape;
+pub mod stereo;
+pub mod tile;
+pub mod tonemap;
 pub mod transformation;
 pub mod tuple;
 pub mod utils;
@@ -1,18 +1,39 @@
 use std::path::Path;
 
-use minifb::Key;
-use rustracer::utils::init_from_path;
+use rustracer::{
+    debug::DebugMode,
+    utils::{init_viewer_from_path, render_to_file},
+};
 
+/// rustracer [SCENE] [--mode DEBUG_MODE] [--output IMAGE.ppm]
 fn main() {
-    let path = Path::new("scenes/ch11_refraction.yml");
+    let mut scene = String::from("scenes/ch11_refraction.yml");
+    let mut debug_mode = None;
+    let mut output = None;
 
-    let (camera, buffer, mut window) = init_from_path(path);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mode" => {
+                let name = args.next().expect("--mode needs the name of a debug mode");
+                debug_mode = Some(
+                    DebugMode::from_name(&name)
+                        .unwrap_or_else(|| panic!("Unknown debug mode: {}", name)),
+                );
+            }
+            "--output" => {
+                output = Some(args.next().expect("--output needs the path of the image"));
+            }
+            _ => scene = arg,
+        }
+    }
 
-    window.set_target_fps(60);
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        // We unwrap here as we want this code to exit if it fails. Real applications may want to handle this in a different way
-        window
-            .update_with_buffer(&buffer, camera.hsize, camera.vsize)
-            .unwrap();
+    if let Some(output) = output {
+        render_to_file(Path::new(&scene), Path::new(&output), debug_mode).unwrap();
+        return;
     }
+
+    init_viewer_from_path(Path::new(&scene))
+        .with_debug_mode(debug_mode)
+        .run();
 }
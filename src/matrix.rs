@@ -1,4 +1,3 @@
-use memoize::memoize;
 use ordered_float::OrderedFloat;
 
 use crate::error;
@@ -203,9 +202,168 @@ impl Matrix {
     }
 }
 
-#[memoize]
-pub fn memoized_inverse(matrix: Matrix) -> Result<Matrix, error::RayTracerError> {
-    matrix.inverse()
+/// Fixed-size 4x4 matrix used by the transformations, living on the stack
+#[derive(Debug, Clone, Copy)]
+pub struct Matrix4 {
+    matrix: [[f64; 4]; 4],
+}
+
+impl PartialEq for Matrix4 {
+    fn eq(&self, other: &Self) -> bool {
+        self.matrix
+            .iter()
+            .flatten()
+            .zip(other.matrix.iter().flatten())
+            .all(|(a, b)| compare_float(*a, *b))
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: Matrix4) -> Matrix4 {
+        let mut matrix = [[0.0; 4]; 4];
+
+        for (row, values) in matrix.iter_mut().enumerate() {
+            for (col, value) in values.iter_mut().enumerate() {
+                *value = self.matrix[row][0] * other.matrix[0][col]
+                    + self.matrix[row][1] * other.matrix[1][col]
+                    + self.matrix[row][2] * other.matrix[2][col]
+                    + self.matrix[row][3] * other.matrix[3][col];
+            }
+        }
+        Matrix4 { matrix }
+    }
+}
+
+impl Mul<Tuple> for Matrix4 {
+    type Output = Tuple;
+
+    fn mul(self, other: Tuple) -> Tuple {
+        &self * other
+    }
+}
+
+impl Mul<Tuple> for &Matrix4 {
+    type Output = Tuple;
+
+    fn mul(self, other: Tuple) -> Tuple {
+        let w = W::to_int(other.w) as f64;
+        let m = &self.matrix;
+        let row =
+            |r: usize| m[r][0] * other.x + m[r][1] * other.y + m[r][2] * other.z + m[r][3] * w;
+
+        Tuple::new_tuple(row(0), row(1), row(2), row(3).round() as i64)
+    }
+}
+
+impl Matrix4 {
+    pub fn with_data(matrix: [[f64; 4]; 4]) -> Matrix4 {
+        Matrix4 { matrix }
+    }
+
+    pub fn new_identity_matrix() -> Matrix4 {
+        Matrix4 {
+            matrix: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn element(&self, row: usize, column: usize) -> f64 {
+        self.matrix[row][column]
+    }
+
+    pub fn set_element(&mut self, row: usize, column: usize, value: f64) {
+        self.matrix[row][column] = value;
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        let m = &self.matrix;
+        Matrix4 {
+            matrix: [
+                [m[0][0], m[1][0], m[2][0], m[3][0]],
+                [m[0][1], m[1][1], m[2][1], m[3][1]],
+                [m[0][2], m[1][2], m[2][2], m[3][2]],
+                [m[0][3], m[1][3], m[2][3], m[3][3]],
+            ],
+        }
+    }
+
+    /// 2x2 determinants of the two upper rows (`s`) and of the two lower rows (`c`),
+    /// from which the determinant and the inverse are both expanded
+    fn sub_determinants(&self) -> ([f64; 6], [f64; 6]) {
+        let m = &self.matrix;
+        let s = [
+            m[0][0] * m[1][1] - m[1][0] * m[0][1],
+            m[0][0] * m[1][2] - m[1][0] * m[0][2],
+            m[0][0] * m[1][3] - m[1][0] * m[0][3],
+            m[0][1] * m[1][2] - m[1][1] * m[0][2],
+            m[0][1] * m[1][3] - m[1][1] * m[0][3],
+            m[0][2] * m[1][3] - m[1][2] * m[0][3],
+        ];
+        let c = [
+            m[2][0] * m[3][1] - m[3][0] * m[2][1],
+            m[2][0] * m[3][2] - m[3][0] * m[2][2],
+            m[2][0] * m[3][3] - m[3][0] * m[2][3],
+            m[2][1] * m[3][2] - m[3][1] * m[2][2],
+            m[2][1] * m[3][3] - m[3][1] * m[2][3],
+            m[2][2] * m[3][3] - m[3][2] * m[2][3],
+        ];
+        (s, c)
+    }
+
+    pub fn determinant(&self) -> f64 {
+        let (s, c) = self.sub_determinants();
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    pub fn is_invertible(&self) -> bool {
+        self.determinant() != 0.0
+    }
+
+    pub fn inverse(&self) -> Result<Matrix4, error::RayTracerError> {
+        let (s, c) = self.sub_determinants();
+        let determinant =
+            s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        if determinant == 0.0 {
+            return Err(error::RayTracerError::new_simple(ErrorEnum::NotInversible));
+        }
+
+        let m = &self.matrix;
+        let inv = 1.0 / determinant;
+        Ok(Matrix4 {
+            matrix: [
+                [
+                    (m[1][1] * c[5] - m[1][2] * c[4] + m[1][3] * c[3]) * inv,
+                    (-m[0][1] * c[5] + m[0][2] * c[4] - m[0][3] * c[3]) * inv,
+                    (m[3][1] * s[5] - m[3][2] * s[4] + m[3][3] * s[3]) * inv,
+                    (-m[2][1] * s[5] + m[2][2] * s[4] - m[2][3] * s[3]) * inv,
+                ],
+                [
+                    (-m[1][0] * c[5] + m[1][2] * c[2] - m[1][3] * c[1]) * inv,
+                    (m[0][0] * c[5] - m[0][2] * c[2] + m[0][3] * c[1]) * inv,
+                    (-m[3][0] * s[5] + m[3][2] * s[2] - m[3][3] * s[1]) * inv,
+                    (m[2][0] * s[5] - m[2][2] * s[2] + m[2][3] * s[1]) * inv,
+                ],
+                [
+                    (m[1][0] * c[4] - m[1][1] * c[2] + m[1][3] * c[0]) * inv,
+                    (-m[0][0] * c[4] + m[0][1] * c[2] - m[0][3] * c[0]) * inv,
+                    (m[3][0] * s[4] - m[3][1] * s[2] + m[3][3] * s[0]) * inv,
+                    (-m[2][0] * s[4] + m[2][1] * s[2] - m[2][3] * s[0]) * inv,
+                ],
+                [
+                    (-m[1][0] * c[3] + m[1][1] * c[1] - m[1][2] * c[0]) * inv,
+                    (m[0][0] * c[3] - m[0][1] * c[1] + m[0][2] * c[0]) * inv,
+                    (-m[3][0] * s[3] + m[3][1] * s[1] - m[3][2] * s[0]) * inv,
+                    (m[2][0] * s[3] - m[2][1] * s[1] + m[2][2] * s[0]) * inv,
+                ],
+            ],
+        })
+    }
 }
 
 #[cfg(test)]
@@ -485,4 +643,87 @@ mod matrix_tests {
         let mb_test = Matrix::with_data(4, data_vector_b_test);
         assert_eq!(mb, mb_test);
     }
+
+    #[test]
+    ///Multiplying 4x4 matrices and a 4x4 matrix by a tuple
+    fn matrix4_multiplication() {
+        let ma = Matrix4::with_data([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 8.0, 7.0, 6.0],
+            [5.0, 4.0, 3.0, 2.0],
+        ]);
+        let mb = Matrix4::with_data([
+            [-2.0, 1.0, 2.0, 3.0],
+            [3.0, 2.0, 1.0, -1.0],
+            [4.0, 3.0, 6.0, 5.0],
+            [1.0, 2.0, 7.0, 8.0],
+        ]);
+        let m_result = Matrix4::with_data([
+            [20.0, 22.0, 50.0, 48.0],
+            [44.0, 54.0, 114.0, 108.0],
+            [40.0, 58.0, 110.0, 102.0],
+            [16.0, 26.0, 46.0, 42.0],
+        ]);
+        assert_eq!(ma * mb, m_result);
+        assert_eq!(ma * Matrix4::new_identity_matrix(), ma);
+
+        let mc = Matrix4::with_data([
+            [1.0, 2.0, 3.0, 4.0],
+            [2.0, 4.0, 4.0, 2.0],
+            [8.0, 6.0, 4.0, 1.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert_eq!(
+            mc * Tuple::new_point(1.0, 2.0, 3.0),
+            Tuple::new_point(18.0, 24.0, 33.0)
+        );
+        assert_eq!(
+            mc * Tuple::new_vector(1.0, 2.0, 3.0),
+            Tuple::new_vector(14.0, 22.0, 32.0)
+        );
+    }
+
+    #[test]
+    ///The closed-form determinant and inverse of a 4x4 matrix agree with the cofactors
+    fn matrix4_inversion() {
+        let data = [
+            [-5.0, 2.0, 6.0, -8.0],
+            [1.0, -5.0, 1.0, 8.0],
+            [7.0, 7.0, -6.0, -7.0],
+            [1.0, -3.0, 7.0, 4.0],
+        ];
+        let ma = Matrix4::with_data(data);
+        let generic = Matrix::with_data(4, data.concat());
+
+        assert_eq!(ma.determinant(), 532.0);
+        assert_eq!(ma.determinant(), generic.determinant());
+
+        let inverse = ma.inverse().unwrap();
+        let generic_inverse = generic.inverse().unwrap();
+        for row in 0..4 {
+            for col in 0..4 {
+                assert!(utils::compare_float(
+                    inverse.element(row, col),
+                    generic_inverse.element(row, col)
+                ));
+            }
+        }
+        assert_eq!(ma * inverse, Matrix4::new_identity_matrix());
+        assert_eq!(ma.transpose().transpose(), ma);
+    }
+
+    #[test]
+    ///A singular 4x4 matrix has no inverse
+    fn matrix4_not_invertible() {
+        let ma = Matrix4::with_data([
+            [-4.0, 2.0, -2.0, -3.0],
+            [9.0, 6.0, 2.0, 6.0],
+            [0.0, -5.0, 1.0, -5.0],
+            [0.0, 0.0, 0.0, 0.0],
+        ]);
+        assert_eq!(ma.determinant(), 0.0);
+        assert!(!ma.is_invertible());
+        assert!(ma.inverse().is_err());
+    }
 }
@@ -0,0 +1,309 @@
+use std::cell::RefCell;
+
+use crate::{
+    color::{self, Color},
+    ray::Ray,
+    shape::object::Object,
+    tuple::Tuple,
+    world::World,
+};
+
+/// Homogeneous fog filling the whole world, surfaces fade to `color` with the distance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub density: f64,
+    pub color: Color,
+}
+
+impl Fog {
+    pub fn new_fog(density: f64, color: Color) -> Fog {
+        Fog { density, color }
+    }
+}
+
+/// Homogeneous participating medium filling a closed object (smoke, murky water...).
+/// Light is extinguished by `density` and scattered once toward the lights with the
+/// `color` albedo, `samples` points being taken along each ray crossing the medium.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Medium {
+    pub density: f64,
+    pub color: Color,
+    pub samples: usize,
+}
+
+impl Medium {
+    pub fn new_medium(density: f64, color: Color, samples: usize) -> Medium {
+        Medium {
+            density,
+            color,
+            samples,
+        }
+    }
+}
+
+thread_local! {
+    /// Distances of the intersections of a ray with one object, reused by each thread
+    static OBJECT_HITS: RefCell<Vec<f64>> = const { RefCell::new(Vec::new()) };
+    /// Parts of a ray inside a medium, reused by each thread
+    static MEDIUM_SEGMENTS: RefCell<Vec<(f64, f64)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Calls `segment` with the start and the end of each part of the `[0, distance]` part of
+/// `ray` inside `object`, the object being closed
+pub fn inside_segments(
+    object: &Object,
+    ray: &Ray,
+    distance: f64,
+    mut segment: impl FnMut(f64, f64),
+) {
+    OBJECT_HITS.with_borrow_mut(|hits| {
+        hits.clear();
+        object.intersect_with(ray, |t| {
+            if t > 0.0 {
+                hits.push(t);
+            }
+        });
+        hits.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        // an odd number of hits ahead means the origin is inside
+        let mut inside = hits.len() % 2 == 1;
+        let mut last = 0.0;
+        for t in hits.iter() {
+            if *t >= distance {
+                break;
+            }
+            if inside {
+                segment(last, *t);
+            }
+            inside = !inside;
+            last = *t;
+        }
+        if inside {
+            segment(last, distance);
+        }
+    })
+}
+
+/// Length of the `[0, distance]` part of `ray` inside `object`, the object being closed
+pub fn inside_length(object: &Object, ray: &Ray, distance: f64) -> f64 {
+    let mut length = 0.0;
+    inside_segments(object, ray, distance, |start, end| length += end - start);
+    length
+}
+
+/// Distance along the ray of the point `length` into the `segments` of a ray
+fn distance_into(segments: &[(f64, f64)], length: f64) -> f64 {
+    let mut remaining = length;
+    for (start, end) in segments {
+        if remaining <= end - start {
+            return start + remaining;
+        }
+        remaining -= end - start;
+    }
+    segments.last().map_or(0.0, |(_, end)| *end)
+}
+
+impl World {
+    /// Blends `color`, seen at `distance` along a ray, with the fog of the world
+    pub fn apply_fog(&self, color: Color, distance: f64) -> Color {
+        match &self.fog {
+            Some(fog) => {
+                let transmittance = (-fog.density * distance).exp();
+                color * transmittance + fog.color * (1.0 - transmittance)
+            }
+            None => color,
+        }
+    }
+
+    /// Attenuates `color`, seen at `distance` along `ray`, by the media the ray crosses on
+    /// the way and adds the light they scatter toward its origin
+    pub fn apply_media(&self, ray: &Ray, distance: f64, color: Color) -> Color {
+        let mut result = color;
+
+        for object in &self.objects {
+            let medium = match &object.material.medium {
+                Some(medium) if medium.density > 0.0 => medium,
+                _ => continue,
+            };
+
+            MEDIUM_SEGMENTS.with_borrow_mut(|segments| {
+                segments.clear();
+                inside_segments(object, ray, distance, |start, end| {
+                    segments.push((start, end))
+                });
+                let length: f64 = segments.iter().map(|(start, end)| end - start).sum();
+                if length <= 0.0 {
+                    return;
+                }
+
+                let transmittance = (-medium.density * length).exp();
+                let mut scattered = color::BLACK;
+                for i in 0..medium.samples {
+                    // free flight distance inside the medium, stratified over the samples
+                    let u = (i as f64 + rand::random::<f64>()) / medium.samples as f64;
+                    let s = -(1.0 - u * (1.0 - transmittance)).ln() / medium.density;
+                    let point = ray.position(distance_into(segments, s));
+                    scattered += self.in_scattering(&point, ray.time);
+                }
+                scattered = scattered
+                    * medium.color
+                    * ((1.0 - transmittance) / medium.samples.max(1) as f64);
+
+                result = result * transmittance + scattered;
+            });
+        }
+        result
+    }
+
+    /// Light reaching a point of a medium at `time` from every light
+    fn in_scattering(&self, point: &Tuple, time: f64) -> Color {
+        let sampled_lights = self.sample_emissive_lights(point, time);
+        self.light_sources
+            .iter()
+            .chain(sampled_lights.iter())
+            .fold(color::BLACK, |sum, light| {
+                sum + light.intensity_at(point) * self.light_visibility_at(point, light, time)
+            })
+    }
+}
+
+#[cfg(test)]
+mod media_tests {
+    use super::*;
+    use crate::{reflection::PointLight, utils};
+
+    fn medium_sphere(density: f64) -> Object {
+        let mut s = Object::new_sphere();
+        s.material.ambient = 0.0;
+        s.material.diffuse = 0.0;
+        s.material.specular = 0.0;
+        s.material.transparency = 1.0;
+        s.material.medium = Some(Medium::new_medium(density, color::WHITE, 2000));
+        s
+    }
+
+    #[test]
+    // Length of a ray segment inside an object, from inside and from outside
+    fn inside_length_test() {
+        let s = Object::new_sphere();
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(utils::compare_float(inside_length(&s, &r, 10.0), 2.0));
+        assert!(utils::compare_float(inside_length(&s, &r, 5.0), 1.0));
+        assert!(utils::compare_float(inside_length(&s, &r, 3.0), 0.0));
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(utils::compare_float(inside_length(&s, &r, 0.5), 0.5));
+        assert!(utils::compare_float(inside_length(&s, &r, 10.0), 1.0));
+    }
+
+    #[test]
+    // Fog fades the surfaces with the distance and fills the background
+    fn fog_test() {
+        let fog = Fog::new_fog(0.1, color::WHITE);
+        let w = World::default_world().with_fog(fog);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let transmittance = (-0.4_f64).exp();
+        let expected = Color::new_color(0.38066, 0.47583, 0.2855) * transmittance
+            + color::WHITE * (1.0 - transmittance);
+        assert_eq!(w.color_at(&r, 5), expected);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        assert_eq!(w.color_at(&r, 5), color::WHITE);
+    }
+
+    #[test]
+    // Light going through a medium is attenuated along the way inside it
+    fn medium_shadow_test() {
+        let mut w = World::new_world();
+        w.add_object(medium_sphere(0.5));
+        let light = PointLight::new_point_light(color::WHITE, Tuple::new_point(0.0, 0.0, -10.0));
+
+        let visibility = w.light_visibility(&Tuple::new_point(0.0, 0.0, 0.0), &light);
+        let expected = (-0.5_f64).exp();
+        assert_eq!(visibility, Color::new_color(expected, expected, expected));
+    }
+
+    #[test]
+    // A medium without density changes nothing
+    fn medium_zero_density_test() {
+        let mut w = World::new_world();
+        w.add_object(medium_sphere(0.0));
+        w.light_sources.push(PointLight::new_point_light(
+            color::WHITE,
+            Tuple::new_point(0.0, 0.0, -10.0),
+        ));
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(w.apply_media(&r, 1.0, color::WHITE), color::WHITE);
+        assert_eq!(w.color_at(&r, 5), color::BLACK);
+    }
+
+    #[test]
+    // Only the part of the ray inside the bounds of the medium attenuates and scatters,
+    // the light at the center giving ∫σe^(-σs)e^(-σ|s-1|) over the diameter
+    fn medium_bounded_test() {
+        let mut w = World::new_world();
+        let mut s = medium_sphere(1.0);
+        s.material.medium = Some(Medium::new_medium(1.0, color::BLACK, 8));
+        w.add_object(s);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let expected = (-2.0_f64).exp();
+        assert_eq!(
+            w.apply_media(&r, 10.0, color::WHITE),
+            Color::new_color(expected, expected, expected)
+        );
+
+        let mut w = World::new_world();
+        w.add_object(medium_sphere(1.0));
+        w.light_sources.push(PointLight::new_point_light(
+            color::WHITE,
+            Tuple::new_point(0.0, 0.0, 0.0),
+        ));
+        let expected = (-1.0_f64).exp() + ((-1.0_f64).exp() - (-3.0_f64).exp()) / 2.0;
+        let difference =
+            w.apply_media(&r, 10.0, color::BLACK) - Color::new_color(expected, expected, expected);
+        assert!(difference.max_component() < 0.01);
+        assert!((difference * -1.0).max_component() < 0.01);
+    }
+
+    #[test]
+    // Inside a sphere of smoke lit from its center, the light scattered toward the
+    // center over the unit radius is (1 - e^-2σ) / 2
+    fn medium_scattering_test() {
+        let mut w = World::new_world();
+        w.add_object(medium_sphere(1.0));
+        w.light_sources.push(PointLight::new_point_light(
+            color::WHITE,
+            Tuple::new_point(0.0, 0.0, 0.0),
+        ));
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let expected = (1.0 - (-2.0_f64).exp()) / 2.0;
+        let difference = w.color_at(&r, 5) - Color::new_color(expected, expected, expected);
+        assert!(difference.max_component() < 0.01);
+        assert!((difference * -1.0).max_component() < 0.01);
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    animation::Interpolation,
+    camera::Camera,
+    color::{self, Color},
+    matrix::Matrix4,
+    shape::object::{Object, Placement},
+    transformation::{Transformation, create_transform},
+};
+
+/// Source of the ids of the motions, keying their inverses in `SAMPLE_INVERSES`
+static NEXT_MOTION_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Time of the last shutter sample traced by each thread and the inverses of the
+    /// motions at that time, by id, reused by all the rays of the sample
+    static SAMPLE_INVERSES: RefCell<(f64, Vec<(usize, Matrix4)>)> =
+        const { RefCell::new((f64::NAN, Vec::new())) };
+}
+
+/// Transform of a moving object at keyframed times, in seconds, blurred by the shutter of
+/// the camera
+#[derive(Debug, Clone)]
+pub struct Motion {
+    id: usize,
+    /// Times of the keyframes, sorted
+    times: Vec<f64>,
+    transforms: Vec<Vec<Transformation>>,
+    /// Transform at each keyframe and its inverse
+    keyframes: Vec<(Matrix4, Matrix4)>,
+}
+
+impl PartialEq for Motion {
+    fn eq(&self, other: &Self) -> bool {
+        self.times == other.times && self.transforms == other.transforms
+    }
+}
+
+impl Motion {
+    pub fn new_motion(mut keyframes: Vec<(f64, Vec<Transformation>)>) -> Motion {
+        assert!(
+            !keyframes.is_empty(),
+            "a motion needs at least one keyframe"
+        );
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (times, transforms): (Vec<f64>, Vec<Vec<Transformation>>) =
+            keyframes.into_iter().unzip();
+        let keyframes = transforms
+            .iter()
+            .map(|transformations| {
+                let transform = create_transform(transformations);
+                (transform, transform.inverse().unwrap())
+            })
+            .collect();
+        Motion {
+            id: NEXT_MOTION_ID.fetch_add(1, Ordering::Relaxed),
+            times,
+            transforms,
+            keyframes,
+        }
+    }
+
+    /// Transform at `time`, held before the first keyframe and after the last one. The
+    /// parameters of the transformations are blended linearly between two keyframes listing
+    /// the same kinds of transformations, so that rotations turn instead of shrinking, and
+    /// the transform otherwise switches at the next keyframe
+    pub fn transform_at(&self, time: f64) -> Matrix4 {
+        let (previous, next, factor) = Interpolation::Linear.segment(&self.times, time);
+        self.blended(previous, next, factor)
+            .unwrap_or(self.keyframes[previous].0)
+    }
+
+    /// Inverse of `transform_at`, taken from the keyframes or computed once per thread for
+    /// each shutter sample
+    pub fn inverse_at(&self, time: f64) -> Matrix4 {
+        let (previous, next, factor) = Interpolation::Linear.segment(&self.times, time);
+        let transform = match self.blended(previous, next, factor) {
+            Some(transform) => transform,
+            None => return self.keyframes[previous].1,
+        };
+
+        SAMPLE_INVERSES.with_borrow_mut(|(sample_time, inverses)| {
+            if *sample_time != time {
+                *sample_time = time;
+                inverses.clear();
+            }
+            if let Some((_, inverse)) = inverses.iter().find(|(id, _)| *id == self.id) {
+                return *inverse;
+            }
+            let inverse = transform.inverse().unwrap();
+            inverses.push((self.id, inverse));
+            inverse
+        })
+    }
+
+    /// Transform `factor` of the way from the keyframe `previous` to the keyframe `next`,
+    /// `None` when it is the one of `previous`
+    fn blended(&self, previous: usize, next: usize, factor: f64) -> Option<Matrix4> {
+        let (from, to) = (&self.transforms[previous], &self.transforms[next]);
+        if factor == 0.0 || from.len() != to.len() {
+            return None;
+        }
+        from.iter()
+            .zip(to)
+            .try_fold(Matrix4::new_identity_matrix(), |transform, (a, b)| {
+                Some(a.lerp(b, factor)?.matrix() * transform)
+            })
+    }
+}
+
+/// Interval the shutter of the camera stays open, in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shutter {
+    pub open: f64,
+    pub close: f64,
+    /// Times averaged for each pixel by the integrators tracing a single ray, path tracing
+    /// giving its own time to each of its samples
+    pub samples: usize,
+}
+
+impl Shutter {
+    pub fn new_shutter(open: f64, close: f64, samples: usize) -> Shutter {
+        Shutter {
+            open,
+            close,
+            samples,
+        }
+    }
+
+    /// Shutter taking the picture at `time`, without motion blur
+    pub fn new_instant(time: f64) -> Shutter {
+        Shutter::new_shutter(time, time, 1)
+    }
+
+    /// Time `u` of the way through the interval, `u` being in `[0, 1)`
+    pub fn time(&self, u: f64) -> f64 {
+        self.open + (self.close - self.open) * u
+    }
+
+    /// Same interval `offset` seconds later
+    pub fn shifted(&self, offset: f64) -> Shutter {
+        Shutter::new_shutter(self.open + offset, self.close + offset, self.samples)
+    }
+
+    pub fn is_instant(&self) -> bool {
+        self.open == self.close
+    }
+}
+
+impl Object {
+    pub fn with_motion(mut self, motion: Option<Motion>) -> Self {
+        self.motion = motion;
+        self
+    }
+
+    /// Inverse of the transform at `time`, only computed for moving objects
+    pub fn inverse_at(&self, time: f64) -> Cow<'_, Matrix4> {
+        match &self.motion {
+            Some(motion) => Cow::Owned(motion.inverse_at(time)),
+            None => Cow::Borrowed(&self.inverse),
+        }
+    }
+
+    /// Transform of the object at `time`, to compute its normals, patterns and samples there
+    pub fn placement_at(&self, time: f64) -> Placement {
+        match &self.motion {
+            Some(motion) => {
+                let inverse = motion.inverse_at(time);
+                Placement {
+                    transform: motion.transform_at(time),
+                    inverse,
+                    inverse_transpose: inverse.transpose(),
+                }
+            }
+            None => self.placement(),
+        }
+    }
+}
+
+impl Camera {
+    pub fn with_shutter(mut self, shutter: Shutter) -> Self {
+        self.shutter = shutter;
+        self
+    }
+
+    /// Average of `trace` over the times the shutter is open, the interval being split in
+    /// `samples` strata each jittered once
+    pub(crate) fn over_shutter(&self, mut trace: impl FnMut(f64) -> Color) -> Color {
+        let shutter = self.shutter;
+        if shutter.is_instant() || shutter.samples <= 1 {
+            return trace(self.sample_time());
+        }
+
+        let mut color = color::BLACK;
+        for i in 0..shutter.samples {
+            let u = (i as f64 + rand::random::<f64>()) / shutter.samples as f64;
+            color += trace(shutter.time(u));
+        }
+        color * (1.0 / shutter.samples as f64)
+    }
+
+    /// Random time while the shutter is open
+    pub(crate) fn sample_time(&self) -> f64 {
+        if self.shutter.is_instant() {
+            return self.shutter.open;
+        }
+        self.shutter.time(rand::random())
+    }
+}
+
+#[cfg(test)]
+mod motion_tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::{
+        camera::View,
+        ray::Ray,
+        reflection::{Material, PointLight},
+        transformation::{create_rotation_y, create_translation},
+        tuple::Tuple,
+        utils,
+        world::World,
+    };
+
+    fn moving_sphere() -> Object {
+        let motion = Motion::new_motion(vec![
+            (1.0, vec![Transformation::Translation(2.0, 0.0, 0.0)]),
+            (0.0, vec![Transformation::Translation(0.0, 0.0, 0.0)]),
+        ]);
+        Object::new_sphere().with_motion(Some(motion))
+    }
+
+    #[test]
+    // The transform is blended between the keyframes and held outside of them
+    fn motion_transform() {
+        let s = moving_sphere();
+        let motion = s.motion.as_ref().unwrap();
+        assert_eq!(motion.transform_at(0.25), create_translation(0.5, 0.0, 0.0));
+        assert_eq!(motion.transform_at(-1.0), Matrix4::new_identity_matrix());
+        assert_eq!(motion.transform_at(3.0), create_translation(2.0, 0.0, 0.0));
+        // the inverses are reused at the keyframes and for the rays of a shutter sample
+        for time in [-1.0, 0.0, 0.25, 0.25, 1.0] {
+            assert_eq!(
+                motion.inverse_at(time),
+                motion.transform_at(time).inverse().unwrap()
+            );
+        }
+        let other = Motion::new_motion(vec![
+            (0.0, vec![Transformation::Translation(0.0, 0.0, 0.0)]),
+            (1.0, vec![Transformation::Translation(0.0, 4.0, 0.0)]),
+        ]);
+        assert_eq!(other.inverse_at(0.25), create_translation(0.0, -1.0, 0.0));
+        assert_eq!(motion.inverse_at(0.25), create_translation(-0.5, 0.0, 0.0));
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(s.intersect(&r).len(), 2);
+        assert!(s.intersect(&r.clone().with_time(0.75)).is_empty());
+
+        let moved = s.placement_at(0.5);
+        assert_eq!(moved.transform, create_translation(1.0, 0.0, 0.0));
+        assert_eq!(
+            s.shape
+                .local_normal_at(&moved, Tuple::new_point(1.0, 0.0, -1.0)),
+            Tuple::new_vector(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    // A rotating sphere keeps its silhouette all along its motion
+    fn motion_rotation() {
+        let motion = Motion::new_motion(vec![
+            (0.0, vec![Transformation::RotationY(0.0)]),
+            (1.0, vec![Transformation::RotationY(PI)]),
+        ]);
+        assert_eq!(motion.transform_at(0.5), create_rotation_y(PI / 2.0));
+
+        let s = Object::new_sphere().with_motion(Some(motion));
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        for time in [0.0, 0.25, 0.5, 0.75, 1.0] {
+            let xs = s.intersect(&r.clone().with_time(time));
+            assert_eq!(xs.len(), 2);
+            assert!(utils::compare_float(xs[0].t, 4.0) && utils::compare_float(xs[1].t, 6.0));
+            assert_eq!(
+                s.shape
+                    .local_normal_at(&s.placement_at(time), Tuple::new_point(0.0, 0.0, -1.0)),
+                Tuple::new_vector(0.0, 0.0, -1.0)
+            );
+        }
+    }
+
+    #[test]
+    // Emissive objects are sampled where they stand at the time of the shaded point
+    fn motion_emissive_light() {
+        let motion = Motion::new_motion(vec![
+            (0.0, vec![Transformation::Scaling(1.0, 1.0, 1.0)]),
+            (1.0, vec![Transformation::Scaling(2.0, 2.0, 2.0)]),
+        ]);
+        let mut w = World::new_world();
+        w.add_emissive_light(vec![Object::new_sphere().with_motion(Some(motion))], 1);
+
+        let point = Tuple::new_point(10.0, 0.0, 0.0);
+        for (time, radius) in [(0.0, 1.0), (1.0, 2.0)] {
+            let (_, position, _, inverse_pdf) =
+                w.sample_emissive_light(&w.emissive_lights[0], &point, time);
+            let distance = (position - Tuple::new_point(0.0, 0.0, 0.0)).magnitude();
+            assert!(utils::compare_float(distance, radius));
+            assert!(utils::compare_float(
+                inverse_pdf,
+                2.0 * PI * radius * radius
+            ));
+        }
+    }
+
+    #[test]
+    // A pixel the object leaves halfway through the exposure is half covered
+    fn motion_blur() {
+        let mut w = World::new_world();
+        w.light_sources.push(PointLight::new_point_light(
+            color::WHITE,
+            Tuple::new_point(0.0, 0.0, -10.0),
+        ));
+        let mut material = Material::default_material();
+        material.ambient = 1.0;
+        material.diffuse = 0.0;
+        material.specular = 0.0;
+        w.add_object(moving_sphere().with_material(material));
+
+        let view = View::new_view(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        let c = Camera::new(11, 11, PI / 2.0).with_view(view);
+        assert_eq!(c.color_at(&w, 5, 5), color::WHITE);
+
+        let c = c.with_shutter(Shutter::new_shutter(0.0, 1.0, 2));
+        assert_eq!(c.color_at(&w, 5, 5), Color::new_color(0.5, 0.5, 0.5));
+        assert_eq!(Shutter::new_instant(2.0).shifted(1.0).time(0.5), 3.0);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{
+    color::{self, Color},
+    ray::Ray,
+    sampling::cosine_hemisphere,
+    shape::object::Object,
+    tuple::Tuple,
+    world::{SAMPLE_EPSILON, World},
+};
+
+/// Ambient occlusion settings, `samples` rays are cast from each shaded point
+/// and the surfaces closer than `distance` hide the ambient light
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmbientOcclusion {
+    pub samples: usize,
+    pub distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new_ambient_occlusion(samples: usize, distance: f64) -> AmbientOcclusion {
+        AmbientOcclusion { samples, distance }
+    }
+}
+
+impl World {
+    /// Fraction of the hemisphere around `normal` left open at `point`, from 0 in a closed
+    /// crevice to 1 on an unobstructed surface. Objects casting no shadow don't occlude.
+    pub fn ambient_occlusion(
+        &self,
+        point: &Tuple,
+        normal: &Tuple,
+        settings: &AmbientOcclusion,
+    ) -> f64 {
+        if settings.samples == 0 {
+            return 1.0;
+        }
+
+        // the shadow offset is too small for the surface not to occlude itself far from the origin
+        let origin = point.clone() + normal.clone() * SAMPLE_EPSILON;
+        let mut occluded = 0;
+        for _ in 0..settings.samples {
+            let direction = cosine_hemisphere(normal, rand::random(), rand::random());
+            let r = Ray::new(origin.clone(), direction);
+            if self.any_hit(&r, settings.distance, Object::has_shadow) {
+                occluded += 1;
+            }
+        }
+        1.0 - occluded as f64 / settings.samples as f64
+    }
+
+    /// Gray level of the ambient occlusion where `ray` hits, white when it misses
+    pub fn occlusion_at(&self, ray: &Ray, settings: &AmbientOcclusion) -> Color {
+        let hit = match self.hit(ray) {
+            Some(hit) => hit,
+            None => return color::WHITE,
+        };
+
+        let comps = self.computations_at(&hit, ray);
+        let occlusion = self.ambient_occlusion(&comps.over_point, &comps.normalv, settings);
+        Color::new_color(occlusion, occlusion, occlusion)
+    }
+}
+
+#[cfg(test)]
+mod occlusion_tests {
+    use super::*;
+    use crate::{transformation, utils};
+
+    #[test]
+    // Nothing occludes a point on a lone plane
+    fn ambient_occlusion_open() {
+        let mut w = World::new_world();
+        w.add_object(Object::new_plane());
+        let settings = AmbientOcclusion::new_ambient_occlusion(16, 10.0);
+        let occlusion = w.ambient_occlusion(
+            &Tuple::new_point(0.0, SAMPLE_EPSILON, 0.0),
+            &Tuple::new_vector(0.0, 1.0, 0.0),
+            &settings,
+        );
+        assert!(utils::compare_float(occlusion, 1.0));
+    }
+
+    #[test]
+    // A point inside a sphere is fully occluded, unless the sphere is farther than the distance
+    fn ambient_occlusion_closed() {
+        let mut w = World::new_world();
+        let mut s = Object::new_sphere();
+        s.set_transform(&transformation::create_scaling(2.0, 2.0, 2.0));
+        w.add_object(s);
+
+        let point = Tuple::new_point(0.0, 0.0, 0.0);
+        let normal = Tuple::new_vector(0.0, 1.0, 0.0);
+
+        let near = AmbientOcclusion::new_ambient_occlusion(16, 3.0);
+        assert!(utils::compare_float(
+            w.ambient_occlusion(&point, &normal, &near),
+            0.0
+        ));
+
+        let far = AmbientOcclusion::new_ambient_occlusion(16, 1.0);
+        assert!(utils::compare_float(
+            w.ambient_occlusion(&point, &normal, &far),
+            1.0
+        ));
+    }
+
+    #[test]
+    // Objects that cast no shadow don't occlude
+    fn ambient_occlusion_no_shadow() {
+        let mut w = World::new_world();
+        let mut s = Object::new_sphere();
+        s.shadow = false;
+        w.add_object(s);
+
+        let settings = AmbientOcclusion::new_ambient_occlusion(16, 3.0);
+        let occlusion = w.ambient_occlusion(
+            &Tuple::new_point(0.0, 0.0, 0.0),
+            &Tuple::new_vector(0.0, 1.0, 0.0),
+            &settings,
+        );
+        assert!(utils::compare_float(occlusion, 1.0));
+    }
+
+    #[test]
+    // The occlusion render is white on a miss and black in a closed room
+    fn occlusion_at_render() {
+        let mut w = World::new_world();
+        w.add_object(Object::new_sphere());
+        let settings = AmbientOcclusion::new_ambient_occlusion(16, 3.0);
+
+        let miss = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        assert_eq!(w.occlusion_at(&miss, &settings), color::WHITE);
+
+        let inside = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(w.occlusion_at(&inside, &settings), color::BLACK);
+    }
+}
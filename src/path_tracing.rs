@@ -0,0 +1,302 @@
+use std::f64::consts::PI;
+
+use crate::{
+    color::{self, Color},
+    ray::Ray,
+    sampling::cosine_hemisphere,
+    tuple::Tuple,
+    world::{Computation, World},
+};
+
+/// Bounces always traced before Russian roulette can end a path
+pub const MIN_BOUNCES: usize = 3;
+pub const MAX_BOUNCES: usize = 64;
+/// Highest probability for a path to survive Russian roulette
+const MAX_SURVIVAL: f64 = 0.95;
+
+impl World {
+    /// Monte Carlo estimate of the light coming back along `ray`.
+    ///
+    /// Each hit follows one of the diffuse, mirror or refraction lobes of the material,
+    /// picked proportionally to their weights. Diffuse bounces are cosine weighted and
+    /// gather the point and emissive lights directly (next-event estimation), so an
+    /// emissive light only adds its emission when reached by a camera or specular ray.
+    /// The ambient and specular terms of the material are not used.
+    pub fn path_trace(&self, ray: &Ray) -> Color {
+        let mut radiance = color::BLACK;
+        let mut throughput = color::WHITE;
+        let mut ray = ray.clone();
+        let mut count_emission = true;
+
+        for bounce in 0..MAX_BOUNCES {
+            let hit = match self.hit(&ray) {
+                Some(hit) => hit,
+                None => break,
+            };
+
+            let comps = self.computations_at(&hit, &ray);
+            let material = comps.object.get_material();
+
+            // Beer–Lambert absorption along the way out of an object
+            if comps.inside && material.absorption != color::BLACK {
+                throughput =
+                    throughput * (material.absorption * (-material.density * comps.t)).exp();
+            }
+
+            if count_emission || !self.is_emissive_light(comps.object) {
+                radiance += throughput * material.emission;
+            }
+
+            let albedo = material
+                .color_at_local(comps.placement.inverse * comps.over_point.clone())
+                * material.diffuse;
+            let diffuse_weight = albedo.max_component().max(0.0);
+            let mut reflect_weight = material.reflective;
+            let mut refract_weight = material.transparency;
+            if reflect_weight > 0.0 && refract_weight > 0.0 {
+                let reflectance = comps.schlick();
+                reflect_weight *= reflectance;
+                refract_weight *= 1.0 - reflectance;
+            }
+
+            let total_weight = diffuse_weight + reflect_weight + refract_weight;
+            if total_weight <= 0.0 {
+                break;
+            }
+
+            let lobe = rand::random::<f64>() * total_weight;
+            if lobe < diffuse_weight {
+                throughput = throughput * albedo * (total_weight / diffuse_weight);
+                radiance += throughput * self.direct_lighting(&comps);
+
+                let direction = cosine_hemisphere(&comps.normalv, rand::random(), rand::random());
+                ray = Ray::new(comps.over_point.clone(), direction).with_time(comps.time);
+                count_emission = false;
+            } else {
+                throughput = throughput * total_weight;
+                let refracted = if lobe < diffuse_weight + reflect_weight {
+                    None
+                } else {
+                    comps.refracted_direction()
+                };
+                ray = match refracted {
+                    Some(direction) => Ray::new(comps.under_point.clone(), direction),
+                    // total internal reflection falls back to the mirror
+                    None => Ray::new(comps.over_point.clone(), comps.reflectv.clone()),
+                }
+                .with_time(comps.time);
+                count_emission = true;
+            }
+
+            if bounce + 1 >= MIN_BOUNCES {
+                let survival = throughput.max_component().min(MAX_SURVIVAL);
+                if rand::random::<f64>() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+        radiance
+    }
+
+    /// Light reflected at a diffuse hit from the lights, without the albedo. The 1/π of
+    /// a Lambertian surface applies to the point lights as it does to the samples of the
+    /// emissive lights.
+    fn direct_lighting(&self, comps: &Computation) -> Color {
+        let mut direct = color::BLACK;
+
+        for light in &self.light_sources {
+            let lightv = (light.position.clone() - comps.over_point.clone()).normalize();
+            let cos = Tuple::dot_product(&lightv, &comps.normalv);
+            if cos > 0.0 {
+                direct += light.intensity_at(&comps.over_point)
+                    * (cos / PI)
+                    * self.light_visibility_at(&comps.over_point, light, comps.time);
+            }
+        }
+
+        for light in &self.emissive_lights {
+            for _ in 0..light.samples {
+                let sample = self.emissive_sample(light, &comps.over_point, comps.time);
+                let lightv = (sample.position.clone() - comps.over_point.clone()).normalize();
+                let cos = Tuple::dot_product(&lightv, &comps.normalv);
+                if cos <= 0.0 || sample.intensity.max_component() <= 0.0 {
+                    continue;
+                }
+
+                direct += sample.intensity
+                    * cos
+                    * self.light_visibility_at(&comps.over_point, &sample, comps.time);
+            }
+        }
+        direct
+    }
+}
+
+#[cfg(test)]
+mod path_tracing_tests {
+    use super::*;
+    use crate::{
+        camera::Integrator,
+        io::yaml::parse,
+        reflection::{self, PointLight},
+        shape::object::Object,
+        transformation,
+    };
+
+    const SAMPLES: usize = 4000;
+
+    fn average(w: &World, r: &Ray) -> Color {
+        let mut sum = color::BLACK;
+        for _ in 0..SAMPLES {
+            sum += w.path_trace(r);
+        }
+        sum * (1.0 / SAMPLES as f64)
+    }
+
+    fn furnace_sphere() -> Object {
+        let mut s = Object::new_sphere();
+        s.material.color = color::WHITE;
+        s.material.diffuse = 0.5;
+        s.set_emission(Color::new_color(0.5, 0.5, 0.5));
+        s
+    }
+
+    fn assert_close(color: Color, expected: f64) {
+        let difference = color - Color::new_color(expected, expected, expected);
+        assert!(
+            difference.max_component() < 0.05 && (difference * -1.0).max_component() < 0.05,
+            "{:?} is not close to {}",
+            color,
+            expected
+        );
+    }
+
+    #[test]
+    // A ray that misses everything carries no light
+    fn path_trace_miss() {
+        let w = World::default_world();
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        assert_eq!(w.path_trace(&r), color::BLACK);
+    }
+
+    #[test]
+    // Inside a closed sphere of albedo a emitting e, the radiance is e / (1 - a)
+    fn path_trace_furnace() {
+        let mut w = World::new_world();
+        w.add_object(furnace_sphere());
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_close(average(&w, &r), 1.0);
+    }
+
+    #[test]
+    // The furnace converges to the same value when the sphere is sampled as a light
+    fn path_trace_furnace_emissive_light() {
+        let mut w = World::new_world();
+        w.add_emissive_light(vec![furnace_sphere()], 1);
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert_close(average(&w, &r), 1.0);
+    }
+
+    #[test]
+    // The Whitted integrator lights a diffuse surface like path tracing does with the samples
+    // of an emissive light
+    fn path_trace_whitted_emissive_light() {
+        let mut w = World::new_world();
+        let mut floor = Object::new_plane();
+        floor.material.ambient = 0.0;
+        floor.material.specular = 0.0;
+        w.add_object(floor);
+
+        let mut lamp = Object::new_quad();
+        lamp.set_emission(Color::new_color(2.0, 2.0, 2.0));
+        lamp.material.diffuse = 0.0;
+        lamp.set_transform(&transformation::create_translation(0.0, 2.0, 0.0));
+        w.add_emissive_light(vec![lamp], 1);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 1.0, -1.0),
+            Tuple::new_vector(0.0, -1.0, 1.0).normalize(),
+        );
+        let mut whitted = color::BLACK;
+        for _ in 0..SAMPLES {
+            whitted += w.color_at(&r, reflection::MAX_RECURTION);
+        }
+        let whitted = whitted * (1.0 / SAMPLES as f64);
+
+        assert!(whitted.max_component() > 0.1);
+        assert_close(average(&w, &r), whitted.max_component());
+    }
+
+    #[test]
+    // A point light over a lone diffuse plane lights it with the 1/π of a Lambertian surface
+    fn path_trace_point_light() {
+        let mut w = World::new_world();
+        w.add_object(Object::new_plane());
+        w.light_sources.push(PointLight::new_point_light(
+            color::WHITE,
+            Tuple::new_point(0.0, 2.0, 0.0),
+        ));
+        let r = Ray::new(
+            Tuple::new_point(0.0, 1.0, 0.0),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+        assert_close(w.path_trace(&r), 0.9 / PI);
+    }
+
+    #[test]
+    // The mean radiance of a small render of the Cornell box matches a reference rendered
+    // with 65536 samples per pixel
+    fn path_trace_cornell_box() {
+        let (w, camera) = parse(std::path::Path::new("scenes/cornell.yml"));
+        let camera = camera
+            .with_size(16, 16)
+            .with_integrator(Integrator::PathTracing { samples: 512 });
+        let canvas = camera.render(&w);
+
+        let mut mean = color::BLACK;
+        for y in 0..16 {
+            for x in 0..16 {
+                mean += canvas.pixel_at(x, y) * (1.0 / 256.0);
+            }
+        }
+        let reference = Color::new_color(0.49992, 0.45627, 0.41140);
+        assert!(
+            (mean - reference).map(f64::abs).max_component() < 0.02,
+            "{:?} is not close to {:?}",
+            mean,
+            reference
+        );
+    }
+
+    #[test]
+    // A perfect mirror shows the emission of the object it reflects
+    fn path_trace_mirror() {
+        let mut w = World::new_world();
+        let mut mirror = Object::new_plane();
+        mirror.material.diffuse = 0.0;
+        mirror.material.reflective = 1.0;
+        w.add_object(mirror);
+
+        let mut lamp = Object::new_sphere();
+        lamp.set_emission(Color::new_color(0.2, 0.4, 0.6));
+        lamp.material.diffuse = 0.0;
+        lamp.set_transform(&transformation::create_translation(0.0, 5.0, 0.0));
+        w.add_emissive_light(vec![lamp], 1);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 1.0, 0.0),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+        assert_eq!(w.path_trace(&r), Color::new_color(0.2, 0.4, 0.6));
+    }
+}
@@ -2,7 +2,7 @@ use serde::Serialize;
 
 use crate::{
     color::{self, Color},
-    matrix::{Matrix, memoized_inverse},
+    matrix::Matrix4,
     shape::{object::Object, shape::Shape},
     tuple::Tuple,
     utils,
@@ -11,8 +11,8 @@ use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Pattern {
-    pub transformation: Matrix,
-    transformation_inverse: Matrix,
+    pub transformation: Matrix4,
+    transformation_inverse: Matrix4,
     pub pattern: Patterns,
 }
 
@@ -22,8 +22,8 @@ impl Default for Pattern {
             pattern: Patterns::Plain(PlainPattern {
                 color: color::WHITE,
             }),
-            transformation: Matrix::new_identity_matrix(4),
-            transformation_inverse: Matrix::new_identity_matrix(4),
+            transformation: Matrix4::new_identity_matrix(),
+            transformation_inverse: Matrix4::new_identity_matrix(),
         }
     }
 }
@@ -85,12 +85,13 @@ impl Pattern {
         }
     }
 
-    pub fn get_transform(&self) -> Matrix {
-        self.transformation.clone()
+    pub fn get_transform(&self) -> Matrix4 {
+        self.transformation
     }
 
-    pub fn set_transform(&mut self, new_transformation: &Matrix) {
-        self.transformation = new_transformation.clone();
+    pub fn set_transform(&mut self, new_transformation: &Matrix4) {
+        self.transformation = *new_transformation;
+        self.transformation_inverse = self.transformation.inverse().unwrap();
     }
 
     pub fn color_at_point(&self, point: &Tuple) -> Color {
@@ -106,8 +107,12 @@ impl Pattern {
     }
 
     pub fn color_at_object(&self, obj: &Object, point: Tuple) -> Color {
-        let obj_point = memoized_inverse(obj.get_transform()).unwrap() * point;
-        let pattern_point = memoized_inverse(self.get_transform()).unwrap() * obj_point;
+        self.color_at_local(obj.get_inverse() * point)
+    }
+
+    /// Color at a point of the object space, where the object may stand elsewhere than at rest
+    pub fn color_at_local(&self, obj_point: Tuple) -> Color {
+        let pattern_point = self.transformation_inverse * obj_point;
         self.color_at_point(&pattern_point)
     }
 }
@@ -344,8 +349,8 @@ mod matrix_tests {
             &Tuple::new_point(0.9, 0.0, 0.0),
             &eyev,
             &normalv,
-            false,
-            Object::new_sphere(),
+            color::WHITE,
+            &Object::new_sphere(),
         );
         let c2 = reflection::lighting(
             &m,
@@ -353,8 +358,8 @@ mod matrix_tests {
             &Tuple::new_point(1.1, 0.0, 0.0),
             &eyev,
             &normalv,
-            false,
-            Object::new_sphere(),
+            color::WHITE,
+            &Object::new_sphere(),
         );
 
         assert_eq!(c1, color::WHITE);
@@ -399,7 +404,7 @@ mod matrix_tests {
     // Scenario: The default pattern transformation
     fn default_test_pattern() {
         let pattern = Pattern::new_test_pattern();
-        assert_eq!(pattern.get_transform(), Matrix::new_identity_matrix(4));
+        assert_eq!(pattern.get_transform(), Matrix4::new_identity_matrix());
     }
 
     #[test]
@@ -0,0 +1,242 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread::{self, JoinHandle},
+};
+
+use rayon::prelude::*;
+
+use crate::{camera::Camera, canvas::Canvas, world::World};
+
+/// Step of a progressive render
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pass {
+    /// One ray traced for each `size` x `size` block of pixels, `Blocks(1)` being the
+    /// first full resolution image
+    Blocks(usize),
+    /// Every pixel averaging `samples` jittered samples
+    Samples(usize),
+}
+
+/// Progressive render settings: the first pass traces one ray per `block_size` square of
+/// pixels, the blocks are halved down to single pixels, then passes of jittered samples are
+/// accumulated until each pixel has `samples` of them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progressive {
+    pub block_size: usize,
+    pub samples: usize,
+}
+
+impl Progressive {
+    pub fn new_progressive(block_size: usize, samples: usize) -> Progressive {
+        Progressive {
+            block_size,
+            samples,
+        }
+    }
+}
+
+impl Camera {
+    /// Renders `world` in passes getting finer, handing the image to `on_pass` after each
+    /// of them. The render gives up between two rows once `stop` is set.
+    pub fn render_progressive(
+        &self,
+        world: &World,
+        settings: &Progressive,
+        stop: &AtomicBool,
+        mut on_pass: impl FnMut(&Canvas, Pass),
+    ) {
+        let width = self.hsize;
+        let mut image = Canvas::new_canvas(self.hsize, self.vsize);
+
+        let first = settings.block_size.max(1).next_power_of_two();
+        let mut block = first;
+        while block >= 1 {
+            image
+                .pixels()
+                .par_chunks_mut(width * block)
+                .enumerate()
+                .for_each(|(i, band)| {
+                    if stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let y = i * block;
+                    let height = band.len() / width;
+                    for x in (0..width).step_by(block) {
+                        // the corners of the blocks twice as large are already traced
+                        if block < first
+                            && x.is_multiple_of(2 * block)
+                            && y.is_multiple_of(2 * block)
+                        {
+                            continue;
+                        }
+                        let color = self.color_at(world, x, y);
+                        for row in 0..height {
+                            let start = row * width + x;
+                            band[start..start + block.min(width - x)].fill(color);
+                        }
+                    }
+                });
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            on_pass(&image, Pass::Blocks(block));
+            block /= 2;
+        }
+
+        let mut sum = image.pixels.clone();
+        for samples in 2..=settings.samples {
+            sum.par_chunks_mut(width)
+                .zip(image.pixels().par_chunks_mut(width))
+                .enumerate()
+                .for_each(|(y, (sum_row, row))| {
+                    if stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    for x in 0..width {
+                        sum_row[x] +=
+                            self.color_at_offset(world, x, y, rand::random(), rand::random());
+                        row[x] = sum_row[x] * (1.0 / samples as f64);
+                    }
+                });
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            on_pass(&image, Pass::Samples(samples));
+        }
+    }
+}
+
+/// Progressive render running on its own thread, stopped when dropped
+pub struct ProgressiveRenderer {
+    stop: Arc<AtomicBool>,
+    passes: Receiver<(Canvas, Pass)>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ProgressiveRenderer {
+    pub fn start(camera: Camera, world: Arc<World>, settings: Progressive) -> ProgressiveRenderer {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, passes) = mpsc::channel();
+
+        let worker_stop = Arc::clone(&stop);
+        let worker = thread::spawn(move || {
+            camera.render_progressive(&world, &settings, &worker_stop, |image, pass| {
+                // nobody is waiting for the image anymore once the receiver is dropped
+                let _ = sender.send((image.clone(), pass));
+            });
+        });
+
+        ProgressiveRenderer {
+            stop,
+            passes,
+            worker: Some(worker),
+        }
+    }
+
+    /// Latest pass finished since the last call, if any
+    pub fn latest_pass(&self) -> Option<(Canvas, Pass)> {
+        self.passes.try_iter().last()
+    }
+
+    /// Whether every pass has been rendered, or the render stopped
+    pub fn is_finished(&self) -> bool {
+        self.worker
+            .as_ref()
+            .is_none_or(|worker| worker.is_finished())
+    }
+
+    /// Stops the render and waits for the worker thread
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            worker.join().unwrap();
+        }
+    }
+}
+
+impl Drop for ProgressiveRenderer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod progressive_tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::{transformation::view_transform, tuple::Tuple};
+
+    fn default_camera(hsize: usize, vsize: usize) -> Camera {
+        let from = Tuple::new_point(0.0, 0.0, -5.0);
+        let to = Tuple::new_point(0.0, 0.0, 0.0);
+        let up = Tuple::new_vector(0.0, 1.0, 0.0);
+        Camera::new(hsize, vsize, PI / 2.0).with_transformation(view_transform(&from, &to, &up))
+    }
+
+    #[test]
+    // Passes get finer and the first full resolution pass is the plain render
+    fn render_progressive_passes() {
+        let w = World::default_world();
+        let c = default_camera(11, 9);
+        let settings = Progressive::new_progressive(3, 3);
+
+        let mut passes = vec![];
+        c.render_progressive(&w, &settings, &AtomicBool::new(false), |image, pass| {
+            if pass == Pass::Blocks(4) {
+                assert_eq!(image.pixel_at(5, 5), image.pixel_at(4, 4));
+                assert_eq!(image.pixel_at(10, 8), image.pixel_at(8, 8));
+            }
+            if pass == Pass::Blocks(1) {
+                assert_eq!(image.pixels, c.render(&w).pixels);
+            }
+            passes.push(pass);
+        });
+        assert_eq!(
+            passes,
+            vec![
+                Pass::Blocks(4),
+                Pass::Blocks(2),
+                Pass::Blocks(1),
+                Pass::Samples(2),
+                Pass::Samples(3)
+            ]
+        );
+    }
+
+    #[test]
+    // A stopped render hands out no more passes
+    fn render_progressive_stop() {
+        let w = World::default_world();
+        let c = default_camera(11, 9);
+        let stop = AtomicBool::new(false);
+
+        let mut passes = 0;
+        c.render_progressive(&w, &Progressive::new_progressive(4, 4), &stop, |_, _| {
+            passes += 1;
+            stop.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(passes, 1);
+    }
+
+    #[test]
+    // The renderer thread sends the passes until the last one
+    fn progressive_renderer() {
+        let c = default_camera(11, 9);
+        let mut renderer = ProgressiveRenderer::start(
+            c,
+            Arc::new(World::default_world()),
+            Progressive::new_progressive(2, 2),
+        );
+        while !renderer.is_finished() {
+            thread::yield_now();
+        }
+        assert_eq!(renderer.latest_pass().unwrap().1, Pass::Samples(2));
+        assert!(renderer.latest_pass().is_none());
+        renderer.stop();
+    }
+}
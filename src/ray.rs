@@ -4,6 +4,9 @@ use crate::{matrix::*, shape::{object::Object, shape::Shape}, tuple::*};
 pub struct Ray {
     pub origin: Tuple,
     pub direction: Tuple,
+    /// Instant the ray is traced at, in seconds, moving objects being intersected where
+    /// they stand at that time
+    pub time: f64,
 }
 
 impl Ray {
@@ -14,39 +17,46 @@ impl Ray {
         if direction.w != W::Vector {
             panic!("Ray::new direction must be a vector")
         }
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
     }
 
     pub fn position(&self, time: f64) -> Tuple {
         self.origin.clone() + self.direction.clone() * time
     }
 
-    pub fn transform(&self, matrix: &Matrix) -> Ray {
+    pub fn transform(&self, matrix: &Matrix4) -> Ray {
         Ray {
             origin: matrix * self.origin.clone(),
             direction: matrix * self.direction.clone(),
+            time: self.time,
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Intersection {
+#[derive(Debug, Clone, Copy)]
+pub struct Intersection<'a> {
     pub t: f64,
-    pub object: Object,
+    pub object: &'a Object,
 }
 
-impl PartialEq for Intersection {
+impl PartialEq for Intersection<'_> {
     fn eq(&self, other: &Self) -> bool {
-        self.t == other.t && self.object == other.object.clone()
+        self.t == other.t && self.object.get_id() == other.object.get_id()
     }
 }
 
-impl Intersection {
-    pub fn new(t: f64, object: &Object) -> Intersection {
-        Intersection {
-            t,
-            object: object.clone(),
-        }
+impl<'a> Intersection<'a> {
+    pub fn new(t: f64, object: &'a Object) -> Intersection<'a> {
+        Intersection { t, object }
     }
 }
 
@@ -54,15 +64,12 @@ pub fn reflect(inv: &Tuple, normal: &Tuple) -> Tuple {
     inv.clone() - normal.clone() * 2.0 * Tuple::dot_product(inv, normal)
 }
 
-pub fn hit_intersections(intersections: Vec<Intersection>) -> Option<Intersection> {
-    let mut tmp_instersections = intersections.clone();
-    tmp_instersections.retain(|value| value.t > 0.0);
-    tmp_instersections.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
-    if tmp_instersections.is_empty() {
-        Option::None
-    } else {
-        Option::Some(tmp_instersections[0].clone())
-    }
+pub fn hit_intersections<'a>(intersections: &[Intersection<'a>]) -> Option<Intersection<'a>> {
+    intersections
+        .iter()
+        .filter(|value| value.t > 0.0)
+        .min_by(|a, b| a.t.partial_cmp(&b.t).unwrap())
+        .copied()
 }
 
 #[cfg(test)]
@@ -120,14 +127,14 @@ mod transformation_tests {
         let origin = Tuple::new_point(0.0, 0.0, -5.0);
         let direction = Tuple::new_vector(0.0, 0.0, 1.0);
         let ray = Ray::new(origin, direction);
-        let mut s = Object::new_sphere();
-        let xs = s.intersect(ray);
+        let s = Object::new_sphere();
+        let xs = s.intersect(&ray);
 
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, 4.0);
-        assert!((xs[0].object == s));
+        assert!((*xs[0].object == s));
         assert_eq!(xs[1].t, 6.0);
-        assert!((xs[1].object == s));
+        assert!((*xs[1].object == s));
     }
 
     #[test]
@@ -136,8 +143,8 @@ mod transformation_tests {
         let origin = Tuple::new_point(0.0, 1.0, -5.0);
         let direction = Tuple::new_vector(0.0, 0.0, 1.0);
         let ray = Ray::new(origin, direction);
-        let mut s = Object::new_sphere();
-        let xs = s.intersect(ray);
+        let s = Object::new_sphere();
+        let xs = s.intersect(&ray);
 
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, 5.0);
@@ -150,8 +157,8 @@ mod transformation_tests {
         let origin = Tuple::new_point(0.0, 2.0, -5.0);
         let direction = Tuple::new_vector(0.0, 0.0, 1.0);
         let ray = Ray::new(origin, direction);
-        let mut s = Object::new_sphere();
-        let xs = s.intersect(ray);
+        let s = Object::new_sphere();
+        let xs = s.intersect(&ray);
 
         assert_eq!(xs.len(), 0);
     }
@@ -162,8 +169,8 @@ mod transformation_tests {
         let origin = Tuple::new_point(0.0, 0.0, 0.0);
         let direction = Tuple::new_vector(0.0, 0.0, 1.0);
         let ray = Ray::new(origin, direction);
-        let mut s = Object::new_sphere();
-        let xs = s.intersect(ray);
+        let s = Object::new_sphere();
+        let xs = s.intersect(&ray);
 
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, -1.0);
@@ -177,7 +184,7 @@ mod transformation_tests {
         let i = Intersection::new(3.5, &s);
 
         assert_eq!(i.t, 3.5);
-        assert!((i.object == s));
+        assert!((*i.object == s));
     }
 
     #[test]
@@ -186,8 +193,8 @@ mod transformation_tests {
         let s = Object::new_sphere();
         let i1 = Intersection::new(1.0, &s);
         let i2 = Intersection::new(2.0, &s);
-        let intersections = vec![i1.clone(), i2];
-        let i = hit_intersections(intersections).unwrap();
+        let intersections = vec![i1, i2];
+        let i = hit_intersections(&intersections).unwrap();
         assert_eq!(i, i1);
     }
 
@@ -197,8 +204,8 @@ mod transformation_tests {
         let s = Object::new_sphere();
         let i1 = Intersection::new(-1.0, &s);
         let i2 = Intersection::new(2.0, &s);
-        let intersections = vec![i1.clone(), i2.clone()];
-        let i = hit_intersections(intersections).unwrap();
+        let intersections = vec![i1, i2];
+        let i = hit_intersections(&intersections).unwrap();
         assert_eq!(i, i2);
     }
 
@@ -208,8 +215,8 @@ mod transformation_tests {
         let s = Object::new_sphere();
         let i1 = Intersection::new(-1.0, &s);
         let i2 = Intersection::new(-2.0, &s);
-        let intersections = vec![i1.clone(), i2.clone()];
-        let i = hit_intersections(intersections);
+        let intersections = vec![i1, i2];
+        let i = hit_intersections(&intersections);
         assert_eq!(i, Option::None);
     }
 
@@ -222,8 +229,8 @@ mod transformation_tests {
         let i3 = Intersection::new(-2.0, &s);
         let i4 = Intersection::new(2.0, &s);
 
-        let intersections = vec![i1.clone(), i2.clone(), i3.clone(), i4.clone()];
-        let i = hit_intersections(intersections).unwrap();
+        let intersections = vec![i1, i2, i3, i4];
+        let i = hit_intersections(&intersections).unwrap();
         assert_eq!(i, i4);
     }
 
@@ -1,5 +1,7 @@
 use crate::{
     color::*,
+    matrix::Matrix4,
+    media::Medium,
     pattern::{self, Pattern},
     ray::reflect,
     shape::{object::Object, shape::Shape},
@@ -8,10 +10,41 @@ use crate::{
 
 pub const MAX_RECURTION: usize = 5;
 
+/// Falloff of the light intensity with the distance to the light
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attenuation {
+    /// Same intensity at any distance
+    None,
+    /// Intensity divided by `constant + linear * d + quadratic * d²`
+    Polynomial {
+        constant: f64,
+        linear: f64,
+        quadratic: f64,
+    },
+    /// Physical falloff, intensity divided by `d²`
+    InverseSquare,
+}
+
+impl Attenuation {
+    /// Factor applied to the intensity at `distance` from the light
+    pub fn factor(&self, distance: f64) -> f64 {
+        match self {
+            Attenuation::None => 1.0,
+            Attenuation::Polynomial {
+                constant,
+                linear,
+                quadratic,
+            } => 1.0 / (constant + linear * distance + quadratic * distance.powi(2)),
+            Attenuation::InverseSquare => 1.0 / distance.powi(2),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct PointLight {
     pub intensity: Color,
     pub position: Tuple,
+    pub attenuation: Attenuation,
 }
 
 impl PointLight {
@@ -19,6 +52,43 @@ impl PointLight {
         PointLight {
             intensity,
             position,
+            attenuation: Attenuation::None,
+        }
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    /// Intensity of the light reaching `point`
+    pub fn intensity_at(&self, point: &Tuple) -> Color {
+        let distance = (self.position.clone() - point.clone()).magnitude();
+        self.intensity * self.attenuation.factor(distance)
+    }
+}
+
+/// Emissive objects of the world sampled as point lights for direct illumination,
+/// several objects (e.g. the triangles of a mesh) can make a single light
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmissiveLight {
+    /// Indexes of the emitting objects in `World::objects`
+    pub objects: Vec<usize>,
+    /// Area of each object where it stands, the objects being picked proportionally to it
+    pub areas: Vec<f64>,
+    pub samples: usize,
+}
+
+impl EmissiveLight {
+    pub fn new_emissive_light(
+        objects: Vec<usize>,
+        areas: Vec<f64>,
+        samples: usize,
+    ) -> EmissiveLight {
+        EmissiveLight {
+            objects,
+            areas,
+            samples,
         }
     }
 }
@@ -34,6 +104,14 @@ pub struct Material {
     pub reflective: f64,
     pub transparency: f64,
     pub refractive_index: f64,
+    /// Per channel absorption coefficient of the medium inside the object (Beer–Lambert)
+    pub absorption: Color,
+    /// Scale applied to `absorption`, the higher the darker thick objects get
+    pub density: f64,
+    /// Light emitted by the surface, added whatever the lighting
+    pub emission: Color,
+    /// Participating medium filling the object
+    pub medium: Option<Medium>,
 }
 
 impl Material {
@@ -48,6 +126,10 @@ impl Material {
             reflective: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            absorption: BLACK,
+            density: 1.0,
+            emission: BLACK,
+            medium: None,
         }
     }
 
@@ -72,6 +154,7 @@ impl Material {
             reflective,
             transparency,
             refractive_index,
+            ..Material::default_material()
         }
     }
 
@@ -105,6 +188,11 @@ impl Material {
         self
     }
 
+    pub fn set_emission(&mut self, emission: Color) -> &Material {
+        self.emission = emission;
+        self
+    }
+
     pub fn with_ambient(mut self, ambient: f64) -> Self {
         self.ambient = ambient;
         self
@@ -149,6 +237,39 @@ impl Material {
         self.pattern = pattern_option;
         self
     }
+
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    pub fn with_density(mut self, density: f64) -> Self {
+        self.density = density;
+        self
+    }
+
+    pub fn with_medium(mut self, medium: Option<Medium>) -> Self {
+        self.medium = medium;
+        self
+    }
+
+    pub fn with_emission(mut self, emission: Color) -> Self {
+        self.emission = emission;
+        self
+    }
+
+    /// Color of the material at a world space `point` of `object`, taking the pattern into account
+    pub fn color_at_object(&self, object: &Object, point: Tuple) -> Color {
+        self.color_at_local(object.get_inverse() * point)
+    }
+
+    /// Color of the material at a `point` in object space
+    pub fn color_at_local(&self, point: Tuple) -> Color {
+        match &self.pattern {
+            Some(pattern) => pattern.color_at_local(point),
+            None => self.color,
+        }
+    }
 }
 
 pub fn lighting(
@@ -157,46 +278,63 @@ pub fn lighting(
     point: &Tuple,
     eyev: &Tuple,
     normalv: &Tuple,
-    in_shadow: bool,
-    object: Object,
+    light_visibility: Color,
+    object: &Object,
 ) -> Color {
-    let color = match &material.pattern {
-        Some(pattern) => pattern.color_at_object(&object, point.clone()),
-        None => material.color,
-    };
-    let effective_color = color * light.intensity;
+    let (diffuse, specular) = lighting_parts(
+        material,
+        light,
+        point,
+        eyev,
+        normalv,
+        light_visibility,
+        object.get_inverse(),
+    );
+    diffuse + specular
+}
+
+/// Diffuse, ambient included, and specular parts of `lighting` once shadowed,
+/// `object_inverse` being the inverse of the transform of the object where it stands
+pub fn lighting_parts(
+    material: &Material,
+    light: &PointLight,
+    point: &Tuple,
+    eyev: &Tuple,
+    normalv: &Tuple,
+    light_visibility: Color,
+    object_inverse: &Matrix4,
+) -> (Color, Color) {
+    let color = material.color_at_local(object_inverse * point.clone());
+    let intensity = light.intensity_at(point);
+    let effective_color = color * intensity;
     let ambiant = effective_color * material.ambient;
 
-    if in_shadow {
-        ambiant
-    } else {
-        let ligthv = (light.position.clone() - point.clone()).normalize();
-        let light_dot_normal = Tuple::dot_product(&ligthv, normalv);
-        let diffuse;
-        let specular;
-        if light_dot_normal < 0.0 {
-            diffuse = BLACK;
-            specular = BLACK;
-        } else {
-            diffuse = effective_color * material.diffuse * light_dot_normal;
-            let reflectv = reflect(&(ligthv * -1.0), normalv);
-            let reflect_dot_eye = Tuple::dot_product(&reflectv, eyev);
-            if reflect_dot_eye <= 0.0 {
-                specular = BLACK;
-            } else {
-                let factor = f64::powf(reflect_dot_eye, material.shininess);
-                specular = light.intensity * material.specular * factor;
-            }
-        }
-        ambiant + diffuse + specular
+    let ligthv = (light.position.clone() - point.clone()).normalize();
+    let light_dot_normal = Tuple::dot_product(&ligthv, normalv);
+    if light_visibility == BLACK || light_dot_normal < 0.0 {
+        return (ambiant, BLACK);
     }
+
+    let diffuse = effective_color * material.diffuse * light_dot_normal;
+    let reflectv = reflect(&(ligthv * -1.0), normalv);
+    let reflect_dot_eye = Tuple::dot_product(&reflectv, eyev);
+    let specular = if reflect_dot_eye <= 0.0 {
+        BLACK
+    } else {
+        let factor = f64::powf(reflect_dot_eye, material.shininess);
+        intensity * material.specular * factor
+    };
+    (
+        ambiant + diffuse * light_visibility,
+        specular * light_visibility,
+    )
 }
 
 #[cfg(test)]
 mod matrix_tests {
     use crate::{
         ray::{Intersection, Ray},
-        transformation,
+        transformation, utils,
         world::{World, prepare_computations_helper},
     };
 
@@ -255,7 +393,7 @@ mod matrix_tests {
             Color::new_color(1.0, 1.0, 1.0),
             Tuple::new_point(0.0, 0.0, -10.0),
         );
-        let in_shadow = false;
+        let light_visibility = WHITE;
 
         let result = lighting(
             &m,
@@ -263,12 +401,59 @@ mod matrix_tests {
             &position,
             &eyev,
             &normalv,
-            in_shadow,
-            Object::new_sphere(),
+            light_visibility,
+            &Object::new_sphere(),
         );
         assert_eq!(result, Color::new_color(1.9, 1.9, 1.9));
     }
 
+    #[test]
+    ///Attenuation factors at a distance of 2
+    fn attenuation_factor() {
+        assert!(utils::compare_float(Attenuation::None.factor(2.0), 1.0));
+        assert!(utils::compare_float(
+            Attenuation::InverseSquare.factor(2.0),
+            0.25
+        ));
+        let polynomial = Attenuation::Polynomial {
+            constant: 1.0,
+            linear: 0.5,
+            quadratic: 0.25,
+        };
+        assert!(utils::compare_float(polynomial.factor(2.0), 1.0 / 3.0));
+    }
+
+    #[test]
+    ///Lighting with the eye between the light and the surface, the light falling off
+    fn lighting_attenuation() {
+        let m = Material::default_material();
+        let position = Tuple::new_point(0.0, 0.0, 0.0);
+
+        let eyev = Tuple::new_vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight::new_point_light(
+            Color::new_color(1.0, 1.0, 1.0),
+            Tuple::new_point(0.0, 0.0, -10.0),
+        )
+        .with_attenuation(Attenuation::InverseSquare);
+
+        assert_eq!(
+            light.intensity_at(&position),
+            Color::new_color(0.01, 0.01, 0.01)
+        );
+
+        let result = lighting(
+            &m,
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            WHITE,
+            &Object::new_sphere(),
+        );
+        assert_eq!(result, Color::new_color(0.019, 0.019, 0.019));
+    }
+
     #[test]
     ///Lighting with the eye between light and surface, eye offset 45°
     fn lighting_2() {
@@ -281,7 +466,7 @@ mod matrix_tests {
             Color::new_color(1.0, 1.0, 1.0),
             Tuple::new_point(0.0, 0.0, -10.0),
         );
-        let in_shadow = false;
+        let light_visibility = WHITE;
 
         let result = lighting(
             &m,
@@ -289,8 +474,8 @@ mod matrix_tests {
             &position,
             &eyev,
             &normalv,
-            in_shadow,
-            Object::new_sphere(),
+            light_visibility,
+            &Object::new_sphere(),
         );
         assert_eq!(result, Color::new_color(1.0, 1.0, 1.0));
     }
@@ -307,7 +492,7 @@ mod matrix_tests {
             Color::new_color(1.0, 1.0, 1.0),
             Tuple::new_point(0.0, 10.0, -10.0),
         );
-        let in_shadow = false;
+        let light_visibility = WHITE;
 
         let result = lighting(
             &m,
@@ -315,8 +500,8 @@ mod matrix_tests {
             &position,
             &eyev,
             &normalv,
-            in_shadow,
-            Object::new_sphere(),
+            light_visibility,
+            &Object::new_sphere(),
         );
         assert_eq!(
             result,
@@ -336,7 +521,7 @@ mod matrix_tests {
             Color::new_color(1.0, 1.0, 1.0),
             Tuple::new_point(0.0, 0.0, 10.0),
         );
-        let in_shadow = false;
+        let light_visibility = WHITE;
 
         let result = lighting(
             &m,
@@ -344,8 +529,8 @@ mod matrix_tests {
             &position,
             &eyev,
             &normalv,
-            in_shadow,
-            Object::new_sphere(),
+            light_visibility,
+            &Object::new_sphere(),
         );
         assert_eq!(result, Color::new_color(0.1, 0.1, 0.1));
     }
@@ -362,7 +547,7 @@ mod matrix_tests {
             Color::new_color(1.0, 1.0, 1.0),
             Tuple::new_point(0.0, 0.0, -10.0),
         );
-        let in_shadow = true;
+        let light_visibility = BLACK;
 
         let result = lighting(
             &m,
@@ -370,12 +555,38 @@ mod matrix_tests {
             &position,
             &eyev,
             &normalv,
-            in_shadow,
-            Object::new_sphere(),
+            light_visibility,
+            &Object::new_sphere(),
         );
         assert_eq!(result, Color::new_color(0.1, 0.1, 0.1));
     }
 
+    #[test]
+    /// Lighting with the light partially filtered by a transparent object
+    fn lighting_6() {
+        let m = Material::default_material();
+        let position = Tuple::new_point(0.0, 0.0, 0.0);
+
+        let eyev = Tuple::new_vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight::new_point_light(
+            Color::new_color(1.0, 1.0, 1.0),
+            Tuple::new_point(0.0, 0.0, -10.0),
+        );
+        let light_visibility = Color::new_color(0.5, 0.0, 1.0);
+
+        let result = lighting(
+            &m,
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            light_visibility,
+            &Object::new_sphere(),
+        );
+        assert_eq!(result, Color::new_color(1.0, 0.1, 1.9));
+    }
+
     #[test]
     //Scenario : Reflectivity for the default material
     fn reflection_test() {
@@ -468,6 +679,35 @@ mod matrix_tests {
         );
     }
 
+    #[test]
+    // The reflected color is added once, whatever the number of lights
+    fn reflection_shade_hit_lights_test() {
+        let mut w = World::default_world();
+        let mut shape = Object::new_plane();
+        shape.set_material(shape.get_material().set_reflective(0.5));
+        shape.set_transform(&transformation::create_translation(0.0, -1.0, 0.0));
+        w.add_object(shape.clone());
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -3.0),
+            Tuple::new_vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let i = Intersection::new(2.0_f64.sqrt(), w.objects.last().unwrap());
+        let comps = prepare_computations_helper(&i, &r);
+        let surface =
+            w.shade_hit(&comps, MAX_RECURTION) - w.reflected_color(comps.clone(), MAX_RECURTION);
+
+        let mut w2 = w.clone();
+        w2.light_sources.push(w.light_sources[0].clone());
+        let i = Intersection::new(2.0_f64.sqrt(), w2.objects.last().unwrap());
+        let comps = prepare_computations_helper(&i, &r);
+        let reflected = w2.reflected_color(comps.clone(), MAX_RECURTION);
+        assert_eq!(
+            w2.shade_hit(&comps, MAX_RECURTION),
+            surface * 2.0 + reflected
+        );
+    }
+
     #[test]
     //Scenario: color_at() with mutually reflective surfaces
     fn reflection_infinite_recursion_test() {
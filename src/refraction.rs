@@ -1,5 +1,6 @@
 use crate::{
     color::{self, Color},
+    inspection::{RayKind, Recorder},
     ray::Ray,
     tuple::Tuple,
     world::{Computation, World},
@@ -13,28 +14,64 @@ pub const DIAMOND_REFRACTION: f64 = 2.417;
 
 impl World {
     pub fn refracted_color(&self, comps: Computation, remaining_iterations: usize) -> Color {
+        self.refracted_color_with(comps, remaining_iterations, &mut ())
+    }
+
+    pub fn refracted_color_with(
+        &self,
+        comps: Computation,
+        remaining_iterations: usize,
+        recorder: &mut impl Recorder,
+    ) -> Color {
         if comps.object.get_material().transparency == 0.0 || remaining_iterations == 0 {
             return color::BLACK;
         }
 
-        let n_ratio = comps.n1 / comps.n2;
-        let cos_i = Tuple::dot_product(&comps.eyev, &comps.normalv);
+        let direction = match comps.refracted_direction() {
+            Some(direction) => direction,
+            None => return color::BLACK,
+        };
+        let refract_ray = Ray::new(comps.under_point.clone(), direction).with_time(comps.time);
+        recorder.bounce(RayKind::Refraction);
+
+        self.color_at_with(&refract_ray, remaining_iterations - 1, recorder)
+            * comps.object.get_material().transparency
+            * self.absorption(&comps, &refract_ray)
+    }
+
+    /// Beer–Lambert attenuation of a refracted ray entering `comps.object`,
+    /// the traveled distance is the one to the next hit along the ray. An open surface,
+    /// the ray never getting out, absorbs nothing.
+    fn absorption(&self, comps: &Computation, refract_ray: &Ray) -> Color {
+        let material = comps.object.get_material();
+        if comps.inside || material.absorption == color::BLACK {
+            return color::WHITE;
+        }
+
+        let distance = match self.hit(refract_ray) {
+            Some(hit) => hit.t,
+            None => return color::WHITE,
+        };
+
+        (material.absorption * (-material.density * distance)).exp()
+    }
+}
+
+impl Computation<'_> {
+    /// Direction of the refracted ray, `None` on total internal reflection
+    pub fn refracted_direction(&self) -> Option<Tuple> {
+        let n_ratio = self.n1 / self.n2;
+        let cos_i = Tuple::dot_product(&self.eyev, &self.normalv);
         let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
 
         if sin2_t > 1.0 {
-            return color::BLACK;
+            return None;
         }
 
         let cos_t = (1.0 - sin2_t).sqrt();
-        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
-        let refract_ray = Ray::new(comps.under_point, direction);
-
-        self.color_at(&refract_ray, remaining_iterations - 1)
-            * comps.object.get_material().transparency
+        Some(self.normalv.clone() * (n_ratio * cos_i - cos_t) - self.eyev.clone() * n_ratio)
     }
-}
 
-impl Computation {
     pub fn schlick(&self) -> f64 {
         let mut cos = Tuple::dot_product(&self.eyev, &self.normalv);
 
@@ -107,7 +144,7 @@ mod refraction_tests {
         ];
 
         for (i, elem) in xs.iter().enumerate() {
-            let comp = prepare_computations_v2(elem, &r, xs.clone());
+            let comp = prepare_computations_v2(elem, &r, &xs);
 
             assert_eq!(comp.n1, valeurs[i][0]);
             assert_eq!(comp.n2, valeurs[i][1]);
@@ -126,7 +163,7 @@ mod refraction_tests {
         shape.set_transform(&transformation::create_translation(0.0, 0.0, 1.0));
 
         let i = Intersection::new(5.0, &shape);
-        let comps = prepare_computations_v2(&i, &r, vec![i.clone()]);
+        let comps = prepare_computations_v2(&i, &r, &[i]);
         assert!(comps.under_point.z > f64::EPSILON / 2.0);
         assert!(comps.point.z < comps.under_point.z);
     }
@@ -146,7 +183,7 @@ mod refraction_tests {
             Intersection::new(4.00, &shape),
             Intersection::new(6.00, &shape),
         ];
-        let comps = prepare_computations_v2(&xs[0], &r, xs.clone());
+        let comps = prepare_computations_v2(&xs[0], &r, &xs);
 
         let c = w.refracted_color(comps, 5);
         assert_eq!(c, color::BLACK);
@@ -156,9 +193,9 @@ mod refraction_tests {
     // Scenario: The refracted color at the maximum recursive depth
     fn refrected_color_2_test() {
         let mut w = World::default_world();
-        let shape = &mut w.objects[0];
-        shape.set_transparency(1.0);
-        shape.set_refractive_index(1.5);
+        w.objects[0].set_transparency(1.0);
+        w.objects[0].set_refractive_index(1.5);
+        let shape = &w.objects[0];
 
         let r = Ray::new(
             Tuple::new_point(0.0, 0.0, -5.0),
@@ -168,7 +205,7 @@ mod refraction_tests {
             Intersection::new(4.00, &shape),
             Intersection::new(6.00, &shape),
         ];
-        let comps = prepare_computations_v2(&xs[0], &r, xs.clone());
+        let comps = prepare_computations_v2(&xs[0], &r, &xs);
 
         let c = w.refracted_color(comps, 0);
         assert_eq!(c, color::BLACK);
@@ -178,9 +215,9 @@ mod refraction_tests {
     // Scenario: The refracted color under total internal reflection
     fn refrected_color_3_test() {
         let mut w = World::default_world();
-        let shape = &mut w.objects[0];
-        shape.set_transparency(1.0);
-        shape.set_refractive_index(1.5);
+        w.objects[0].set_transparency(1.0);
+        w.objects[0].set_refractive_index(1.5);
+        let shape = &w.objects[0];
 
         let r = Ray::new(
             Tuple::new_point(0.0, 0.0, 2.0_f64.sqrt() / 2.0),
@@ -190,7 +227,7 @@ mod refraction_tests {
             Intersection::new(-2.0_f64.sqrt() / 2.0, &shape),
             Intersection::new(2.0_f64.sqrt() / 2.0, &shape),
         ];
-        let comps = prepare_computations_v2(&xs[1], &r, xs.clone());
+        let comps = prepare_computations_v2(&xs[1], &r, &xs);
 
         let c = w.refracted_color(comps, 5);
         assert_eq!(c, color::BLACK);
@@ -219,7 +256,7 @@ mod refraction_tests {
             Intersection::new(0.9899, &w.objects[0]),
         ];
 
-        let comps = prepare_computations_v2(&xs[2], &r, xs.clone());
+        let comps = prepare_computations_v2(&xs[2], &r, &xs);
 
         let c = w.refracted_color(comps, 5);
         assert_eq!(
@@ -254,10 +291,78 @@ mod refraction_tests {
 
         let xs = vec![Intersection::new(2.0_f64.sqrt(), &floor)];
 
-        let comps = prepare_computations_v2(&xs[0], &r, xs.clone());
+        let comps = prepare_computations_v2(&xs[0], &r, &xs);
 
         let c = w.shade_hit(&comps, 5);
-        assert_eq!(c, Color::new_color(0.93642, 0.68642, 0.68642));
+        // the ball is lit through the half transparent floor
+        assert_eq!(c, Color::new_color(1.12547, 0.68643, 0.68643));
+    }
+
+    #[test]
+    // Scenario: The refracted color is attenuated by the distance traveled inside the object
+    fn refracted_color_absorption_test() {
+        let mut w = World::default_world();
+        w.objects[1].set_transparency(1.0);
+        w.objects[1].set_refractive_index(1.5);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = vec![
+            Intersection::new(4.0, &w.objects[0]),
+            Intersection::new(4.5, &w.objects[1]),
+            Intersection::new(5.5, &w.objects[1]),
+            Intersection::new(6.0, &w.objects[0]),
+        ];
+        let comps = prepare_computations_v2(&xs[1], &r, &xs);
+        let clear = w.refracted_color(comps, 5);
+
+        let absorption = Color::new_color(0.5, 1.0, 2.0);
+        let material = w.objects[1]
+            .get_material()
+            .with_absorption(absorption)
+            .with_density(2.0);
+        w.objects[1].set_material(&material);
+        let xs = vec![
+            Intersection::new(4.0, &w.objects[0]),
+            Intersection::new(4.5, &w.objects[1]),
+            Intersection::new(5.5, &w.objects[1]),
+            Intersection::new(6.0, &w.objects[0]),
+        ];
+        let comps = prepare_computations_v2(&xs[1], &r, &xs);
+        let tinted = w.refracted_color(comps, 5);
+
+        // the ray goes straight through the center of the inner sphere of radius 0.5
+        assert_eq!(tinted, clear * (absorption * (-2.0 * 1.0)).exp());
+    }
+
+    #[test]
+    // A partly absorbing open surface, left by the refracted ray without an exit, absorbs
+    // nothing
+    fn refracted_color_absorption_open_test() {
+        let mut w = World::default_world();
+        let mut floor = Object::new_plane()
+            .with_transformation(transformation::create_translation(0.0, -1.0, 0.0));
+        floor.set_transparency(0.5);
+        floor.set_refractive_index(1.5);
+        w.add_object(floor);
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, -3.0),
+            Tuple::new_vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let clear = w.color_at(&r, 5);
+
+        let material = w.objects[2]
+            .get_material()
+            .with_absorption(Color::new_color(0.3, 0.0, 0.0));
+        w.objects[2].set_material(&material);
+        let tinted = w.color_at(&r, 5);
+
+        let (red, green, blue) = tinted.channels();
+        assert!(!red.is_nan() && !green.is_nan() && !blue.is_nan());
+        assert_eq!(tinted, clear);
     }
 
     #[test]
@@ -274,7 +379,7 @@ mod refraction_tests {
             Intersection::new(2.0_f64.sqrt() / 2.0, &shape),
         ];
 
-        let comps = prepare_computations_v2(&xs[1], &r, xs.clone());
+        let comps = prepare_computations_v2(&xs[1], &r, &xs);
         let refelctance = comps.schlick();
         assert_eq!(refelctance, 1.0);
     }
@@ -294,7 +399,7 @@ mod refraction_tests {
             Intersection::new(1.0, &shape),
         ];
 
-        let comps = prepare_computations_v2(&xs[1], &r, xs.clone());
+        let comps = prepare_computations_v2(&xs[1], &r, &xs);
         let refelctance = comps.schlick();
         assert!(utils::compare_float(refelctance, 0.04));
     }
@@ -311,7 +416,7 @@ mod refraction_tests {
 
         let xs = vec![Intersection::new(1.8589, &shape)];
 
-        let comps = prepare_computations_v2(&xs[0], &r, xs.clone());
+        let comps = prepare_computations_v2(&xs[0], &r, &xs);
         let refelctance = comps.schlick();
         assert!(utils::compare_float(refelctance, 0.48873));
     }
@@ -341,9 +446,10 @@ mod refraction_tests {
 
         let xs = vec![Intersection::new(2.0_f64.sqrt(), &floor)];
 
-        let comps = prepare_computations_v2(&xs[0], &r, xs.clone());
+        let comps = prepare_computations_v2(&xs[0], &r, &xs);
 
         let c = w.shade_hit(&comps, 5);
-        assert_eq!(c, Color::new_color(0.93391,  0.69643,  0.69243));
+        // the ball is lit through the half transparent floor
+        assert_eq!(c, Color::new_color(1.11500, 0.69643, 0.69243));
     }
 }
@@ -0,0 +1,51 @@
+use std::f64::consts::PI;
+
+use crate::tuple::Tuple;
+
+/// Direction in the hemisphere around `normal` for the `(u, v)` coordinates in `[0, 1)²`,
+/// distributed proportionally to the cosine with the normal
+pub fn cosine_hemisphere(normal: &Tuple, u: f64, v: f64) -> Tuple {
+    let r = u.sqrt();
+    let phi = 2.0 * PI * v;
+    let x = r * phi.cos();
+    let y = r * phi.sin();
+    let z = (1.0 - u).max(0.0).sqrt();
+
+    let helper = if normal.x.abs() > 0.9 {
+        Tuple::new_vector(0.0, 1.0, 0.0)
+    } else {
+        Tuple::new_vector(1.0, 0.0, 0.0)
+    };
+    let tangent = Tuple::cross_product(&helper, normal).normalize();
+    let bitangent = Tuple::cross_product(normal, &tangent);
+
+    (tangent * x + bitangent * y + normal.clone() * z).normalize()
+}
+
+#[cfg(test)]
+mod sampling_tests {
+    use super::*;
+    use crate::utils;
+
+    #[test]
+    // The center of the sample square points along the normal
+    fn cosine_hemisphere_center() {
+        let normal = Tuple::new_vector(0.0, 1.0, 0.0);
+        assert_eq!(cosine_hemisphere(&normal, 0.0, 0.0), normal);
+    }
+
+    #[test]
+    // Samples stay in the hemisphere of the normal and are normalized
+    fn cosine_hemisphere_side() {
+        let normal = Tuple::new_vector(1.0, 1.0, 0.0).normalize();
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.99, 0.75), (0.7, 0.0)] {
+            let direction = cosine_hemisphere(&normal, u, v);
+            assert!(direction.is_unit());
+            assert!(Tuple::dot_product(&direction, &normal) >= 0.0);
+            assert!(utils::compare_float(
+                Tuple::dot_product(&direction, &normal),
+                (1.0 - u).sqrt()
+            ));
+        }
+    }
+}
@@ -1,4 +1,6 @@
 pub mod sphere;
 pub mod shape;
 pub mod plane;
-pub mod object;
\ No newline at end of file
+pub mod object;
+pub mod quad;
+pub mod triangle;
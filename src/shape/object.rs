@@ -2,7 +2,8 @@ use uuid::Uuid;
 
 use crate::{
     color::Color,
-    matrix::Matrix,
+    matrix::Matrix4,
+    motion::Motion,
     pattern::Pattern,
     ray::{Intersection, Ray},
     reflection::Material,
@@ -10,22 +11,92 @@ use crate::{
     tuple::Tuple,
 };
 
+/// Transform of an object at a given time, with the inverses derived from it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub transform: Matrix4,
+    pub inverse: Matrix4,
+    pub inverse_transpose: Matrix4,
+}
+
+impl Placement {
+    pub fn new_placement(transform: Matrix4) -> Placement {
+        let inverse = transform.inverse().unwrap();
+        Placement {
+            transform,
+            inverse,
+            inverse_transpose: inverse.transpose(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Object {
-    pub transform: Matrix,
+    pub transform: Matrix4,
+    /// Inverse of `transform`, kept up to date by `set_transform`
+    pub(crate) inverse: Matrix4,
+    /// Transposed inverse of `transform`, turning local normals into world normals
+    pub(crate) inverse_transpose: Matrix4,
     pub material: Material,
     pub shape: Shape,
     pub id: Uuid,
     pub shadow: bool,
+    /// Keyframed transform used instead of `transform` by the rays traced at other times
+    pub motion: Option<Motion>,
 }
 
 impl Object {
-    pub fn intersect(&mut self, ray: Ray) -> Vec<Intersection> {
-        self.shape.local_intersect(self.clone(), ray)
+    pub fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut intersections = vec![];
+        self.intersect_with(ray, |t| intersections.push(Intersection::new(t, self)));
+        intersections
+    }
+
+    /// Calls `hit` with the distance of each intersection of `ray` with the object,
+    /// without building a list
+    pub fn intersect_with(&self, ray: &Ray, mut hit: impl FnMut(f64)) {
+        self.shape.local_intersect(self, ray, &mut hit);
+    }
+
+    /// Whether `ray` hits the object closer than `distance`
+    pub fn hits_before(&self, ray: &Ray, distance: f64) -> bool {
+        self.shape.local_hit_before(self, ray, distance)
+    }
+
+    /// Whether the object stops the light, casting a shadow without letting any through
+    pub fn blocks_light(&self) -> bool {
+        self.shadow && self.material.transparency == 0.0
+    }
+
+    /// `ray` in the object space, where the object stands at the time of the ray
+    pub fn local_ray(&self, ray: &Ray) -> Ray {
+        ray.transform(&self.inverse_at(ray.time))
+    }
+
+    /// Transform of the object at rest, the one of the rays without a time
+    pub fn placement(&self) -> Placement {
+        Placement {
+            transform: self.transform,
+            inverse: self.inverse,
+            inverse_transpose: self.inverse_transpose,
+        }
     }
 
     pub fn normal_at(&self, point: Tuple) -> Tuple {
-        self.shape.local_normal_at(self.clone(), point)
+        self.shape.local_normal_at(&self.placement(), point)
+    }
+
+    /// Surface coordinates of a point of the object, in world space
+    pub fn uv_at(&self, point: &Tuple) -> (f64, f64) {
+        self.shape.local_uv(&(self.inverse * point.clone()))
+    }
+
+    pub fn sample_surface(&self, u: f64, v: f64, toward: &Tuple) -> (Tuple, Tuple, f64) {
+        self.shape.sample_surface(&self.placement(), u, v, toward)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        self.shape.surface_area(&self.placement())
     }
 
     pub fn set_transparency(&mut self, transparency: f64) {
@@ -52,12 +123,26 @@ impl Object {
         self.set_material(self.get_material().set_color(color));
     }
 
-    pub fn set_transform(&mut self, new_stransform: &Matrix) {
-        self.transform = new_stransform.clone();
+    pub fn set_emission(&mut self, emission: Color) {
+        self.set_material(self.get_material().set_emission(emission));
+    }
+
+    pub fn set_transform(&mut self, new_stransform: &Matrix4) {
+        self.transform = *new_stransform;
+        self.inverse = self.transform.inverse().unwrap();
+        self.inverse_transpose = self.inverse.transpose();
+    }
+
+    pub fn get_transform(&self) -> Matrix4 {
+        self.transform
+    }
+
+    pub fn get_inverse(&self) -> &Matrix4 {
+        &self.inverse
     }
 
-    pub fn get_transform(&self) -> Matrix {
-        self.transform.clone()
+    pub fn get_inverse_transpose(&self) -> &Matrix4 {
+        &self.inverse_transpose
     }
 
     pub fn set_material(&mut self, new_material: &Material) {
@@ -72,7 +157,7 @@ impl Object {
         self.id
     }
 
-    pub fn has_shadow (&self)-> bool{
+    pub fn has_shadow(&self) -> bool {
         self.shadow
     }
 
@@ -81,8 +166,8 @@ impl Object {
         self
     }
 
-    pub fn with_transformation(mut self, transformation: Matrix) -> Self {
-        self.transform = transformation;
+    pub fn with_transformation(mut self, transformation: Matrix4) -> Self {
+        self.set_transform(&transformation);
         self
     }
 
@@ -0,0 +1,88 @@
+use uuid::Uuid;
+
+use crate::matrix::Matrix4;
+use crate::reflection::Material;
+use crate::shape::object::Object;
+
+use super::shape::Shape;
+
+impl Object {
+    /// A square of side 2 centered on the origin in the xz plane
+    pub fn new_quad() -> Object {
+        Object {
+            id: Uuid::new_v4(),
+            transform: Matrix4::new_identity_matrix(),
+            inverse: Matrix4::new_identity_matrix(),
+            inverse_transpose: Matrix4::new_identity_matrix(),
+            material: Material::default_material(),
+            shape: Shape::Quad(),
+            shadow: true,
+            motion: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod quad_tests {
+    use super::*;
+    use crate::ray::Ray;
+    use crate::transformation;
+    use crate::tuple::Tuple;
+
+    #[test]
+    // Scenario: The normal of a quad is constant everywhere
+    fn test_normal_quad() {
+        let q = Object::new_quad();
+        let n = q.normal_at(Tuple::new_point(0.0, 0.0, 0.0));
+        let n2 = q.normal_at(Tuple::new_point(0.5, 0.0, -0.5));
+        assert_eq!(n, Tuple::new_vector(0.0, 1.0, 0.0));
+        assert_eq!(n2, Tuple::new_vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    // Scenario: A ray hits a quad inside its bounds
+    fn test_intersection_inside() {
+        let q = Object::new_quad();
+        let r = Ray::new(
+            Tuple::new_point(0.5, 1.0, -0.5),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+        let xs = q.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 1.0);
+    }
+
+    #[test]
+    // Scenario: A ray misses a quad outside its bounds
+    fn test_intersection_outside() {
+        let q = Object::new_quad();
+        let r = Ray::new(
+            Tuple::new_point(1.5, 1.0, 0.0),
+            Tuple::new_vector(0.0, -1.0, 0.0),
+        );
+        assert!(q.intersect(&r).is_empty());
+    }
+
+    #[test]
+    // Scenario: The area of a scaled quad
+    fn test_quad_area() {
+        let mut q = Object::new_quad();
+        q.set_transform(&transformation::create_scaling(2.0, 1.0, 3.0));
+        assert!(crate::utils::compare_float(q.surface_area(), 24.0));
+    }
+
+    #[test]
+    // Scenario: Samples of a transformed quad lie on its surface
+    fn test_quad_sample() {
+        let mut q = Object::new_quad();
+        q.set_transform(
+            &transformation::create_rotation_x(std::f64::consts::PI / 2.0)
+                .translation(0.0, 0.0, 5.0),
+        );
+        let toward = Tuple::new_point(0.0, 0.0, 0.0);
+        let (point, normal, area) = q.sample_surface(0.25, 1.0, &toward);
+        assert_eq!(point, Tuple::new_point(-0.5, -1.0, 5.0));
+        assert_eq!(normal, Tuple::new_vector(0.0, 0.0, -1.0));
+        assert!(crate::utils::compare_float(area, 4.0));
+    }
+}
@@ -1,98 +1,185 @@
-use std::fmt::{Debug, Formatter};
+use std::f64::consts::PI;
 
 use crate::color::Color;
-use crate::matrix::memoized_inverse;
 use crate::pattern::Pattern;
-use crate::ray::{Intersection, Ray};
-use crate::shape::object::Object;
+use crate::ray::Ray;
+use crate::shape::object::{Object, Placement};
 use crate::tuple;
-use crate::{matrix::Matrix, reflection};
+use crate::{matrix::Matrix4, reflection};
 
 use crate::{reflection::Material, tuple::*};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Shape {
-    ShapeTest { saved_ray: Ray },
+    ShapeTest(),
     Sphere { origin: Tuple, radius: f64 },
     Plane(),
+    Quad(),
+    Triangle {
+        p1: Tuple,
+        p2: Tuple,
+        p3: Tuple,
+        e1: Tuple,
+        e2: Tuple,
+        normal: Tuple,
+    },
 }
 
 impl Object {
     fn new_test_shape() -> Object {
         Object {
-            transform: Matrix::new_identity_matrix(4),
+            transform: Matrix4::new_identity_matrix(),
+            inverse: Matrix4::new_identity_matrix(),
+            inverse_transpose: Matrix4::new_identity_matrix(),
             material: reflection::Material::default_material(),
             id: Uuid::new_v4(),
-            shape: Shape::ShapeTest {
-                saved_ray: Ray {
-                    direction: Tuple {
-                        x: 0.0,
-                        y: 0.0,
-                        z: 0.0,
-                        w: W::Point,
-                    },
-                    origin: Tuple {
-                        x: 0.0,
-                        y: 0.0,
-                        z: 0.0,
-                        w: W::Point,
-                    },
-                },
-            },
+            shape: Shape::ShapeTest(),
             shadow: true,
+            motion: None,
         }
     }
 }
 
 impl Shape {
-    pub fn local_intersect(&mut self, object: Object, local_ray: Ray) -> Vec<Intersection> {
+    /// Calls `hit` with the distance along `ray` of each intersection with `object`
+    pub fn local_intersect(&self, object: &Object, ray: &Ray, hit: &mut impl FnMut(f64)) {
+        let transformed_ray = object.local_ray(ray);
         match self {
-            Shape::ShapeTest { saved_ray } => {
-                *saved_ray =
-                    local_ray.transform(&memoized_inverse(object.transform.clone()).unwrap());
-                vec![]
-            }
+            Shape::ShapeTest() => {}
             Shape::Sphere { origin, radius: _ } => {
-                let transformed_ray =
-                    local_ray.transform(&memoized_inverse(object.transform.clone()).unwrap());
                 let sphere_to_ray = transformed_ray.origin - origin.to_owned();
                 let a = Tuple::dot_product(&transformed_ray.direction, &transformed_ray.direction);
                 let b = 2.0 * Tuple::dot_product(&transformed_ray.direction, &sphere_to_ray);
                 let c = Tuple::dot_product(&sphere_to_ray, &sphere_to_ray) - 1.0;
                 let discriminant = b.powi(2) - 4.0 * a * c;
 
+                if discriminant >= 0.0 {
+                    hit((-b - discriminant.sqrt()) / (2.0 * a));
+                    hit((-b + discriminant.sqrt()) / (2.0 * a));
+                }
+            }
+            Shape::Plane() => {
+                if transformed_ray.direction.y.abs() >= 0.00001 {
+                    hit(-transformed_ray.origin.y / transformed_ray.direction.y);
+                }
+            }
+            Shape::Quad() => {
+                if transformed_ray.direction.y.abs() < 0.00001 {
+                    return;
+                }
+                let t = -transformed_ray.origin.y / transformed_ray.direction.y;
+                let point = transformed_ray.position(t);
+                if point.x.abs() <= 1.0 && point.z.abs() <= 1.0 {
+                    hit(t);
+                }
+            }
+            Shape::Triangle {
+                p1,
+                p2: _,
+                p3: _,
+                e1,
+                e2,
+                normal: _,
+            } => {
+                let dir_cross_e2 = Tuple::cross_product(&transformed_ray.direction, e2);
+                let det = Tuple::dot_product(e1, &dir_cross_e2);
+                if det.abs() < 0.00001 {
+                    return;
+                }
+
+                let f = 1.0 / det;
+                let p1_to_origin = transformed_ray.origin.clone() - p1.clone();
+                let u = f * Tuple::dot_product(&p1_to_origin, &dir_cross_e2);
+                if !(0.0..=1.0).contains(&u) {
+                    return;
+                }
+
+                let origin_cross_e1 = Tuple::cross_product(&p1_to_origin, e1);
+                let v = f * Tuple::dot_product(&transformed_ray.direction, &origin_cross_e1);
+                if v < 0.0 || u + v > 1.0 {
+                    return;
+                }
+
+                hit(f * Tuple::dot_product(e2, &origin_cross_e1));
+            }
+        }
+    }
+
+    /// Whether `ray` hits `object` at a distance in `(0, distance)`, giving up as soon as
+    /// the hit is ruled out
+    pub fn local_hit_before(&self, object: &Object, ray: &Ray, distance: f64) -> bool {
+        let before = |t: f64| t > 0.0 && t < distance;
+        let transformed_ray = object.local_ray(ray);
+        match self {
+            Shape::ShapeTest() => false,
+            Shape::Sphere { origin, radius: _ } => {
+                let sphere_to_ray = transformed_ray.origin - origin.to_owned();
+                let half_b = Tuple::dot_product(&transformed_ray.direction, &sphere_to_ray);
+                let c = Tuple::dot_product(&sphere_to_ray, &sphere_to_ray) - 1.0;
+                // outside of the sphere and going away from it
+                if c > 0.0 && half_b > 0.0 {
+                    return false;
+                }
+
+                let a = Tuple::dot_product(&transformed_ray.direction, &transformed_ray.direction);
+                let discriminant = half_b.powi(2) - a * c;
                 if discriminant < 0.0 {
-                    vec![]
-                } else {
-                    let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
-                    let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
-                    vec![
-                        Intersection::new(t1, &object),
-                        Intersection::new(t2, &object),
-                    ]
+                    return false;
                 }
+                let root = discriminant.sqrt();
+                before((-half_b - root) / a) || before((-half_b + root) / a)
             }
             Shape::Plane() => {
-                let transformed_ray =
-                    local_ray.transform(&memoized_inverse(object.transform.clone()).unwrap());
+                transformed_ray.direction.y.abs() >= 0.00001
+                    && before(-transformed_ray.origin.y / transformed_ray.direction.y)
+            }
+            Shape::Quad() => {
                 if transformed_ray.direction.y.abs() < 0.00001 {
-                    vec![]
-                } else {
-                    let t = -transformed_ray.origin.y / transformed_ray.direction.y;
-                    vec![Intersection::new(t, &object)]
+                    return false;
+                }
+                let t = -transformed_ray.origin.y / transformed_ray.direction.y;
+                if !before(t) {
+                    return false;
+                }
+                let point = transformed_ray.position(t);
+                point.x.abs() <= 1.0 && point.z.abs() <= 1.0
+            }
+            Shape::Triangle {
+                p1,
+                p2: _,
+                p3: _,
+                e1,
+                e2,
+                normal: _,
+            } => {
+                let dir_cross_e2 = Tuple::cross_product(&transformed_ray.direction, e2);
+                let det = Tuple::dot_product(e1, &dir_cross_e2);
+                if det.abs() < 0.00001 {
+                    return false;
+                }
+
+                // the distance first, the shadow rays mostly missing by being too short
+                let f = 1.0 / det;
+                let p1_to_origin = transformed_ray.origin.clone() - p1.clone();
+                let origin_cross_e1 = Tuple::cross_product(&p1_to_origin, e1);
+                if !before(f * Tuple::dot_product(e2, &origin_cross_e1)) {
+                    return false;
                 }
+
+                let u = f * Tuple::dot_product(&p1_to_origin, &dir_cross_e2);
+                let v = f * Tuple::dot_product(&transformed_ray.direction, &origin_cross_e1);
+                (0.0..=1.0).contains(&u) && v >= 0.0 && u + v <= 1.0
             }
         }
     }
 
-    pub fn local_normal_at(&self, object: Object, point: Tuple) -> Tuple {
+    pub fn local_normal_at(&self, placement: &Placement, point: Tuple) -> Tuple {
         match self {
-            Shape::ShapeTest { saved_ray: _ } => {
-                let local_point = memoized_inverse(object.transform.clone()).unwrap() * point;
+            Shape::ShapeTest() => {
+                let local_point = placement.inverse * point;
                 let local_normal = local_point;
-                let mut world_normal =
-                    memoized_inverse(object.transform.clone()).unwrap() * local_normal;
+                let mut world_normal = placement.inverse * local_normal;
                 world_normal.w = W::from_int(0);
                 world_normal.normalize()
             }
@@ -100,35 +187,193 @@ impl Shape {
                 origin: _,
                 radius: _,
             } => {
-                let object_point =
-                    memoized_inverse(object.transform.clone()).unwrap() * point.clone();
+                let object_point = placement.inverse * point.clone();
                 let object_normal = object_point - Tuple::new_point(0.0, 0.0, 0.0);
-                let mut world_normal = memoized_inverse(object.transform.clone())
-                    .unwrap()
-                    .transpose()
-                    * object_normal;
+                let mut world_normal = placement.inverse_transpose * object_normal;
                 world_normal.w = tuple::W::from_int(0);
                 world_normal.normalize()
             }
-            Shape::Plane() => {
-                memoized_inverse(object.transform.clone()).unwrap()
-                    * Tuple::new_vector(0.0, 1.0, 0.0)
+            Shape::Plane() => placement.inverse * Tuple::new_vector(0.0, 1.0, 0.0),
+            Shape::Quad() => world_normal(placement, Tuple::new_vector(0.0, 1.0, 0.0)),
+            Shape::Triangle {
+                p1: _,
+                p2: _,
+                p3: _,
+                e1: _,
+                e2: _,
+                normal,
+            } => world_normal(placement, normal.clone()),
+        }
+    }
+
+    /// Whether `sample_surface` and `surface_area` handle the shape, so that it can be an
+    /// emissive light
+    pub fn can_be_sampled(&self) -> bool {
+        matches!(
+            self,
+            Shape::Sphere { .. } | Shape::Quad() | Shape::Triangle { .. }
+        )
+    }
+
+    /// Point and normal in world space for the `(u, v)` coordinates in `[0, 1)²`, used to
+    /// sample emissive objects, along with the area the point is drawn from.
+    /// Seen from the outside only the half of a sphere facing `toward` is sampled,
+    /// and the normal always faces `toward`.
+    pub fn sample_surface(
+        &self,
+        placement: &Placement,
+        u: f64,
+        v: f64,
+        toward: &Tuple,
+    ) -> (Tuple, Tuple, f64) {
+        let local_toward = placement.inverse * toward.clone();
+        let mut sampled_area = self.surface_area(placement);
+
+        let (local_point, local_normal) = match self {
+            Shape::Sphere { origin, radius: _ } => {
+                let z = 1.0 - 2.0 * u;
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let phi = 2.0 * PI * v;
+                let mut direction = Tuple::new_vector(r * phi.cos(), r * phi.sin(), z);
+                let to_toward = local_toward.clone() - origin.clone();
+                if to_toward.magnitude() > 1.0 {
+                    sampled_area /= 2.0;
+                    if Tuple::dot_product(&direction, &to_toward) < 0.0 {
+                        direction = direction * -1.0;
+                    }
+                }
+                (origin.clone() + direction.clone(), direction)
+            }
+            Shape::Quad() => (
+                Tuple::new_point(2.0 * u - 1.0, 0.0, 2.0 * v - 1.0),
+                Tuple::new_vector(0.0, 1.0, 0.0),
+            ),
+            Shape::Triangle {
+                p1,
+                p2: _,
+                p3: _,
+                e1,
+                e2,
+                normal,
+            } => {
+                let (u, v) = if u + v > 1.0 {
+                    (1.0 - u, 1.0 - v)
+                } else {
+                    (u, v)
+                };
+                (p1.clone() + e1.clone() * u + e2.clone() * v, normal.clone())
+            }
+            Shape::Plane() | Shape::ShapeTest() => {
+                panic!(
+                    "Only spheres, quads and triangles can be sampled, got: {:?}",
+                    self
+                )
+            }
+        };
+
+        let point = placement.transform * local_point;
+        let mut normal = world_normal(placement, local_normal);
+        if Tuple::dot_product(&normal, &(toward.clone() - point.clone())) < 0.0 {
+            normal = normal * -1.0;
+        }
+        (point, normal, sampled_area)
+    }
+
+    /// Surface coordinates in `[0, 1]²` of a point of the shape in object space: longitude and
+    /// latitude on spheres, unit tiles on planes, the coordinates `sample_surface` takes on
+    /// quads and triangles
+    pub fn local_uv(&self, point: &Tuple) -> (f64, f64) {
+        match self {
+            Shape::Sphere { origin, radius } => {
+                let d = point.clone() - origin.clone();
+                let theta = d.x.atan2(d.z);
+                let phi = (d.y / radius).clamp(-1.0, 1.0).acos();
+                (1.0 - (theta / (2.0 * PI) + 0.5), 1.0 - phi / PI)
+            }
+            Shape::Plane() => (point.x.rem_euclid(1.0), point.z.rem_euclid(1.0)),
+            Shape::Quad() => ((point.x + 1.0) / 2.0, (point.z + 1.0) / 2.0),
+            Shape::Triangle {
+                p1,
+                p2: _,
+                p3: _,
+                e1,
+                e2,
+                normal: _,
+            } => {
+                // barycentric coordinates along the two edges
+                let d = point.clone() - p1.clone();
+                let d11 = Tuple::dot_product(e1, e1);
+                let d12 = Tuple::dot_product(e1, e2);
+                let d22 = Tuple::dot_product(e2, e2);
+                let d1 = Tuple::dot_product(&d, e1);
+                let d2 = Tuple::dot_product(&d, e2);
+                let denominator = d11 * d22 - d12 * d12;
+                (
+                    (d22 * d1 - d12 * d2) / denominator,
+                    (d11 * d2 - d12 * d1) / denominator,
+                )
+            }
+            Shape::ShapeTest() => (0.0, 0.0),
+        }
+    }
+
+    /// Area of the transformed surface, spheres are approximated as ellipsoids
+    pub fn surface_area(&self, placement: &Placement) -> f64 {
+        let transform = &placement.transform;
+        match self {
+            Shape::Sphere {
+                origin: _,
+                radius: _,
+            } => {
+                let a = (transform * Tuple::new_vector(1.0, 0.0, 0.0)).magnitude();
+                let b = (transform * Tuple::new_vector(0.0, 1.0, 0.0)).magnitude();
+                let c = (transform * Tuple::new_vector(0.0, 0.0, 1.0)).magnitude();
+                4.0 * PI * (a * b + b * c + c * a) / 3.0
+            }
+            Shape::Quad() => {
+                let edge_u = transform * Tuple::new_vector(2.0, 0.0, 0.0);
+                let edge_v = transform * Tuple::new_vector(0.0, 0.0, 2.0);
+                Tuple::cross_product(&edge_u, &edge_v).magnitude()
+            }
+            Shape::Triangle {
+                p1: _,
+                p2: _,
+                p3: _,
+                e1,
+                e2,
+                normal: _,
+            } => {
+                let edge_1 = transform * e1.clone();
+                let edge_2 = transform * e2.clone();
+                Tuple::cross_product(&edge_1, &edge_2).magnitude() / 2.0
+            }
+            Shape::Plane() | Shape::ShapeTest() => {
+                panic!(
+                    "Only spheres, quads and triangles have an area, got: {:?}",
+                    self
+                )
             }
         }
     }
 }
 
+fn world_normal(placement: &Placement, local_normal: Tuple) -> Tuple {
+    let mut world_normal = placement.inverse_transpose * local_normal;
+    world_normal.w = W::Vector;
+    world_normal.normalize()
+}
+
 #[cfg(test)]
 mod shape_tests {
     use super::*;
-    use crate::transformation;
+    use crate::{transformation, utils};
     use std::f64::consts::PI;
 
     #[test]
     // Scenario: The default transformation
     fn default_test_shape() {
         let s = Object::new_test_shape();
-        assert_eq!(s.transform, Matrix::new_identity_matrix(4));
+        assert_eq!(s.transform, Matrix4::new_identity_matrix());
     }
 
     #[test]
@@ -172,13 +417,10 @@ mod shape_tests {
         );
         let mut s = Object::new_test_shape();
         s.set_transform(&transformation::create_scaling(2.0, 2.0, 2.0));
-        let _: Vec<Intersection> = s.intersect(r);
-        if let Shape::ShapeTest { saved_ray } = s.shape {
-            assert_eq!(saved_ray.origin, Tuple::new_point(0.0, 0.0, -2.5));
-            assert_eq!(saved_ray.direction, Tuple::new_vector(0.0, 0.0, 0.5));
-        } else {
-            panic!("Shuold not happend")
-        }
+        assert!(s.intersect(&r).is_empty());
+        let local_ray = s.local_ray(&r);
+        assert_eq!(local_ray.origin, Tuple::new_point(0.0, 0.0, -2.5));
+        assert_eq!(local_ray.direction, Tuple::new_vector(0.0, 0.0, 0.5));
     }
 
     #[test]
@@ -190,14 +432,10 @@ mod shape_tests {
         );
         let mut s = Object::new_test_shape();
         s.set_transform(&transformation::create_translation(5.0, 0.0, 0.0));
-        let _: Vec<Intersection> = s.intersect(r);
-
-        if let Shape::ShapeTest { saved_ray } = s.shape {
-            assert_eq!(saved_ray.origin, Tuple::new_point(-5.0, 0.0, -5.0));
-            assert_eq!(saved_ray.direction, Tuple::new_vector(0.0, 0.0, 1.0));
-        } else {
-            panic!("Should not happend")
-        }
+        assert!(s.intersect(&r).is_empty());
+        let local_ray = s.local_ray(&r);
+        assert_eq!(local_ray.origin, Tuple::new_point(-5.0, 0.0, -5.0));
+        assert_eq!(local_ray.direction, Tuple::new_vector(0.0, 0.0, 1.0));
     }
 
     #[test]
@@ -222,4 +460,77 @@ mod shape_tests {
         );
         assert_eq!(n, Tuple::new_vector(0.795805, 0.537492, -0.27891));
     }
+
+    #[test]
+    // Surface coordinates of points on each kind of shape
+    fn test_local_uv() {
+        let sphere = Object::new_sphere().shape;
+        let (u, v) = sphere.local_uv(&Tuple::new_point(0.0, 0.0, -1.0));
+        assert!(utils::compare_float(u, 0.0) && utils::compare_float(v, 0.5));
+        let (u, v) = sphere.local_uv(&Tuple::new_point(1.0, 0.0, 0.0));
+        assert!(utils::compare_float(u, 0.25) && utils::compare_float(v, 0.5));
+        let (_, v) = sphere.local_uv(&Tuple::new_point(0.0, 1.0, 0.0));
+        assert!(utils::compare_float(v, 1.0));
+
+        let plane = Object::new_plane().shape;
+        assert_eq!(
+            plane.local_uv(&Tuple::new_point(-0.25, 0.0, 1.5)),
+            (0.75, 0.5)
+        );
+
+        let quad = Object::new_quad().shape;
+        assert_eq!(
+            quad.local_uv(&Tuple::new_point(0.5, 0.0, -1.0)),
+            (0.75, 0.0)
+        );
+
+        let triangle = Object::new_triangle(
+            Tuple::new_point(0.0, 1.0, 0.0),
+            Tuple::new_point(-1.0, 0.0, 0.0),
+            Tuple::new_point(1.0, 0.0, 0.0),
+        )
+        .shape;
+        let (u, v) = triangle.local_uv(&Tuple::new_point(0.0, 0.0, 0.0));
+        assert!(utils::compare_float(u, 0.5) && utils::compare_float(v, 0.5));
+    }
+
+    #[test]
+    // The early outs of hits_before agree with the intersections
+    fn hits_before_intersections() {
+        let objects = [
+            Object::new_sphere().with_transformation(transformation::create_scaling(2.0, 1.0, 1.0)),
+            Object::new_plane(),
+            Object::new_quad().with_transformation(transformation::create_rotation_x(0.3)),
+            Object::new_triangle(
+                Tuple::new_point(0.0, 1.0, 0.0),
+                Tuple::new_point(-1.0, 0.0, 0.0),
+                Tuple::new_point(1.0, 0.0, 0.0),
+            ),
+        ];
+        let origins = [
+            Tuple::new_point(0.1, 0.2, -5.0),
+            Tuple::new_point(0.3, 3.0, 0.4),
+            Tuple::new_point(-0.2, -2.0, 0.1),
+            Tuple::new_point(0.25, 0.1, 0.05),
+        ];
+        let directions = [
+            Tuple::new_vector(0.0, 0.0, 1.0),
+            Tuple::new_vector(0.1, -1.0, 0.2),
+            Tuple::new_vector(-0.3, 0.9, 0.1).normalize(),
+            Tuple::new_vector(0.0, 0.0, -1.0),
+        ];
+
+        for object in &objects {
+            for origin in &origins {
+                for direction in &directions {
+                    let ray = Ray::new(origin.clone(), direction.clone());
+                    let xs = object.intersect(&ray);
+                    for distance in [0.5, 2.5, 4.5, 10.0] {
+                        let expected = xs.iter().any(|i| i.t > 0.0 && i.t < distance);
+                        assert_eq!(object.hits_before(&ray, distance), expected);
+                    }
+                }
+            }
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use uuid::Uuid;
 
-use crate::matrix::{Matrix, memoized_inverse};
+use crate::matrix::Matrix4;
 use crate::ray::{Intersection, Ray};
 use crate::reflection::Material;
 use crate::shape::object::Object;
@@ -16,9 +16,12 @@ impl Object {
                 radius: 1.0,
             },
             id: Uuid::new_v4(),
-            transform: Matrix::new_identity_matrix(4),
+            transform: Matrix4::new_identity_matrix(),
+            inverse: Matrix4::new_identity_matrix(),
+            inverse_transpose: Matrix4::new_identity_matrix(),
             material: Material::default_material(),
             shadow: true,
+            motion: None,
         }
     }
 
@@ -33,9 +36,12 @@ impl Object {
                 radius: 1.0,
             },
             id: Uuid::new_v4(),
-            transform: Matrix::new_identity_matrix(4),
+            transform: Matrix4::new_identity_matrix(),
+            inverse: Matrix4::new_identity_matrix(),
+            inverse_transpose: Matrix4::new_identity_matrix(),
             material,
             shadow: true,
+            motion: None,
         }
     }
 }
@@ -51,7 +57,7 @@ mod sphere_tests {
     fn glassy_sphere_test() {
         let s = Object::new_glass_sphere();
 
-        assert_eq!(s.transform, Matrix::new_identity_matrix(4));
+        assert_eq!(s.transform, Matrix4::new_identity_matrix());
         assert_eq!(s.material.transparency, 1.0);
         assert_eq!(s.material.refractive_index, 1.5);
     }
@@ -62,7 +68,7 @@ mod sphere_tests {
         let s = Object::new_sphere();
         let n = s
             .shape
-            .local_normal_at(s.clone(), Tuple::new_point(1.0, 0.0, 0.0));
+            .local_normal_at(&s.placement(), Tuple::new_point(1.0, 0.0, 0.0));
 
         assert_eq!(n, Tuple::new_vector(1.0, 0.0, 0.0));
     }
@@ -73,7 +79,7 @@ mod sphere_tests {
         let s = Object::new_sphere();
         let n = s
             .shape
-            .local_normal_at(s.clone(), Tuple::new_point(0.0, 1.0, 0.0));
+            .local_normal_at(&s.placement(), Tuple::new_point(0.0, 1.0, 0.0));
 
         assert_eq!(n, Tuple::new_vector(0.0, 1.0, 0.0));
     }
@@ -84,7 +90,7 @@ mod sphere_tests {
         let s = Object::new_sphere();
         let n = s
             .shape
-            .local_normal_at(s.clone(), Tuple::new_point(0.0, 0.0, 1.0));
+            .local_normal_at(&s.placement(), Tuple::new_point(0.0, 0.0, 1.0));
 
         assert_eq!(n, Tuple::new_vector(0.0, 0.0, 1.0));
     }
@@ -95,7 +101,7 @@ mod sphere_tests {
         let mut s = Object::new_sphere();
         s.set_transform(&transformation::create_translation(0.0, 1.0, 0.0));
         let n = s.shape.local_normal_at(
-            s.clone(),
+            &s.placement(),
             Tuple::new_point(0.0, 1.7071067811865475, -0.7071067811865476),
         );
 
@@ -113,7 +119,7 @@ mod sphere_tests {
             * transformation::create_rotation_z(PI / 5.0);
         s.set_transform(&transformation);
         let n = s.shape.local_normal_at(
-            s.clone(),
+            &s.placement(),
             Tuple::new_point(0.0, 2.0_f64.sqrt() / 2.0, -2.0_f64.sqrt() / 2.0),
         );
 
@@ -128,7 +134,7 @@ mod sphere_tests {
     fn sphere_normal_nonaxial() {
         let s = Object::new_sphere();
         let n = s.shape.local_normal_at(
-            s.clone(),
+            &s.placement(),
             Tuple::new_point(
                 3.0_f64.sqrt() / 3.0,
                 3.0_f64.sqrt() / 3.0,
@@ -151,7 +157,7 @@ mod sphere_tests {
     fn sphere_normalized() {
         let s = Object::new_sphere();
         let n = s.shape.local_normal_at(
-            s.clone(),
+            &s.placement(),
             Tuple::new_point(
                 3.0_f64.sqrt() / 3.0,
                 3.0_f64.sqrt() / 3.0,
@@ -166,7 +172,7 @@ mod sphere_tests {
     ///A sphere's default transformation
     fn sphere_default() {
         let s = Object::new_sphere();
-        assert_eq!(s.transform, Matrix::new_identity_matrix(4));
+        assert_eq!(s.transform, Matrix4::new_identity_matrix());
     }
 
     #[test]
@@ -177,6 +183,11 @@ mod sphere_tests {
         s.set_transform(&t);
 
         assert_eq!(s.transform, t.clone());
+        assert_eq!(
+            *s.get_inverse(),
+            transformation::create_translation(-2.0, -3.0, -4.0)
+        );
+        assert_eq!(*s.get_inverse_transpose(), s.get_inverse().transpose());
     }
 
     #[test]
@@ -187,7 +198,7 @@ mod sphere_tests {
         let ray = Ray::new(origin, direction);
         let mut s = Object::new_sphere();
         s.set_transform(&transformation::create_scaling(2.0, 2.0, 2.0));
-        let xs = s.intersect(ray);
+        let xs = s.intersect(&ray);
 
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, 3.0);
@@ -202,8 +213,28 @@ mod sphere_tests {
         let ray = Ray::new(origin, direction);
         let mut s = Object::new_sphere();
         s.set_transform(&transformation::create_translation(5.0, 0.0, 0.0));
-        let xs = s.intersect(ray);
+        let xs = s.intersect(&ray);
 
         assert_eq!(xs.len(), 0);
     }
+
+    #[test]
+    ///A sphere is hit before a distance when one of its intersections ahead is closer
+    fn sphere_hits_before() {
+        let ray = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let s = Object::new_sphere();
+
+        assert!(s.hits_before(&ray, 4.5));
+        assert!(!s.hits_before(&ray, 4.0));
+
+        let inside = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(s.hits_before(&inside, 2.0));
+        assert!(!s.hits_before(&inside, 0.5));
+    }
 }
@@ -0,0 +1,151 @@
+use uuid::Uuid;
+
+use crate::matrix::Matrix4;
+use crate::reflection::Material;
+use crate::shape::object::Object;
+use crate::tuple::Tuple;
+
+use super::shape::Shape;
+
+impl Object {
+    pub fn new_triangle(p1: Tuple, p2: Tuple, p3: Tuple) -> Object {
+        let e1 = p2.clone() - p1.clone();
+        let e2 = p3.clone() - p1.clone();
+        let normal = Tuple::cross_product(&e2, &e1).normalize();
+
+        Object {
+            id: Uuid::new_v4(),
+            transform: Matrix4::new_identity_matrix(),
+            inverse: Matrix4::new_identity_matrix(),
+            inverse_transpose: Matrix4::new_identity_matrix(),
+            material: Material::default_material(),
+            shape: Shape::Triangle {
+                p1,
+                p2,
+                p3,
+                e1,
+                e2,
+                normal,
+            },
+            shadow: true,
+            motion: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod triangle_tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    fn default_triangle() -> Object {
+        Object::new_triangle(
+            Tuple::new_point(0.0, 1.0, 0.0),
+            Tuple::new_point(-1.0, 0.0, 0.0),
+            Tuple::new_point(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    // Scenario: Constructing a triangle
+    fn test_triangle_creation() {
+        let t = default_triangle();
+        if let Shape::Triangle {
+            p1: _,
+            p2: _,
+            p3: _,
+            e1,
+            e2,
+            normal,
+        } = t.shape
+        {
+            assert_eq!(e1, Tuple::new_vector(-1.0, -1.0, 0.0));
+            assert_eq!(e2, Tuple::new_vector(1.0, -1.0, 0.0));
+            assert_eq!(normal, Tuple::new_vector(0.0, 0.0, -1.0));
+        } else {
+            panic!("Should not happend")
+        }
+    }
+
+    #[test]
+    // Scenario: Finding the normal on a triangle
+    fn test_triangle_normal() {
+        let t = default_triangle();
+        let n1 = t.normal_at(Tuple::new_point(0.0, 0.5, 0.0));
+        let n2 = t.normal_at(Tuple::new_point(-0.5, 0.75, 0.0));
+        let n3 = t.normal_at(Tuple::new_point(0.5, 0.25, 0.0));
+        assert_eq!(n1, Tuple::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(n2, Tuple::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(n3, Tuple::new_vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    // Scenario: Intersecting a ray parallel to the triangle
+    fn test_triangle_parallel() {
+        let t = default_triangle();
+        let r = Ray::new(
+            Tuple::new_point(0.0, -1.0, -2.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        assert!(t.intersect(&r).is_empty());
+    }
+
+    #[test]
+    // Scenario: A ray misses the p1-p3 edge
+    fn test_triangle_miss_p1_p3() {
+        let t = default_triangle();
+        let r = Ray::new(
+            Tuple::new_point(1.0, 1.0, -2.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(t.intersect(&r).is_empty());
+    }
+
+    #[test]
+    // Scenario: A ray misses the p1-p2 edge
+    fn test_triangle_miss_p1_p2() {
+        let t = default_triangle();
+        let r = Ray::new(
+            Tuple::new_point(-1.0, 1.0, -2.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(t.intersect(&r).is_empty());
+    }
+
+    #[test]
+    // Scenario: A ray misses the p2-p3 edge
+    fn test_triangle_miss_p2_p3() {
+        let t = default_triangle();
+        let r = Ray::new(
+            Tuple::new_point(0.0, -1.0, -2.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        assert!(t.intersect(&r).is_empty());
+    }
+
+    #[test]
+    // Scenario: A ray strikes a triangle
+    fn test_triangle_hit() {
+        let t = default_triangle();
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.5, -2.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = t.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.0);
+    }
+
+    #[test]
+    // Scenario: The area of a triangle and its samples
+    fn test_triangle_sample() {
+        let t = default_triangle();
+        assert!(crate::utils::compare_float(t.surface_area(), 1.0));
+
+        let toward = Tuple::new_point(0.0, 0.0, 5.0);
+        let (point, normal, area) = t.sample_surface(0.75, 0.75, &toward);
+        assert_eq!(point, Tuple::new_point(0.0, 0.5, 0.0));
+        assert_eq!(normal, Tuple::new_vector(0.0, 0.0, 1.0));
+        assert!(crate::utils::compare_float(area, 1.0));
+    }
+}
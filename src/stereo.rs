@@ -0,0 +1,214 @@
+use crate::{
+    camera::{Camera, View},
+    color::Color,
+};
+
+/// How the images of the two eyes share the canvas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StereoLayout {
+    /// Left eye on the left half, right eye on the right half
+    SideBySide,
+    /// Left eye on the top half, right eye on the bottom half
+    TopBottom,
+    /// Both eyes on the whole canvas, the red channel coming from the left eye and the
+    /// green and blue ones from the right eye
+    Anaglyph,
+}
+
+pub const STEREO_LAYOUTS: [StereoLayout; 3] = [
+    StereoLayout::SideBySide,
+    StereoLayout::TopBottom,
+    StereoLayout::Anaglyph,
+];
+
+impl StereoLayout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StereoLayout::SideBySide => "side-by-side",
+            StereoLayout::TopBottom => "top-bottom",
+            StereoLayout::Anaglyph => "anaglyph",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<StereoLayout> {
+        STEREO_LAYOUTS
+            .into_iter()
+            .find(|layout| layout.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+/// Two eyes on both sides of the camera position, turned inward to look at the same point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stereo {
+    pub layout: StereoLayout,
+    /// Distance between the eyes
+    pub interocular: f64,
+    /// Distance along the view direction of the point both eyes look at, which appears at
+    /// the depth of the screen
+    pub convergence: f64,
+}
+
+impl Stereo {
+    pub fn new_stereo(layout: StereoLayout, interocular: f64, convergence: f64) -> Stereo {
+        Stereo {
+            layout,
+            interocular,
+            convergence,
+        }
+    }
+
+    /// Size of the image of each eye on a `hsize` x `vsize` canvas, the right eye taking the
+    /// extra pixel of odd sizes
+    fn eye_size(&self, eye: Eye, hsize: usize, vsize: usize) -> (usize, usize) {
+        let half = |size: usize| match eye {
+            Eye::Left => size / 2,
+            Eye::Right => size - size / 2,
+        };
+        match self.layout {
+            StereoLayout::SideBySide => (half(hsize), vsize),
+            StereoLayout::TopBottom => (hsize, half(vsize)),
+            StereoLayout::Anaglyph => (hsize, vsize),
+        }
+    }
+
+    /// View of `eye`, moved half the interocular distance sideways from `view`
+    pub fn eye_view(&self, eye: Eye, view: &View) -> View {
+        let side = match eye {
+            Eye::Left => -0.5,
+            Eye::Right => 0.5,
+        };
+        let target = view.from.clone() + view.forward() * self.convergence;
+        let from = view.from.clone() + view.right() * (side * self.interocular);
+        View::new_view(from, target, view.up.clone())
+    }
+}
+
+impl Camera {
+    pub fn with_stereo(mut self, stereo: Option<Stereo>) -> Self {
+        self.stereo = stereo;
+        self.update_eyes();
+        self
+    }
+
+    /// Single camera of `eye`, rendering its part of the stereo image
+    pub fn eye(&self, eye: Eye) -> Camera {
+        let stereo = self.stereo.expect("only stereo cameras have eyes");
+        let (hsize, vsize) = stereo.eye_size(eye, self.hsize, self.vsize);
+        let view = stereo.eye_view(eye, &self.view);
+
+        let mut camera = self.clone().with_stereo(None).with_size(hsize, vsize);
+        camera.set_view(view);
+        camera
+    }
+
+    /// Builds the cameras of the eyes again after a change of the stereo camera
+    pub(crate) fn update_eyes(&mut self) {
+        self.eyes = match self.stereo {
+            Some(_) => vec![self.eye(Eye::Left), self.eye(Eye::Right)],
+            None => vec![],
+        };
+    }
+
+    /// Camera tracing the pixel `(col, row)` of the canvas, and the pixel in its own image,
+    /// the left eye for anaglyphs
+    pub(crate) fn lens_for_pixel(&self, col: usize, row: usize) -> (&Camera, usize, usize) {
+        let stereo = match self.stereo {
+            Some(stereo) => stereo,
+            None => return (self, col, row),
+        };
+        let left = &self.eyes[0];
+        match stereo.layout {
+            StereoLayout::SideBySide if col >= left.hsize => (&self.eyes[1], col - left.hsize, row),
+            StereoLayout::TopBottom if row >= left.vsize => (&self.eyes[1], col, row - left.vsize),
+            _ => (left, col, row),
+        }
+    }
+}
+
+/// Red of the left eye with the green and blue of the right one
+pub fn anaglyph(left: Color, right: Color) -> Color {
+    let (red, _, _) = left.channels();
+    let (_, green, blue) = right.channels();
+    Color::new_color(red, green, blue)
+}
+
+#[cfg(test)]
+mod stereo_tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::{color, tuple::Tuple, world::World};
+
+    fn stereo_camera(layout: StereoLayout) -> Camera {
+        let view = View::new_view(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        Camera::new(21, 11, PI / 3.0)
+            .with_view(view)
+            .with_stereo(Some(Stereo::new_stereo(layout, 0.5, 5.0)))
+    }
+
+    #[test]
+    // The eyes stand apart and look at the convergence point
+    fn stereo_eyes() {
+        let c = stereo_camera(StereoLayout::SideBySide);
+
+        let left = c.eye(Eye::Left);
+        let right = c.eye(Eye::Right);
+        assert_eq!((left.hsize, left.vsize), (10, 11));
+        assert_eq!((right.hsize, right.vsize), (11, 11));
+        assert_eq!(left.view.from, Tuple::new_point(-0.25, 0.0, -5.0));
+        assert_eq!(right.view.from, Tuple::new_point(0.25, 0.0, -5.0));
+        assert_eq!(left.view.to, Tuple::new_point(0.0, 0.0, 0.0));
+        assert!(left.stereo.is_none());
+
+        let (lens, col, row) = c.lens_for_pixel(12, 3);
+        assert_eq!(lens.view, right.view);
+        assert_eq!((col, row), (2, 3));
+
+        let c = stereo_camera(StereoLayout::TopBottom);
+        let (lens, col, row) = c.lens_for_pixel(12, 3);
+        assert_eq!(lens.view, c.eye(Eye::Left).view);
+        assert_eq!((lens.hsize, lens.vsize), (21, 5));
+        assert_eq!((col, row), (12, 3));
+    }
+
+    #[test]
+    // The images of both eyes are put together on one canvas
+    fn stereo_render() {
+        let w = World::default_world();
+
+        let c = stereo_camera(StereoLayout::SideBySide);
+        let image = c.render(&w);
+        assert_eq!((image.width, image.height), (21, 11));
+        let left = c.eye(Eye::Left).render(&w);
+        let right = c.eye(Eye::Right).render(&w);
+        assert_eq!(image.pixel_at(4, 5), left.pixel_at(4, 5));
+        assert_eq!(image.pixel_at(15, 5), right.pixel_at(5, 5));
+
+        let c = stereo_camera(StereoLayout::Anaglyph);
+        let image = c.render(&w);
+        let left = c.eye(Eye::Left).render(&w);
+        let right = c.eye(Eye::Right).render(&w);
+        assert_eq!(
+            image.pixel_at(10, 5),
+            anaglyph(left.pixel_at(10, 5), right.pixel_at(10, 5))
+        );
+
+        assert_eq!(
+            anaglyph(color::WHITE, Color::new_color(0.2, 0.3, 0.4)),
+            Color::new_color(1.0, 0.3, 0.4)
+        );
+        for layout in STEREO_LAYOUTS {
+            assert_eq!(StereoLayout::from_name(layout.name()), Some(layout));
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::f64::consts::PI;
+
+/// Order in which the tiles of an image are rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileOrder {
+    /// Rows of tiles from the top left corner
+    Scanline,
+    /// Rings of tiles around the center of the image
+    Spiral,
+    /// Along a Hilbert curve, neighbouring tiles being rendered together
+    Hilbert,
+}
+
+/// Rectangle of pixels rendered as a unit, clipped to the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    pub fn new_tile(x: usize, y: usize, width: usize, height: usize) -> Tile {
+        Tile {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.width * self.height
+    }
+}
+
+/// Square tiles of `size` pixels covering a `width` x `height` image in the given order,
+/// the tiles on the right and bottom edges being cut to the image
+pub fn tiles(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+    let size = size.max(1);
+    let columns = width.div_ceil(size);
+    let rows = height.div_ceil(size);
+
+    let mut cells: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect();
+
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            let center_x = (columns as f64 - 1.0) / 2.0;
+            let center_y = (rows as f64 - 1.0) / 2.0;
+            cells.sort_by(|a, b| {
+                spiral_key(a, center_x, center_y)
+                    .partial_cmp(&spiral_key(b, center_x, center_y))
+                    .unwrap()
+            });
+        }
+        TileOrder::Hilbert => {
+            let side = columns.max(rows).next_power_of_two();
+            cells.sort_by_key(|(column, row)| hilbert_index(side, *column, *row));
+        }
+    }
+
+    cells
+        .into_iter()
+        .map(|(column, row)| {
+            let x = column * size;
+            let y = row * size;
+            Tile::new_tile(x, y, size.min(width - x), size.min(height - y))
+        })
+        .collect()
+}
+
+/// Tiles covering `region` of an image, as `tiles` covers a whole image
+pub fn tiles_in(region: &Tile, size: usize, order: TileOrder) -> Vec<Tile> {
+    tiles(region.width, region.height, size, order)
+        .into_iter()
+        .map(|tile| {
+            Tile::new_tile(
+                tile.x + region.x,
+                tile.y + region.y,
+                tile.width,
+                tile.height,
+            )
+        })
+        .collect()
+}
+
+/// Ring around the center, then angle in the ring
+fn spiral_key(cell: &(usize, usize), center_x: f64, center_y: f64) -> (f64, f64) {
+    let dx = cell.0 as f64 - center_x;
+    let dy = cell.1 as f64 - center_y;
+    let ring = dx.abs().max(dy.abs()).round();
+    let angle = dy.atan2(dx).rem_euclid(2.0 * PI);
+    (ring, angle)
+}
+
+/// Position of `(x, y)` along the Hilbert curve filling a `side` x `side` grid,
+/// `side` being a power of two
+fn hilbert_index(side: usize, mut x: usize, mut y: usize) -> usize {
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = usize::from(x & s > 0);
+        let ry = usize::from(y & s > 0);
+        index += s * s * ((3 * rx) ^ ry);
+
+        // rotate the quadrant so the curve stays continuous
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tile_tests {
+    use super::*;
+
+    fn covered_pixels(tiles: &[Tile], width: usize, height: usize) -> Vec<usize> {
+        let mut covered = vec![0; width * height];
+        for tile in tiles {
+            for y in tile.y..tile.y + tile.height {
+                for x in tile.x..tile.x + tile.width {
+                    covered[y * width + x] += 1;
+                }
+            }
+        }
+        covered
+    }
+
+    #[test]
+    // Every pixel is covered exactly once whatever the resolution and the order
+    fn tiles_cover_image() {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+            let tiles = tiles(37, 23, 8, order);
+            assert_eq!(tiles.len(), 5 * 3);
+            assert!(
+                covered_pixels(&tiles, 37, 23)
+                    .iter()
+                    .all(|count| *count == 1)
+            );
+        }
+        assert_eq!(
+            tiles(37, 23, 8, TileOrder::Scanline).last(),
+            Some(&Tile::new_tile(32, 16, 5, 7))
+        );
+    }
+
+    #[test]
+    // The tiles of a region start at its corner and stay inside it
+    fn tiles_in_region() {
+        let region = Tile::new_tile(5, 3, 20, 9);
+        let tiles = tiles_in(&region, 8, TileOrder::Scanline);
+        assert_eq!(tiles.len(), 3 * 2);
+        assert_eq!(tiles[0], Tile::new_tile(5, 3, 8, 8));
+        assert_eq!(tiles.last(), Some(&Tile::new_tile(21, 11, 4, 1)));
+        let covered = covered_pixels(&tiles, 25, 12);
+        assert_eq!(covered.iter().sum::<usize>(), region.pixel_count());
+    }
+
+    #[test]
+    // Scanline goes row by row, spiral starts from the center
+    fn tiles_order() {
+        let scanline = tiles(30, 30, 10, TileOrder::Scanline);
+        assert_eq!(scanline[0], Tile::new_tile(0, 0, 10, 10));
+        assert_eq!(scanline[1], Tile::new_tile(10, 0, 10, 10));
+        assert_eq!(scanline[3], Tile::new_tile(0, 10, 10, 10));
+
+        let spiral = tiles(30, 30, 10, TileOrder::Spiral);
+        assert_eq!(spiral[0], Tile::new_tile(10, 10, 10, 10));
+    }
+
+    #[test]
+    // Consecutive tiles along the Hilbert curve are neighbours
+    fn tiles_hilbert() {
+        let hilbert = tiles(40, 40, 10, TileOrder::Hilbert);
+        assert_eq!(hilbert[0], Tile::new_tile(0, 0, 10, 10));
+        for pair in hilbert.windows(2) {
+            let distance = pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y);
+            assert_eq!(distance, 10);
+        }
+    }
+}
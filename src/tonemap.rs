@@ -0,0 +1,131 @@
+use crate::{canvas::Canvas, color::Color};
+
+/// Curve bringing the linear channels of the render into `[0, 1]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    /// Cuts the channels above 1
+    Clamp,
+    /// `x / (1 + x)`, never quite reaching white
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve, with a toe and a soft shoulder
+    Aces,
+}
+
+pub const TONE_MAPS: [ToneMap; 3] = [ToneMap::Clamp, ToneMap::Reinhard, ToneMap::Aces];
+
+impl ToneMap {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneMap::Clamp => "clamp",
+            ToneMap::Reinhard => "reinhard",
+            ToneMap::Aces => "aces",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ToneMap> {
+        TONE_MAPS.into_iter().find(|map| map.name() == name)
+    }
+
+    fn apply(&self, x: f64) -> f64 {
+        let x = x.max(0.0);
+        match self {
+            ToneMap::Clamp => x,
+            ToneMap::Reinhard => x / (1.0 + x),
+            ToneMap::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+        }
+    }
+}
+
+/// Conversion of the linear render to the colors shown on screen or written in 8 bit images
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapping {
+    pub operator: ToneMap,
+    /// Stops the render is brightened by before the curve, negative to darken it
+    pub exposure: f64,
+    /// Whether the result is encoded with the sRGB transfer function instead of kept linear
+    pub srgb: bool,
+}
+
+impl ToneMapping {
+    pub fn new_tone_mapping(operator: ToneMap, exposure: f64, srgb: bool) -> ToneMapping {
+        ToneMapping {
+            operator,
+            exposure,
+            srgb,
+        }
+    }
+
+    /// Clamping the linear values, the way the renders were always shown
+    pub fn new_linear() -> ToneMapping {
+        ToneMapping::new_tone_mapping(ToneMap::Clamp, 0.0, false)
+    }
+
+    /// Display color of the linear `color`, in `[0, 1]`
+    pub fn apply(&self, color: Color) -> Color {
+        let scale = self.exposure.exp2();
+        color.map(|x| {
+            let x = self.operator.apply(x * scale).min(1.0);
+            if self.srgb { srgb_encode(x) } else { x }
+        })
+    }
+
+    pub fn apply_canvas(&self, canvas: &Canvas) -> Canvas {
+        let mut canvas = canvas.clone();
+        for pixel in canvas.pixels().iter_mut() {
+            *pixel = self.apply(*pixel);
+        }
+        canvas
+    }
+}
+
+/// sRGB transfer function of a linear value in `[0, 1]`
+pub fn srgb_encode(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tonemap_tests {
+    use super::*;
+    use crate::{color, utils};
+
+    #[test]
+    // Each curve keeps black and maps bright values into range
+    fn tone_maps() {
+        let bright = Color::new_color(4.0, 1.0, -1.0);
+
+        let clamp = ToneMapping::new_linear();
+        assert_eq!(clamp.apply(bright), Color::new_color(1.0, 1.0, 0.0));
+        assert_eq!(clamp.apply(color::BLACK), color::BLACK);
+
+        let reinhard = ToneMapping::new_tone_mapping(ToneMap::Reinhard, 0.0, false);
+        assert_eq!(reinhard.apply(bright), Color::new_color(0.8, 0.5, 0.0));
+
+        let aces = ToneMapping::new_tone_mapping(ToneMap::Aces, 0.0, false);
+        assert_eq!(aces.apply(color::BLACK), color::BLACK);
+        let (white, _, _) = aces.apply(Color::new_color(100.0, 0.0, 0.0)).channels();
+        assert!(white > 0.99 && white <= 1.0);
+
+        for map in TONE_MAPS {
+            assert_eq!(ToneMap::from_name(map.name()), Some(map));
+        }
+    }
+
+    #[test]
+    // Exposure is counted in stops, sRGB brightens the mid tones
+    fn tone_mapping_exposure_srgb() {
+        let gray = Color::new_color(0.25, 0.25, 0.25);
+
+        let brighter = ToneMapping::new_tone_mapping(ToneMap::Clamp, 1.0, false);
+        assert_eq!(brighter.apply(gray), Color::new_color(0.5, 0.5, 0.5));
+
+        let srgb = ToneMapping::new_tone_mapping(ToneMap::Clamp, 0.0, true);
+        let (red, _, _) = srgb.apply(gray).channels();
+        assert!(utils::compare_float_with_threshold(red, 0.5371, 0.0001));
+        assert!(utils::compare_float(srgb_encode(1.0), 1.0));
+        assert!(utils::compare_float(srgb_encode(0.001), 0.01292));
+    }
+}
@@ -1,23 +1,23 @@
-use crate::{matrix::Matrix, tuple::Tuple};
+use crate::{matrix::Matrix4, tuple::Tuple};
 
-pub fn create_translation(x: f64, y: f64, z: f64) -> Matrix {
-    let mut m = Matrix::new_identity_matrix(4);
+pub fn create_translation(x: f64, y: f64, z: f64) -> Matrix4 {
+    let mut m = Matrix4::new_identity_matrix();
     m.set_element(0, 3, x);
     m.set_element(1, 3, y);
     m.set_element(2, 3, z);
     m
 }
 
-pub fn create_scaling(x: f64, y: f64, z: f64) -> Matrix {
-    let mut m = Matrix::new_identity_matrix(4);
+pub fn create_scaling(x: f64, y: f64, z: f64) -> Matrix4 {
+    let mut m = Matrix4::new_identity_matrix();
     m.set_element(0, 0, x);
     m.set_element(1, 1, y);
     m.set_element(2, 2, z);
     m
 }
 
-pub fn create_rotation_x(radians: f64) -> Matrix {
-    let mut m = Matrix::new_identity_matrix(4);
+pub fn create_rotation_x(radians: f64) -> Matrix4 {
+    let mut m = Matrix4::new_identity_matrix();
     m.set_element(1, 1, radians.cos());
     m.set_element(1, 2, -radians.sin());
     m.set_element(2, 1, radians.sin());
@@ -25,8 +25,8 @@ pub fn create_rotation_x(radians: f64) -> Matrix {
     m
 }
 
-pub fn create_rotation_y(radians: f64) -> Matrix {
-    let mut m = Matrix::new_identity_matrix(4);
+pub fn create_rotation_y(radians: f64) -> Matrix4 {
+    let mut m = Matrix4::new_identity_matrix();
     m.set_element(0, 0, radians.cos());
     m.set_element(0, 2, radians.sin());
     m.set_element(2, 0, -radians.sin());
@@ -34,8 +34,8 @@ pub fn create_rotation_y(radians: f64) -> Matrix {
     m
 }
 
-pub fn create_rotation_z(radians: f64) -> Matrix {
-    let mut m = Matrix::new_identity_matrix(4);
+pub fn create_rotation_z(radians: f64) -> Matrix4 {
+    let mut m = Matrix4::new_identity_matrix();
     m.set_element(0, 0, radians.cos());
     m.set_element(0, 1, -radians.sin());
     m.set_element(1, 0, radians.sin());
@@ -43,8 +43,8 @@ pub fn create_rotation_z(radians: f64) -> Matrix {
     m
 }
 
-pub fn create_shearing(x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f64) -> Matrix {
-    let mut m = Matrix::new_identity_matrix(4);
+pub fn create_shearing(x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f64) -> Matrix4 {
+    let mut m = Matrix4::new_identity_matrix();
     m.set_element(0, 1, x_y);
     m.set_element(0, 2, x_z);
     m.set_element(1, 0, y_x);
@@ -54,34 +54,34 @@ pub fn create_shearing(x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f6
     m
 }
 
-impl Matrix {
-    pub fn translation(self, x: f64, y: f64, z: f64) -> Matrix {
+impl Matrix4 {
+    pub fn translation(self, x: f64, y: f64, z: f64) -> Matrix4 {
         let translation = create_translation(x, y, z);
         translation * self
     }
 
-    pub fn scaling(self, x: f64, y: f64, z: f64) -> Matrix {
+    pub fn scaling(self, x: f64, y: f64, z: f64) -> Matrix4 {
         let scaling = create_scaling(x, y, z);
         scaling * self
     }
 
-    pub fn rotation_x(self, radians: f64) -> Matrix {
+    pub fn rotation_x(self, radians: f64) -> Matrix4 {
         let rotation_x = create_rotation_x(radians);
         rotation_x * self
     }
 
-    pub fn rotation_y(self, radians: f64) -> Matrix {
+    pub fn rotation_y(self, radians: f64) -> Matrix4 {
         let rotation_y = create_rotation_y(radians);
         rotation_y * self
     }
 
-    pub fn rotation_z(self, radians: f64) -> Matrix {
+    pub fn rotation_z(self, radians: f64) -> Matrix4 {
         let rotation_z = create_rotation_z(radians);
         rotation_z * self
     }
 
-    pub fn shearing(self, x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f64) -> Matrix {
-        let mut m = Matrix::new_identity_matrix(4);
+    pub fn shearing(self, x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f64) -> Matrix4 {
+        let mut m = Matrix4::new_identity_matrix();
         m.set_element(0, 1, x_y);
         m.set_element(0, 2, x_z);
         m.set_element(1, 0, y_x);
@@ -92,18 +92,79 @@ impl Matrix {
     }
 }
 
-pub fn view_transform(from: &Tuple, to: &Tuple, up: &Tuple) -> Matrix {
+/// Elementary transformation kept by its parameters, so that two transformations of the
+/// same kind can be blended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transformation {
+    Translation(f64, f64, f64),
+    Scaling(f64, f64, f64),
+    RotationX(f64),
+    RotationY(f64),
+    RotationZ(f64),
+    Shearing([f64; 6]),
+}
+
+impl Transformation {
+    pub fn matrix(&self) -> Matrix4 {
+        match *self {
+            Transformation::Translation(x, y, z) => create_translation(x, y, z),
+            Transformation::Scaling(x, y, z) => create_scaling(x, y, z),
+            Transformation::RotationX(radians) => create_rotation_x(radians),
+            Transformation::RotationY(radians) => create_rotation_y(radians),
+            Transformation::RotationZ(radians) => create_rotation_z(radians),
+            Transformation::Shearing([x_y, x_z, y_x, y_z, z_x, z_y]) => {
+                create_shearing(x_y, x_z, y_x, y_z, z_x, z_y)
+            }
+        }
+    }
+
+    /// Parameters blended linearly by `factor`, `None` when the transformations are not of
+    /// the same kind
+    pub fn lerp(&self, other: &Transformation, factor: f64) -> Option<Transformation> {
+        let mix = |a: f64, b: f64| a + (b - a) * factor;
+        let blended = match (*self, *other) {
+            (Transformation::Translation(ax, ay, az), Transformation::Translation(bx, by, bz)) => {
+                Transformation::Translation(mix(ax, bx), mix(ay, by), mix(az, bz))
+            }
+            (Transformation::Scaling(ax, ay, az), Transformation::Scaling(bx, by, bz)) => {
+                Transformation::Scaling(mix(ax, bx), mix(ay, by), mix(az, bz))
+            }
+            (Transformation::RotationX(a), Transformation::RotationX(b)) => {
+                Transformation::RotationX(mix(a, b))
+            }
+            (Transformation::RotationY(a), Transformation::RotationY(b)) => {
+                Transformation::RotationY(mix(a, b))
+            }
+            (Transformation::RotationZ(a), Transformation::RotationZ(b)) => {
+                Transformation::RotationZ(mix(a, b))
+            }
+            (Transformation::Shearing(a), Transformation::Shearing(b)) => {
+                Transformation::Shearing(std::array::from_fn(|i| mix(a[i], b[i])))
+            }
+            _ => return None,
+        };
+        Some(blended)
+    }
+}
+
+/// Transform applying `transformations` in order, the first one being applied first
+pub fn create_transform(transformations: &[Transformation]) -> Matrix4 {
+    transformations
+        .iter()
+        .fold(Matrix4::new_identity_matrix(), |m, t| t.matrix() * m)
+}
+
+pub fn view_transform(from: &Tuple, to: &Tuple, up: &Tuple) -> Matrix4 {
     let forward = (to.clone() - from.clone()).normalize();
     let upn = up.normalize();
     let left = Tuple::cross_product(&forward, &upn);
     let true_up = Tuple::cross_product(&left, &forward);
-    let orientation = Matrix::with_data(
-        4,
-        vec![
-            left.x, left.y, left.z, 0.0, true_up.x, true_up.y, true_up.z, 0.0, -forward.x,
-            -forward.y, -forward.z, 0.0, 0.0, 0.0, 0.0, 1.0,
-        ],
-    );
+    let orientation = Matrix4::with_data([
+        [left.x, left.y, left.z, 0.0],
+        [true_up.x, true_up.y, true_up.z, 0.0],
+        [-forward.x, -forward.y, -forward.z, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
     orientation * create_translation(-from.x, -from.y, -from.z)
 }
 
@@ -325,7 +386,7 @@ mod transformation_tests {
 
         let t = view_transform(&from, &to, &up);
 
-        assert_eq!(Matrix::new_identity_matrix(4), t);
+        assert_eq!(Matrix4::new_identity_matrix(), t);
     }
 
     #[test]
@@ -362,27 +423,27 @@ mod transformation_tests {
         let t = view_transform(&from, &to, &up);
 
         assert_eq!(
-            Matrix::with_data(
-                4,
-                vec![
+            Matrix4::with_data([
+                [
                     -0.5070925528371099,
                     0.5070925528371099,
                     0.6761234037828132,
-                    -2.366431913239846,
+                    -2.366431913239846
+                ],
+                [
                     0.7677159338596801,
                     0.6060915267313263,
                     0.12121830534626524,
-                    -2.8284271247461894,
+                    -2.8284271247461894
+                ],
+                [
                     -0.35856858280031806,
                     0.5976143046671968,
                     -0.7171371656006361,
-                    0.0,
-                    0.0,
-                    0.0,
-                    0.0,
-                    1.0
-                ]
-            ),
+                    0.0
+                ],
+                [0.0, 0.0, 0.0, 1.0]
+            ]),
             t
         );
     }
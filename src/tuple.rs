@@ -1,3 +1,4 @@
+use std::fmt;
 use std::ops::Add;
 use std::ops::Div;
 use std::ops::Mul;
@@ -137,6 +138,19 @@ impl Tuple {
     }
 }
 
+/// `(x, y, z)`, the precision applying to each coordinate
+impl fmt::Display for Tuple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        fmt::Display::fmt(&self.x, f)?;
+        write!(f, ", ")?;
+        fmt::Display::fmt(&self.y, f)?;
+        write!(f, ", ")?;
+        fmt::Display::fmt(&self.z, f)?;
+        write!(f, ")")
+    }
+}
+
 impl PartialEq for Tuple {
     fn eq(&self, other: &Self) -> bool {
         compare_float(self.x, other.x)
@@ -1,8 +1,20 @@
-use std::path::Path;
+use std::{fs, io, path::Path, sync::Mutex, time::Instant};
 
+use indicatif::{ProgressBar, ProgressStyle};
 use minifb::Window;
 
-use crate::{camera::Camera, drivers::minifb_driver, io::yaml::parse, world::World};
+use crate::{
+    aov,
+    camera::{Camera, Integrator},
+    checkpoint::{CHECKPOINT_INTERVAL, Checkpoint, scene_hash},
+    debug::DebugMode,
+    drivers::{minifb_driver, viewer::Viewer},
+    io::{
+        image::{is_float_image, write_image},
+        yaml::{parse, parse_animation, scene_files, try_parse_at},
+    },
+    world::World,
+};
 
 pub fn compare_float(value1: f64, value2: f64) -> bool {
     (value1 - value2).abs() < 0.00001
@@ -25,41 +37,141 @@ pub fn index_from_pos(x: usize, y: usize, width: usize) -> usize {
 // TODO a ranger
 
 pub fn init_from_path(path: &Path) -> (Camera, Vec<u32>, Window) {
-    let (objects, ligths, camera) = parse(path);
-
-    //world creation
-    let mut world = World::new_world();
-    world.objects = objects;
-
-    //ligth sources
-    world.light_sources = ligths;
+    let (world, camera) = parse(path);
 
     //render result to a canvas
-    // let canvas = camera.render_with_update_bar(world);
-    let canvas = camera.render_par_with_update_bar(world);
-
-    let buffer = minifb_driver::buffer_from_canvas(&canvas);
+    println!("Starting render");
+    let bar = ProgressBar::new((camera.hsize * camera.vsize) as u64);
+    bar.set_style(
+        ProgressStyle::with_template("{bar:120} [{percent_precise}%] [T : {elapsed:}]").unwrap(),
+    );
+    let canvas = camera.render_with_progress(&world, |progress| {
+        bar.inc(progress.tile.pixel_count() as u64)
+    });
+    bar.finish();
+    println!("Done rendering");
+
+    let buffer = minifb_driver::buffer_from_canvas(&camera.tone_mapping.apply_canvas(&canvas));
     let window = minifb_driver::new_window(&canvas);
 
     (camera, buffer, window)
 }
 
 pub fn init_headless_from_path(path: &Path) -> (Camera, Vec<u32>, Window) {
-    let (objects, ligths, camera) = parse(path);
-
-    //world creation
-    let mut world = World::new_world();
-    world.objects = objects;
-
-    //ligth sources
-    world.light_sources = ligths;
+    let (world, camera) = parse(path);
 
     //render result to a canvas
-    // let canvas = camera.render_with_update_bar(world);
-    let canvas = camera.render_par_headless(world);
+    let canvas = camera.render(&world);
 
-    let buffer = minifb_driver::buffer_from_canvas(&canvas);
+    let buffer = minifb_driver::buffer_from_canvas(&camera.tone_mapping.apply_canvas(&canvas));
     let window = minifb_driver::new_window(&canvas);
 
     (camera, buffer, window)
-}
\ No newline at end of file
+}
+
+/// Opens the window right away, the scene being rendered progressively in the background
+pub fn init_viewer_from_path(path: &Path) -> Viewer {
+    let (world, camera) = parse(path);
+    Viewer::new_viewer(camera, world).with_scene_file(path)
+}
+
+/// Renders the scene into the image `output`, the AOVs of the camera being written next to
+/// it. PFM and Radiance images keep the linear values, the others are tone mapped. Animated
+/// scenes are written as a sequence of images numbered after `output`. The tiles done are
+/// saved periodically next to the image, an interrupted render of the same scene resuming
+/// from them, the tiles unfinished when it stopped being rendered again from the start. A
+/// `debug_mode` replaces the integrator of the camera.
+pub fn render_to_file(path: &Path, output: &Path, debug_mode: Option<DebugMode>) -> io::Result<()> {
+    // the included files are part of the scene the checkpoint is made for
+    let mut scene = fs::read(path)?;
+    for file in scene_files(path).iter().skip(1) {
+        scene.extend(fs::read(file)?);
+    }
+    // the checkpoint of a render with another integrator isn't resumed
+    if let Some(mode) = debug_mode {
+        scene.extend(mode.name().as_bytes());
+    }
+    let with_mode = |camera: Camera| match debug_mode {
+        Some(mode) => camera.with_integrator(Integrator::Debug(mode)),
+        None => camera,
+    };
+
+    let invalid = |error| io::Error::new(io::ErrorKind::InvalidData, error);
+
+    let animation = match parse_animation(path).map_err(invalid)? {
+        Some(animation) => animation,
+        None => {
+            let (world, camera) = try_parse_at(path, 0.0).map_err(invalid)?;
+            let camera = with_mode(camera);
+            return render_frame(&world, &camera, output, scene_hash(&scene, 0.0));
+        }
+    };
+
+    for frame in 0..animation.frames {
+        println!("Frame {}/{}", frame + 1, animation.frames);
+        let time = animation.time(frame);
+        let (world, camera) = try_parse_at(path, time).map_err(invalid)?;
+        let camera = with_mode(camera);
+        let output = animation.frame_path(output, frame);
+        render_frame(&world, &camera, &output, scene_hash(&scene, time))?;
+    }
+    Ok(())
+}
+
+fn render_frame(world: &World, camera: &Camera, output: &Path, hash: u64) -> io::Result<()> {
+    let checkpoint_path = Checkpoint::path(output);
+    // the AOVs are saved with the tiles, after the beauty image
+    let layers = 1 + camera.aovs.len();
+    let checkpoint = Checkpoint::load(&checkpoint_path, hash, camera.hsize, camera.vsize, layers)
+        .unwrap_or_else(|| Checkpoint::new_checkpoint(hash, camera.hsize, camera.vsize, layers));
+    let resumed: usize = checkpoint
+        .tiles
+        .iter()
+        .map(|(tile, _)| tile.pixel_count())
+        .sum();
+    if resumed > 0 {
+        println!("Resuming from {}", checkpoint_path.display());
+    }
+
+    let bar = ProgressBar::new(camera.render_region().pixel_count() as u64);
+    bar.set_style(
+        ProgressStyle::with_template("{bar:120} [{percent_precise}%] [T : {elapsed:}]").unwrap(),
+    );
+    bar.inc(resumed as u64);
+
+    let done_tiles = checkpoint.tiles.clone();
+    let checkpoint = Mutex::new((checkpoint, Instant::now()));
+    let (canvas, images) = camera.render_resuming(world, &done_tiles, |progress| {
+        bar.inc(progress.tile.pixel_count() as u64);
+
+        let (checkpoint, saved) = &mut *checkpoint.lock().unwrap();
+        checkpoint.add(*progress.tile, progress.pixels.to_vec());
+        if saved.elapsed() >= CHECKPOINT_INTERVAL {
+            if let Err(error) = checkpoint.save(&checkpoint_path) {
+                bar.println(format!("Could not save the checkpoint: {}", error));
+            }
+            *saved = Instant::now();
+        }
+    });
+    bar.finish();
+
+    let float = is_float_image(output);
+    if float {
+        write_image(&canvas, output)?;
+    } else {
+        write_image(&camera.tone_mapping.apply_canvas(&canvas), output)?;
+    }
+
+    for (aov, image) in camera.aovs.iter().zip(&images) {
+        if float {
+            write_image(image, &aov.path(output))?;
+        } else {
+            write_image(&aov::displayable(*aov, image), &aov.path(output))?;
+        }
+    }
+
+    match fs::remove_file(&checkpoint_path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
@@ -1,25 +1,51 @@
+use std::{cell::RefCell, f64::consts::PI};
+
+use uuid::Uuid;
+
 use crate::{
     color::{self, Color},
-    ray::{Intersection, Ray, hit_intersections, reflect},
-    reflection::{Material, PointLight, lighting},
-    shape::{object::Object, shape::Shape},
+    inspection::{RayKind, Recorder},
+    media::{Fog, inside_length},
+    occlusion::AmbientOcclusion,
+    ray::{Intersection, Ray, reflect},
+    reflection::{EmissiveLight, Material, PointLight, lighting_parts},
+    shape::{
+        object::{Object, Placement},
+        shape::Shape,
+    },
     transformation,
     tuple::Tuple,
 };
 
 pub const SHADOW_EPSILON: f64 = 0.00000000001;
+/// Offset of the light samples from the emissive surfaces so they don't shadow themselves
+pub const SAMPLE_EPSILON: f64 = 0.0001;
+
+thread_local! {
+    /// Intersections of a ray with the world, as distances and indices of the objects, used
+    /// for the refractive indices by each thread
+    static WORLD_HITS: RefCell<Vec<(f64, usize)>> = const { RefCell::new(Vec::new()) };
+    /// Ids and refractive indices of the objects a ray is inside of
+    static CONTAINERS: RefCell<Vec<(Uuid, f64)>> = const { RefCell::new(Vec::new()) };
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct World {
     pub light_sources: Vec<PointLight>,
+    pub emissive_lights: Vec<EmissiveLight>,
     pub objects: Vec<Object>,
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+    pub fog: Option<Fog>,
 }
 
 impl World {
     pub fn new_world() -> World {
         World {
             light_sources: vec![],
+            emissive_lights: vec![],
             objects: vec![],
+            ambient_occlusion: None,
+            fog: None,
         }
     }
 
@@ -36,98 +62,373 @@ impl World {
         s1.material.specular = 0.2;
 
         let mut s2 = Object::new_sphere();
-        s2.transform = transformation::create_scaling(0.5, 0.5, 0.5);
+        s2.set_transform(&transformation::create_scaling(0.5, 0.5, 0.5));
 
         World {
             light_sources: vec![light],
+            emissive_lights: vec![],
             objects: vec![s1, s2],
+            ambient_occlusion: None,
+            fog: None,
         }
     }
 
-    pub fn intersect_world(&self, ray: &Ray) -> Vec<Intersection> {
+    pub fn intersect_world(&self, ray: &Ray) -> Vec<Intersection<'_>> {
         let mut intersections = vec![];
+        self.intersect_world_into(ray, &mut intersections);
+        intersections
+    }
+
+    /// Replaces the content of `intersections` by the sorted intersections of `ray`
+    /// ahead of its origin, reusing the allocation of the buffer
+    pub fn intersect_world_into<'a>(
+        &'a self,
+        ray: &Ray,
+        intersections: &mut Vec<Intersection<'a>>,
+    ) {
+        intersections.clear();
         for object in &self.objects {
-            intersections.append(&mut object.clone().intersect(ray.clone()));
+            object.intersect_with(ray, |t| {
+                if t > 0.0 {
+                    intersections.push(Intersection::new(t, object));
+                }
+            });
         }
-        intersections.retain(|value| value.t > 0.0);
         intersections.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
-        intersections
     }
 
+    /// Closest intersection of `ray` ahead of its origin, found without building the list
+    pub fn hit(&self, ray: &Ray) -> Option<Intersection<'_>> {
+        let mut closest: Option<Intersection> = None;
+        for object in &self.objects {
+            object.intersect_with(ray, |t| {
+                if t > 0.0 && closest.is_none_or(|hit| t < hit.t) {
+                    closest = Some(Intersection::new(t, object));
+                }
+            });
+        }
+        closest
+    }
+
+    /// Whether `ray` hits, closer than `distance`, an object accepted by `blocks`,
+    /// returning as soon as one is found
+    pub fn any_hit(&self, ray: &Ray, distance: f64, blocks: impl Fn(&Object) -> bool) -> bool {
+        self.objects
+            .iter()
+            .any(|object| blocks(object) && object.hits_before(ray, distance))
+    }
+
+    /// Whether an opaque object casting shadows lies between `point` and the light
     pub fn is_shadowed_for_light(&self, point: &Tuple, light_source: &PointLight) -> bool {
-        let v = light_source.position.clone() - point.clone();
-        let distance = v.magnitude();
-        let direction = v.normalize();
+        let (r, distance) = shadow_ray(point, light_source, 0.0);
+        self.any_hit(&r, distance, Object::blocks_light)
+    }
+
+    /// Fraction of the light reaching `point`, each transparent surface crossed on the way
+    /// filters it by its color and transparency, an opaque one blocks it
+    pub fn light_visibility(&self, point: &Tuple, light_source: &PointLight) -> Color {
+        self.light_visibility_at(point, light_source, 0.0)
+    }
+
+    /// `light_visibility` with the objects where they stand at `time`
+    pub fn light_visibility_at(
+        &self,
+        point: &Tuple,
+        light_source: &PointLight,
+        time: f64,
+    ) -> Color {
+        let (r, distance) = shadow_ray(point, light_source, time);
+        if self.any_hit(&r, distance, Object::blocks_light) {
+            return color::BLACK;
+        }
+
+        // the filters multiply, so the surfaces can be crossed in any order
+        let mut visibility = color::WHITE;
+        for object in &self.objects {
+            if let Some(medium) = &object.material.medium {
+                visibility =
+                    visibility * (-medium.density * inside_length(object, &r, distance)).exp();
+            }
+            if object.has_shadow() && !object.blocks_light() {
+                let material = &object.material;
+                object.intersect_with(&r, |t| {
+                    if t > 0.0 && t < distance {
+                        let filter =
+                            material.color_at_local(*object.inverse_at(time) * r.position(t));
+                        visibility = visibility * filter * material.transparency;
+                    }
+                });
+            }
+
+            if visibility == color::BLACK {
+                break;
+            }
+        }
+        visibility
+    }
 
-        let r = Ray::new(point.clone(), direction);
-        let intersections = self.intersect_world(&r);
+    /// Point lights standing for the emissive lights, sampled for a `point` shaded at `time`
+    pub fn sample_emissive_lights(&self, point: &Tuple, time: f64) -> Vec<PointLight> {
+        let mut samples = vec![];
 
-        if let Some(h) = hit_intersections(intersections) {
-            return h.object.has_shadow() && h.t < distance;
+        for light in &self.emissive_lights {
+            for _ in 0..light.samples {
+                samples.push(self.emissive_sample(light, point, time));
+            }
         }
-        false
+        samples
+    }
+
+    /// Sample of `light` seen from `point` at `time` as a point light, its intensity being
+    /// the light the sample brings to a surface facing it, the 1/π of a Lambertian surface
+    /// included
+    pub fn emissive_sample(&self, light: &EmissiveLight, point: &Tuple, time: f64) -> PointLight {
+        let (object, position, normal, inverse_pdf) =
+            self.sample_emissive_light(light, point, time);
+        let emission = object.get_material().emission;
+
+        let to_light = position.clone() - point.clone();
+        let distance2 = Tuple::dot_product(&to_light, &to_light);
+        let cos_light = -Tuple::dot_product(&to_light.normalize(), &normal);
+        let weight = cos_light.max(0.0) * inverse_pdf / (distance2 * PI * light.samples as f64);
+
+        PointLight::new_point_light(emission * weight, position + normal * SAMPLE_EPSILON)
+    }
+
+    /// Random point and normal on one of the objects of `light` where they stand at `time`,
+    /// seen from `point`, with the object it lies on and the inverse of the probability
+    /// density of the sample
+    pub fn sample_emissive_light(
+        &self,
+        light: &EmissiveLight,
+        point: &Tuple,
+        time: f64,
+    ) -> (&Object, Tuple, Tuple, f64) {
+        let total_area: f64 = light.areas.iter().sum();
+
+        // pick an object of the light proportionally to its area
+        let mut remaining = rand::random::<f64>() * total_area;
+        let mut picked = light.objects.len() - 1;
+        for (i, area) in light.areas.iter().enumerate() {
+            if remaining < *area {
+                picked = i;
+                break;
+            }
+            remaining -= area;
+        }
+
+        let object = &self.objects[light.objects[picked]];
+        let placement = object.placement_at(time);
+        let (position, normal, sampled_area) =
+            object
+                .shape
+                .sample_surface(&placement, rand::random(), rand::random(), point);
+
+        (
+            object,
+            position,
+            normal,
+            sampled_area * total_area / light.areas[picked],
+        )
+    }
+
+    /// Whether `object` is sampled as part of an emissive light
+    pub fn is_emissive_light(&self, object: &Object) -> bool {
+        self.emissive_lights.iter().any(|light| {
+            light
+                .objects
+                .iter()
+                .any(|index| self.objects[*index].get_id() == object.get_id())
+        })
     }
 
     pub fn shade_hit(&self, comps: &Computation, remaining_calculations: usize) -> Color {
-        let mut shade = Color::new_color(0.0, 0.0, 0.0);
+        self.shade_hit_with(comps, remaining_calculations, &mut ())
+    }
+
+    /// `shade_hit` telling `recorder` about each light and the parts of the color
+    pub fn shade_hit_with(
+        &self,
+        comps: &Computation,
+        remaining_calculations: usize,
+        recorder: &mut impl Recorder,
+    ) -> Color {
+        let mut material = comps.object.get_material();
+        let mut surface = material.emission;
+
+        if let Some(settings) = &self.ambient_occlusion {
+            material.ambient *= self.ambient_occlusion(&comps.over_point, &comps.normalv, settings);
+        }
 
-        for light in &self.light_sources {
-            let is_shadow = self.is_shadowed_for_light(&comps.over_point, light);
-            let surface = lighting(
-                &comps.object.get_material(),
+        // the emissive lights add no ambient term, the intensity of their samples depending on
+        // where they lie, like path tracing which has none
+        let sampled_lights = self.sample_emissive_lights(&comps.over_point, comps.time);
+        let unlit = Material {
+            ambient: 0.0,
+            ..material.clone()
+        };
+        let lights = self
+            .light_sources
+            .iter()
+            .map(|light| (light, &material))
+            .chain(sampled_lights.iter().map(|light| (light, &unlit)));
+
+        for (light, lit_material) in lights {
+            let light_visibility = self.light_visibility_at(&comps.over_point, light, comps.time);
+            recorder.light(light, light_visibility);
+            let (diffuse, specular) = lighting_parts(
+                lit_material,
                 light,
                 &comps.over_point,
                 &comps.eyev,
                 &comps.normalv,
-                is_shadow,
-                comps.object.clone(),
+                light_visibility,
+                &comps.placement.inverse,
             );
+            recorder.lit(diffuse, specular);
+            surface += diffuse + specular;
+        }
 
-            let reflected = self.reflected_color(comps.clone(), remaining_calculations);
+        let reflected = self.reflected_color_with(comps.clone(), remaining_calculations, recorder);
+        let refracted = self.refracted_color_with(comps.clone(), remaining_calculations, recorder);
 
-            let refracted = self.refracted_color(comps.clone(), remaining_calculations);
-            let material = comps.object.get_material();
-            if material.reflective > 0.0 && material.transparency > 0.0 {
-                let reflectance = comps.schlick();
-                shade += surface + reflected * reflectance + refracted * (1.0 - reflectance);
-            } else {
-                shade += surface + reflected + refracted;
-            }
+        if material.reflective > 0.0 && material.transparency > 0.0 {
+            let reflectance = comps.schlick();
+            recorder.shaded(surface, reflected, refracted, Some(reflectance));
+            surface + reflected * reflectance + refracted * (1.0 - reflectance)
+        } else {
+            recorder.shaded(surface, reflected, refracted, None);
+            surface + reflected + refracted
         }
-
-        shade
     }
 
     pub fn color_at(&self, ray: &Ray, remaining_calculations: usize) -> Color {
-        let intersections = self.intersect_world(ray);
+        self.color_at_with(ray, remaining_calculations, &mut ())
+    }
 
-        if intersections.is_empty() {
-            return color::BLACK;
-        }
+    /// `color_at` telling `recorder` about the ray, what it hits and the resulting color
+    pub fn color_at_with(
+        &self,
+        ray: &Ray,
+        remaining_calculations: usize,
+        recorder: &mut impl Recorder,
+    ) -> Color {
+        recorder.ray(ray);
+        let (color, distance) = match self.hit(ray) {
+            Some(hit) => {
+                let comps = self.computations_at(&hit, ray);
+                recorder.hit(&comps);
+                (
+                    self.shade_hit_with(&comps, remaining_calculations, recorder),
+                    hit.t,
+                )
+            }
+            None => (color::BLACK, f64::INFINITY),
+        };
 
-        let comps = prepare_computations_v2(&intersections[0], ray, intersections.clone());
-        self.shade_hit(&comps, remaining_calculations)
+        let color = self.apply_media(ray, distance, color);
+        let color = self.apply_fog(color, distance);
+        recorder.color(color);
+        color
+    }
+
+    /// Computations of the hit of `ray`, the refractive indices on both sides of the
+    /// surface are only looked up among all the intersections for transparent objects
+    pub fn computations_at<'a>(&'a self, hit: &Intersection<'a>, ray: &Ray) -> Computation<'a> {
+        let mut comps = prepare_computations_helper(hit, ray);
+        if hit.object.material.transparency > 0.0 {
+            WORLD_HITS.with_borrow_mut(|hits| {
+                hits.clear();
+                for (index, object) in self.objects.iter().enumerate() {
+                    object.intersect_with(ray, |t| {
+                        if t > 0.0 {
+                            hits.push((t, index));
+                        }
+                    });
+                }
+                hits.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+                let intersections = hits
+                    .iter()
+                    .map(|(t, index)| Intersection::new(*t, &self.objects[*index]));
+                (comps.n1, comps.n2) = CONTAINERS.with_borrow_mut(|containers| {
+                    refractive_indices(hit, intersections, containers)
+                });
+            });
+        }
+        comps
     }
 
     pub fn reflected_color(&self, comps: Computation, remaining_calculations: usize) -> Color {
+        self.reflected_color_with(comps, remaining_calculations, &mut ())
+    }
+
+    pub fn reflected_color_with(
+        &self,
+        comps: Computation,
+        remaining_calculations: usize,
+        recorder: &mut impl Recorder,
+    ) -> Color {
         if comps.object.get_material().reflective == 0.0 || remaining_calculations == 0 {
             return color::BLACK;
         }
-        let reflect_ray = Ray::new(comps.over_point, comps.reflectv);
-        let ref_color = self.color_at(&reflect_ray, remaining_calculations - 1);
+        let reflect_ray = Ray::new(comps.over_point, comps.reflectv).with_time(comps.time);
+        recorder.bounce(RayKind::Reflection);
+        let ref_color = self.color_at_with(&reflect_ray, remaining_calculations - 1, recorder);
 
         ref_color * comps.object.get_material().reflective
     }
 
+    pub fn with_ambient_occlusion(mut self, ambient_occlusion: AmbientOcclusion) -> Self {
+        self.ambient_occlusion = Some(ambient_occlusion);
+        self
+    }
+
+    pub fn with_fog(mut self, fog: Fog) -> Self {
+        self.fog = Some(fog);
+        self
+    }
+
     pub fn add_object(&mut self, obj: Object) {
         self.objects.push(obj);
     }
+
+    /// Adds emissive objects to the world, sampled together as a single light
+    pub fn add_emissive_light(&mut self, objects: Vec<Object>, samples: usize) {
+        let first = self.objects.len();
+        self.objects.extend(objects);
+        let indexes = (first..self.objects.len()).collect();
+        let areas = self.objects[first..]
+            .iter()
+            .map(Object::surface_area)
+            .collect();
+        self.emissive_lights
+            .push(EmissiveLight::new_emissive_light(indexes, areas, samples));
+    }
+
+    /// Adds an emissive object to the emissive light at `light_index`
+    pub fn add_to_emissive_light(&mut self, light_index: usize, object: Object) {
+        let light = &mut self.emissive_lights[light_index];
+        light.objects.push(self.objects.len());
+        light.areas.push(object.surface_area());
+        self.objects.push(object);
+    }
+}
+
+/// Ray from `point` toward the light at `time` and the distance to the light
+fn shadow_ray(point: &Tuple, light_source: &PointLight, time: f64) -> (Ray, f64) {
+    let v = light_source.position.clone() - point.clone();
+    let distance = v.magnitude();
+    (
+        Ray::new(point.clone(), v.normalize()).with_time(time),
+        distance,
+    )
 }
 
 #[derive(Debug, Clone)]
-pub struct Computation {
+pub struct Computation<'a> {
     pub t: f64,
-    pub object: Object,
+    pub object: &'a Object,
     pub point: Tuple,
     pub over_point: Tuple,
     pub under_point: Tuple,
@@ -137,12 +438,16 @@ pub struct Computation {
     pub reflectv: Tuple,
     pub n1: f64,
     pub n2: f64,
+    /// Time of the ray, given to the rays leaving the hit
+    pub time: f64,
+    /// Transform of the object at the time of the ray
+    pub placement: Placement,
 }
 
-impl PartialEq for Computation {
+impl PartialEq for Computation<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.t == other.t
-            && self.object == other.object.clone()
+            && self.object == other.object
             && self.point == other.point
             && self.over_point == other.over_point
             && self.under_point == other.under_point
@@ -151,93 +456,106 @@ impl PartialEq for Computation {
             && self.inside == other.inside
             && self.reflectv == other.reflectv
             && self.n1 == other.n1
+            && self.time == other.time
+            && self.placement == other.placement
             && self.n2 == other.n2
     }
 }
 
-impl Computation {
-    fn new() -> Computation {
-        Computation {
-            t: 0.0,
-            object: Object::new_sphere(),
-            point: Tuple::new_point(0.0, 0.0, 0.0),
-            over_point: Tuple::new_point(0.0, 0.0, 0.0),
-            under_point: Tuple::new_point(0.0, 0.0, 0.0),
-            eyev: Tuple::new_vector(0.0, 0.0, 0.0),
-            normalv: Tuple::new_vector(0.0, 0.0, 0.0),
-            inside: true,
-            reflectv: Tuple::new_vector(0.0, 0.0, 0.0),
-            n1: 0.0,
-            n2: 0.0,
-        }
+pub fn prepare_computations_helper<'a>(
+    intersection: &Intersection<'a>,
+    ray: &Ray,
+) -> Computation<'a> {
+    let t = intersection.t;
+    let object = intersection.object;
+    let point = ray.position(t);
+    let eyev = ray.direction.clone() * -1.0;
+    let placement = object.placement_at(ray.time);
+    let mut normalv = object.shape.local_normal_at(&placement, point.clone());
+    let reflectv = reflect(&ray.direction, &normalv);
+
+    let inside = Tuple::dot_product(&normalv, &eyev) < 0.0;
+    if inside {
+        normalv = normalv * -1.0;
     }
-}
-
-pub fn prepare_computations_helper(intersection: &Intersection, ray: &Ray) -> Computation {
-    let mut comps = Computation::new();
-
-    comps.t = intersection.t;
-    comps.object = intersection.object.clone();
-    comps.point = ray.position(comps.t);
-    comps.eyev = ray.direction.clone() * -1.0;
-    comps.normalv = comps.object.normal_at(comps.point.clone());
-    comps.reflectv = reflect(&ray.direction.clone(), &comps.normalv);
 
-    if Tuple::dot_product(&comps.normalv, &comps.eyev) < 0.0 {
-        comps.inside = true;
-        comps.normalv = comps.normalv * -1.0;
-    } else {
-        comps.inside = false;
+    let over_point = point.clone() + normalv.clone() * SHADOW_EPSILON;
+    let under_point = point.clone() - normalv.clone() * SHADOW_EPSILON;
+
+    Computation {
+        t,
+        object,
+        point,
+        over_point,
+        under_point,
+        eyev,
+        normalv,
+        inside,
+        reflectv,
+        n1: 1.0,
+        n2: 1.0,
+        time: ray.time,
+        placement,
     }
-
-    comps.over_point = comps.point.clone() + comps.normalv.clone() * SHADOW_EPSILON;
-    comps.under_point = comps.point.clone() - comps.normalv.clone() * SHADOW_EPSILON;
-
-    comps
 }
 
-pub fn prepare_computations_v2(
-    intersection: &Intersection,
+pub fn prepare_computations_v2<'a>(
+    intersection: &Intersection<'a>,
     ray: &Ray,
-    intersection_list: Vec<Intersection>,
-) -> Computation {
+    intersection_list: &[Intersection<'a>],
+) -> Computation<'a> {
     let mut comps = prepare_computations_helper(intersection, ray);
-    let mut container: Vec<Object> = Vec::new();
+    (comps.n1, comps.n2) = refractive_indices(
+        intersection,
+        intersection_list.iter().copied(),
+        &mut Vec::new(),
+    );
+    comps
+}
 
-    for i in intersection_list {
+/// Refractive indices on both sides of the surface at `intersection`, from the objects the
+/// sorted `intersections` go in and out of before it. `container` is cleared and used to
+/// track those objects by their id and refractive index.
+fn refractive_indices<'a>(
+    intersection: &Intersection<'a>,
+    intersections: impl Iterator<Item = Intersection<'a>>,
+    container: &mut Vec<(Uuid, f64)>,
+) -> (f64, f64) {
+    let refractive_index =
+        |container: &Vec<(Uuid, f64)>| container.last().map_or(1.0, |(_, index)| *index);
+    container.clear();
+    let (mut n1, mut n2) = (1.0, 1.0);
+
+    for i in intersections {
         let is_hit = i == *intersection;
         if is_hit {
-            if container.is_empty() {
-                comps.n1 = 1.0;
-            } else {
-                comps.n1 = container.last().unwrap().get_material().refractive_index
-            }
+            n1 = refractive_index(container);
         }
-        let find_item = container.iter().position(|n| *n == i.object);
+        let find_item = container
+            .iter()
+            .position(|(id, _)| *id == i.object.get_id());
 
         match find_item {
             Some(x) => {
                 container.remove(x);
             }
-            None => container.push(i.object),
+            None => container.push((i.object.get_id(), i.object.material.refractive_index)),
         }
 
         if is_hit {
-            if container.is_empty() {
-                comps.n2 = 1.0;
-            } else {
-                comps.n2 = container.last().unwrap().get_material().refractive_index
-            }
-
+            n2 = refractive_index(container);
             break;
         }
     }
-    comps
+    (n1, n2)
 }
 
 #[cfg(test)]
 mod matrix_tests {
-    use crate::{reflection, transformation::create_translation};
+    use crate::{
+        reflection::{self, Attenuation},
+        transformation::create_translation,
+    };
 
     use super::*;
 
@@ -266,7 +584,7 @@ mod matrix_tests {
         s1.material.specular = 0.2;
 
         let mut s2 = Object::new_sphere();
-        s2.transform = transformation::create_scaling(0.5, 0.5, 0.5);
+        s2.set_transform(&transformation::create_scaling(0.5, 0.5, 0.5));
 
         assert_eq!(w.objects.len(), 2);
         assert_eq!(w.light_sources, vec![light]);
@@ -289,6 +607,31 @@ mod matrix_tests {
         assert_eq!(xs[3].t, 6.0);
     }
 
+    #[test]
+    ///The hit of a world is its closest intersection ahead of the ray
+    fn hit_world_test() {
+        let w = World::default_world();
+        let ray = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let mut xs = vec![];
+        w.intersect_world_into(&ray, &mut xs);
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(w.hit(&ray), Some(xs[0]));
+        assert_eq!(xs[0].t, 0.5);
+        assert!(*xs[0].object == w.objects[1]);
+
+        let miss = Ray::new(
+            Tuple::new_point(0.0, 5.0, 0.0),
+            Tuple::new_vector(0.0, 1.0, 0.0),
+        );
+        w.intersect_world_into(&miss, &mut xs);
+        assert!(xs.is_empty());
+        assert_eq!(w.hit(&miss), None);
+    }
+
     #[test]
     ///Precomputing the state of an intersection
     fn precomputing_test() {
@@ -296,10 +639,8 @@ mod matrix_tests {
             Tuple::new_point(0.0, 0.0, -5.0),
             Tuple::new_vector(0.0, 0.0, 1.0),
         );
-        let i = Intersection {
-            object: Object::new_sphere(),
-            t: 4.0,
-        };
+        let s = Object::new_sphere();
+        let i = Intersection { object: &s, t: 4.0 };
         let comps = prepare_computations_helper(&i, &ray);
 
         assert_eq!(comps.t, i.t);
@@ -316,13 +657,11 @@ mod matrix_tests {
             Tuple::new_point(0.0, 0.0, -5.0),
             Tuple::new_vector(0.0, 0.0, 1.0),
         );
-        let i = Intersection {
-            object: Object::new_sphere(),
-            t: 4.0,
-        };
+        let s = Object::new_sphere();
+        let i = Intersection { object: &s, t: 4.0 };
         let comps = prepare_computations_helper(&i, &ray);
 
-        assert_eq!(comps.inside, false);
+        assert!(!comps.inside);
     }
 
     #[test]
@@ -332,15 +671,13 @@ mod matrix_tests {
             Tuple::new_point(0.0, 0.0, 0.0),
             Tuple::new_vector(0.0, 0.0, 1.0),
         );
-        let i = Intersection {
-            object: Object::new_sphere(),
-            t: 4.0,
-        };
+        let s = Object::new_sphere();
+        let i = Intersection { object: &s, t: 4.0 };
         let comps = prepare_computations_helper(&i, &ray);
 
         assert_eq!(comps.eyev, Tuple::new_vector(0.0, 0.0, -1.0));
         assert_eq!(comps.normalv, Tuple::new_vector(0.0, 0.0, -1.0));
-        assert_eq!(comps.inside, true);
+        assert!(comps.inside);
     }
 
     #[test]
@@ -354,7 +691,7 @@ mod matrix_tests {
         );
         let shape = w.objects.first().unwrap().clone();
         let i = Intersection {
-            object: shape,
+            object: &shape,
             t: 4.0,
         };
         let comps = prepare_computations_helper(&i, &ray);
@@ -381,7 +718,7 @@ mod matrix_tests {
         );
         let shape = w.objects[1].clone();
         let i = Intersection {
-            object: shape,
+            object: &shape,
             t: 0.5,
         };
         let comps = prepare_computations_helper(&i, &ray);
@@ -449,7 +786,7 @@ mod matrix_tests {
         let w = World::default_world();
         let point = Tuple::new_point(0.0, 10.0, 0.0);
 
-        assert_eq!(w.is_shadowed_for_light(&point, &w.light_sources[0]), false);
+        assert!(!w.is_shadowed_for_light(&point, &w.light_sources[0]));
     }
 
     #[test]
@@ -458,7 +795,7 @@ mod matrix_tests {
         let w = World::default_world();
         let point = Tuple::new_point(10.0, -10.0, 10.0);
 
-        assert_eq!(w.is_shadowed_for_light(&point, &w.light_sources[0]), true);
+        assert!(w.is_shadowed_for_light(&point, &w.light_sources[0]));
     }
 
     #[test]
@@ -467,7 +804,7 @@ mod matrix_tests {
         let w = World::default_world();
         let point = Tuple::new_point(-20.0, 20.0, -20.0);
 
-        assert_eq!(w.is_shadowed_for_light(&point, &w.light_sources[0]), false);
+        assert!(!w.is_shadowed_for_light(&point, &w.light_sources[0]));
     }
 
     #[test]
@@ -476,7 +813,61 @@ mod matrix_tests {
         let w = World::default_world();
         let point = Tuple::new_point(-2.0, 2.0, -2.0);
 
-        assert_eq!(w.is_shadowed_for_light(&point, &w.light_sources[0]), false);
+        assert!(!w.is_shadowed_for_light(&point, &w.light_sources[0]));
+    }
+
+    #[test]
+    ///Any-hit queries only stop on the accepted objects closer than the distance
+    fn any_hit_test() {
+        let mut w = World::default_world();
+        let ray = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+
+        assert!(w.any_hit(&ray, 10.0, Object::blocks_light));
+        assert!(!w.any_hit(&ray, 3.9, Object::blocks_light));
+
+        w.objects[0].shadow = false;
+        w.objects[1].set_transparency(0.5);
+        assert!(!w.any_hit(&ray, 10.0, Object::blocks_light));
+        assert!(w.any_hit(&ray, 10.0, Object::has_shadow));
+    }
+
+    #[test]
+    ///A transparent object filters the light by its color instead of blocking it
+    fn shadow_transparent_test() {
+        let mut w = World::default_world();
+        for object in w.objects.iter_mut() {
+            object.set_transparency(0.5);
+            object.set_color(color::WHITE);
+        }
+        w.objects[0].set_color(Color::new_color(1.0, 0.5, 0.0));
+        let point = Tuple::new_point(10.0, -10.0, 10.0);
+
+        // two crossings of each sphere on the way to the light
+        assert_eq!(
+            w.light_visibility(&point, &w.light_sources[0]),
+            Color::new_color(0.0625, 0.015625, 0.0)
+        );
+        assert!(!w.is_shadowed_for_light(&point, &w.light_sources[0]));
+    }
+
+    #[test]
+    ///There is no shadow when objects are fully transparent
+    fn shadow_transparent_clear_test() {
+        let mut w = World::default_world();
+        for object in w.objects.iter_mut() {
+            object.set_transparency(1.0);
+            object.set_color(color::WHITE);
+        }
+        let point = Tuple::new_point(10.0, -10.0, 10.0);
+
+        assert_eq!(
+            w.light_visibility(&point, &w.light_sources[0]),
+            color::WHITE
+        );
+        assert!(!w.is_shadowed_for_light(&point, &w.light_sources[0]));
     }
 
     #[test]
@@ -500,7 +891,7 @@ mod matrix_tests {
         );
 
         let i = Intersection {
-            object: s2.clone(),
+            object: &s2,
             t: 4.0,
         };
         let comps = prepare_computations_helper(&i, &ray);
@@ -509,6 +900,99 @@ mod matrix_tests {
         assert_eq!(c, Color::new_color(0.1, 0.1, 0.1));
     }
 
+    #[test]
+    ///Ambient occlusion scales the ambient term of shade_hit()
+    fn shade_hit_ambient_occlusion_test() {
+        let mut room = Object::new_sphere();
+        room.material = Material::default_material()
+            .with_color(color::WHITE)
+            .with_ambient(1.0)
+            .with_diffuse(0.0)
+            .with_specular(0.0);
+        room.set_transform(&transformation::create_scaling(2.0, 2.0, 2.0));
+
+        let mut w = World::new_world();
+        w.add_object(room);
+        w.light_sources.push(PointLight::new_point_light(
+            color::WHITE,
+            Tuple::new_point(0.0, 0.0, 0.0),
+        ));
+
+        let r = Ray::new(
+            Tuple::new_point(0.0, 0.0, 0.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let xs = w.intersect_world(&r);
+        let comps = prepare_computations_v2(&xs[0], &r, &xs);
+        assert_eq!(w.shade_hit(&comps, 1), color::WHITE);
+
+        let occluded = w
+            .clone()
+            .with_ambient_occlusion(AmbientOcclusion::new_ambient_occlusion(8, 5.0));
+        assert_eq!(occluded.shade_hit(&comps, 1), color::BLACK);
+    }
+
+    #[test]
+    ///shade_hit() adds the emission of the material even without lights
+    fn shade_hit_emission_test() {
+        let w = World::new_world();
+        let mut shape = Object::new_sphere();
+        shape.set_emission(Color::new_color(0.2, 0.4, 0.6));
+
+        let ray = Ray::new(
+            Tuple::new_point(0.0, 0.0, -5.0),
+            Tuple::new_vector(0.0, 0.0, 1.0),
+        );
+        let i = Intersection {
+            object: &shape,
+            t: 4.0,
+        };
+        let comps = prepare_computations_helper(&i, &ray);
+        let c = w.shade_hit(&comps, reflection::MAX_RECURTION);
+
+        assert_eq!(c, Color::new_color(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    ///A tiny emissive quad lights the scene like a point light fading with the square of the
+    ///distance, its intensity being the emission times the area over π
+    fn emissive_light_test() {
+        let mut w = World::new_world();
+        let mut floor = Object::new_plane();
+        floor.material.ambient = 0.0;
+        w.add_object(floor);
+
+        let emission = Color::new_color(1.0, 0.5, 0.25) * (PI / 0.0004);
+        let mut lamp = Object::new_quad();
+        lamp.set_emission(emission);
+        lamp.set_transform(
+            &transformation::create_scaling(0.01, 1.0, 0.01).translation(0.0, 5.0, 0.0),
+        );
+        w.add_emissive_light(vec![lamp], 4);
+        assert_eq!(w.emissive_lights[0].objects, vec![1]);
+
+        let ray = Ray::new(
+            Tuple::new_point(0.0, 1.0, -1.0),
+            Tuple::new_vector(0.0, -2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0),
+        );
+        let i = Intersection::new(2.0_f64.sqrt(), &w.objects[0]);
+        let comps = prepare_computations_helper(&i, &ray);
+        let lit_by_lamp = w.shade_hit(&comps, reflection::MAX_RECURTION);
+
+        let mut reference = World::new_world();
+        reference.add_object(Object::new_plane());
+        reference.light_sources = vec![
+            PointLight::new_point_light(
+                Color::new_color(1.0, 0.5, 0.25),
+                Tuple::new_point(0.0, 5.0, 0.0),
+            )
+            .with_attenuation(Attenuation::InverseSquare),
+        ];
+        let lit_by_point = reference.shade_hit(&comps, reflection::MAX_RECURTION);
+
+        assert_eq!(lit_by_lamp, lit_by_point);
+    }
+
     #[test]
     ///The hit should offset the point
     fn precomputing_epsilon_test() {
@@ -518,7 +1002,10 @@ mod matrix_tests {
         );
         let mut s1 = Object::new_sphere();
         s1.set_transform(&create_translation(0.0, 0.0, 1.0));
-        let i = Intersection { object: s1, t: 5.0 };
+        let i = Intersection {
+            object: &s1,
+            t: 5.0,
+        };
         let comps = prepare_computations_helper(&i, &ray);
 
         assert_eq!(comps.over_point.z, -SHADOW_EPSILON);